@@ -2,7 +2,27 @@ use std::io::Write;
 
 use math::*;
 
+fn parse_number_format(format: &str) -> Option<NumberFormat> {
+    match format.split_whitespace().collect::<Vec<_>>()[..] {
+        ["fraction"] => Some(NumberFormat::Fraction),
+        ["mixed"] => Some(NumberFormat::MixedNumber),
+        ["repeating"] => Some(NumberFormat::RepeatingDecimal),
+        ["decimal"] => Some(NumberFormat::DecimalUpTo(10)),
+        ["decimal", places] => places.parse().ok().map(NumberFormat::DecimalUpTo),
+        _ => None,
+    }
+}
+
 fn main() {
+    let mut diagnostic_style = DiagnosticStyle::Human;
+    let mut definitions = Definitions::new();
+    let mut display_options = DisplayOptions {
+        number_format: NumberFormat::Fraction,
+    };
+    // the last result as LaTeX, printed by `:latex`
+    let mut last_latex = None;
+    // the steps of the last simplified equation, printed by `:steps`
+    let mut last_steps: Option<Vec<Step>> = None;
     'main_loop: loop {
         print!("> ");
         std::io::stdout().flush().unwrap();
@@ -11,26 +31,160 @@ fn main() {
             .next()
             .unwrap()
             .expect("read a line from the console");
-        if line.len() > 0 && line.chars().nth(0).unwrap() == ':' {
+        if line.starts_with(':') {
             match &line as &str {
                 ":exit" => break 'main_loop,
-                _ => println!("Unknown command '{line}'"),
+                ":diagnostics human" => diagnostic_style = DiagnosticStyle::Human,
+                ":diagnostics machine" => diagnostic_style = DiagnosticStyle::Machine,
+                ":latex" => match &last_latex {
+                    Some(latex) => println!("{latex}"),
+                    None => println!("There is no result to print"),
+                },
+                ":steps" => match &last_steps {
+                    Some(steps) if !steps.is_empty() => {
+                        println!("{}", steps[0].before.with_options(display_options));
+                        for step in steps {
+                            println!(
+                                "  {}: {}",
+                                step.description,
+                                step.after.with_options(display_options)
+                            );
+                        }
+                    }
+                    Some(_) => println!("The equation was already simplified"),
+                    None => println!("There are no steps to print"),
+                },
+                ":defs" => {
+                    for definition in definitions.definitions() {
+                        println!("{}", definition.with_options(display_options));
+                    }
+                }
+                _ => {
+                    if let Some(format) = line.strip_prefix(":format ") {
+                        match parse_number_format(format) {
+                            Some(number_format) => display_options.number_format = number_format,
+                            None => println!(
+                                "Unknown format '{}', expected fraction, mixed, repeating or decimal [places]",
+                                format.trim()
+                            ),
+                        }
+                    } else if let Some(name) = line.strip_prefix(":undef ") {
+                        match definitions.undefine(name.trim()) {
+                            Some(definition) => {
+                                println!("Removed {}", definition.with_options(display_options))
+                            }
+                            None => println!("'{}' is not defined", name.trim()),
+                        }
+                    } else {
+                        println!("Unknown command '{line}'");
+                    }
+                }
             }
-        } else {
+        } else if line.contains(":=") {
             let mut lexer = Lexer::new("stdin", &line);
-            match parse_equation(&mut lexer) {
-                Ok(equation) => {
-                    println!("{equation}");
-                    let equation = simplify(&equation);
-                    let (left, right) = equation.as_equality().unwrap();
-                    if !left.contains_variable() && !right.contains_variable() {
-                        println!("{}", eval_expression(left) == eval_expression(right));
-                    } else {
-                        println!("{equation}");
+            match parse_definition_with(&mut lexer, &definitions) {
+                Ok(definition) => match definitions.define(definition.clone()) {
+                    Ok(()) => println!("{}", definition.with_options(display_options)),
+                    Err(error) => println!("{error}"),
+                },
+                Err(error) => {
+                    print!(
+                        "{}",
+                        Diagnostic::from(&error).render(&line, diagnostic_style)
+                    );
+                }
+            }
+        } else if !line.contains('=') {
+            let mut lexer = Lexer::new("stdin", &line);
+            let expression = match parse_expression_with(&mut lexer, &definitions) {
+                Ok(expression) => expression,
+                Err(error) => {
+                    print!(
+                        "{}",
+                        Diagnostic::from(&error).render(&line, diagnostic_style)
+                    );
+                    continue;
+                }
+            };
+            let expression = match definitions.expand_expression(&expression) {
+                Ok(expression) => expression,
+                Err(error) => {
+                    println!("{error}");
+                    continue;
+                }
+            };
+            last_steps = None;
+            match simplify_expression_fully(&expression) {
+                Ok(simplified) if !simplified.contains_variable() => {
+                    match eval_expression(&simplified) {
+                        // approximations like `sqrt(2)` are printed as decimals
+                        Ok(value) if simplified.is_exact() => {
+                            println!("{}", format_number(&value, display_options.number_format));
+                            last_latex = Some(Atom::new(AtomKind::Number(value)).to_latex());
+                        }
+                        Ok(value) => {
+                            let places = match display_options.number_format {
+                                NumberFormat::DecimalUpTo(places) => places,
+                                _ => 10,
+                            };
+                            let value = format_number(&value, NumberFormat::DecimalUpTo(places));
+                            println!("{value}");
+                            last_latex = Some(value);
+                        }
+                        Err(error) => println!("{error}"),
                     }
                 }
+                Ok(simplified) => {
+                    println!("{}", simplified.with_options(display_options));
+                    last_latex = Some(simplified.to_latex());
+                }
+                Err(error) => println!("{error}"),
+            }
+        } else {
+            let mut lexer = Lexer::new("stdin", &line);
+            let system = match parse_system_with(&mut lexer, &definitions) {
+                Ok(system) => system,
+                Err(error) => {
+                    print!(
+                        "{}",
+                        Diagnostic::from(&error).render(&line, diagnostic_style)
+                    );
+                    continue;
+                }
+            };
+            println!("{}", system.with_options(display_options));
+            let system = match definitions.expand_system(&system) {
+                Ok(system) => system,
                 Err(error) => {
-                    println!("{}", error);
+                    println!("{error}");
+                    continue;
+                }
+            };
+            if let [equation] = &system.equations[..] {
+                match simplify_with_trace(equation) {
+                    Ok((equation, steps)) => {
+                        last_steps = Some(steps);
+                        let (left, right, _) = equation.as_equality().unwrap();
+                        if !left.contains_variable() && !right.contains_variable() {
+                            match (eval_expression(left), eval_expression(right)) {
+                                (Ok(left), Ok(right)) => println!("{}", left == right),
+                                (Err(error), _) | (_, Err(error)) => println!("{error}"),
+                            }
+                        } else {
+                            println!("{}", equation.with_options(display_options));
+                            last_latex = Some(equation.to_latex());
+                        }
+                    }
+                    Err(error) => println!("{error}"),
+                }
+            } else {
+                last_steps = None;
+                match solve_system(&system) {
+                    Ok(solution) => {
+                        println!("{}", solution.with_options(display_options));
+                        last_latex = Some(solution.to_latex());
+                    }
+                    Err(error) => println!("{error}"),
                 }
             }
         }
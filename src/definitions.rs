@@ -0,0 +1,331 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+use derive_more::{Display, IsVariant};
+use enum_as_inner::EnumAsInner;
+
+use crate::{
+    builtin_function, span_prefix, Arity, Atom, AtomKind, DisplayOptions, DisplayWith, Equation,
+    Expression, SourceSpan, System, Term,
+};
+
+/// A name defined with `:=`, like `f(x) := 3x^2 + 1` or `g := 9.81`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Definition {
+    pub name: String,
+    /// `None` for constants, which are used without parentheses
+    pub parameters: Option<Vec<String>>,
+    pub body: Expression,
+    pub span: Option<SourceSpan>,
+}
+
+impl DisplayWith for Definition {
+    fn fmt_with(&self, f: &mut std::fmt::Formatter, options: &DisplayOptions) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(parameters) = &self.parameters {
+            write!(f, "({})", parameters.join(", "))?;
+        }
+        write!(f, " := ")?;
+        self.body.fmt_with(f, options)
+    }
+}
+
+impl Display for Definition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.fmt_with(f, &DisplayOptions::default())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Display, IsVariant, EnumAsInner)]
+pub enum DefinitionError {
+    #[display(
+        fmt = "{}'{name}' is a built-in function and cannot be redefined",
+        "span_prefix(span)"
+    )]
+    BuiltinRedefinition {
+        name: String,
+        span: Option<SourceSpan>,
+    },
+    #[display(fmt = "{}'{name}' is defined in terms of itself", "span_prefix(span)")]
+    RecursiveDefinition {
+        name: String,
+        span: Option<SourceSpan>,
+    },
+    #[display(
+        fmt = "{}Function '{name}' takes {expected}, but got {got}",
+        "span_prefix(span)"
+    )]
+    WrongArgumentCount {
+        name: String,
+        expected: Arity,
+        got: usize,
+        span: Option<SourceSpan>,
+    },
+}
+
+/// Replaces variables with expressions all at once, so a value is never substituted into again.
+/// This is what keeps `f(y, 1)` with `f(x, y) := x + y` from becoming `1 + 1` instead of `y + 1`
+pub fn substitute(expression: &Expression, values: &HashMap<String, Expression>) -> Expression {
+    Expression {
+        terms: expression
+            .terms
+            .iter()
+            .map(|term| Term {
+                atoms: term
+                    .atoms
+                    .iter()
+                    .map(|atom| substitute_atom(atom, values))
+                    .collect(),
+                span: term.span.clone(),
+            })
+            .collect(),
+        span: expression.span.clone(),
+    }
+}
+
+fn substitute_atom(atom: &Atom, values: &HashMap<String, Expression>) -> Atom {
+    let kind = match &atom.kind {
+        AtomKind::Number(_) => return atom.clone(),
+        AtomKind::Variable(name) => match values.get(name) {
+            Some(value) => return group(value.clone(), atom.span.clone()),
+            None => return atom.clone(),
+        },
+        AtomKind::Group(expression) => AtomKind::Group(substitute(expression, values)),
+        AtomKind::Fraction {
+            numerator,
+            denominator,
+        } => AtomKind::Fraction {
+            numerator: substitute(numerator, values),
+            denominator: substitute(denominator, values),
+        },
+        AtomKind::Power { base, exponent } => AtomKind::Power {
+            base: Box::new(substitute_atom(base, values)),
+            exponent: Box::new(substitute_atom(exponent, values)),
+        },
+        AtomKind::Call { name, args } => AtomKind::Call {
+            name: name.clone(),
+            args: args
+                .iter()
+                .map(|argument| substitute(argument, values))
+                .collect(),
+        },
+    };
+    Atom {
+        kind,
+        span: atom.span.clone(),
+    }
+}
+
+/// Wraps an expanded definition so it is used as a single atom, with the span of where it was used
+fn group(expression: Expression, span: Option<SourceSpan>) -> Atom {
+    Atom {
+        kind: AtomKind::Group(expression),
+        span,
+    }
+}
+
+/// The definitions of a session, which are expanded in everything that uses them
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Definitions {
+    definitions: BTreeMap<String, Definition>,
+}
+
+impl Definitions {
+    pub fn new() -> Definitions {
+        Definitions::default()
+    }
+
+    /// Adds or replaces a definition, unless it would be recursive
+    pub fn define(&mut self, definition: Definition) -> Result<(), DefinitionError> {
+        if builtin_function(&definition.name).is_some() {
+            return Err(DefinitionError::BuiltinRedefinition {
+                name: definition.name,
+                span: definition.span,
+            });
+        }
+        let mut definitions = self.clone();
+        definitions.declare(definition.clone());
+        definitions.expand_expression_in(
+            &definition.body,
+            definition.parameters.as_deref().unwrap_or_default(),
+            &mut vec![definition.name.clone()],
+        )?;
+        *self = definitions;
+        Ok(())
+    }
+
+    /// Adds a definition without checking it, used while parsing the body of the definition itself
+    pub(crate) fn declare(&mut self, definition: Definition) {
+        self.definitions.insert(definition.name.clone(), definition);
+    }
+
+    pub fn undefine(&mut self, name: &str) -> Option<Definition> {
+        self.definitions.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Definition> {
+        self.definitions.get(name)
+    }
+
+    /// The definitions ordered by name
+    pub fn definitions(&self) -> impl Iterator<Item = &Definition> {
+        self.definitions.values()
+    }
+
+    /// The arity of a user-defined function, `None` for constants and unknown names
+    pub fn function_arity(&self, name: &str) -> Option<Arity> {
+        self.definitions
+            .get(name)?
+            .parameters
+            .as_ref()
+            .map(|parameters| Arity::Exactly(parameters.len()))
+    }
+
+    /// `bound` are the parameters of the function being expanded, which hide definitions with the same name
+    fn expand_atom_in(
+        &self,
+        atom: &Atom,
+        bound: &[String],
+        expanding: &mut Vec<String>,
+    ) -> Result<Atom, DefinitionError> {
+        let kind = match &atom.kind {
+            AtomKind::Number(_) => return Ok(atom.clone()),
+            AtomKind::Variable(name) => {
+                let definition = match self.definitions.get(name) {
+                    Some(definition)
+                        if definition.parameters.is_none() && !bound.contains(name) =>
+                    {
+                        definition
+                    }
+                    _ => return Ok(atom.clone()),
+                };
+                let body = self.expand_definition_in(definition, atom, &[], expanding)?;
+                return Ok(group(body, atom.span.clone()));
+            }
+            AtomKind::Group(expression) => {
+                AtomKind::Group(self.expand_expression_in(expression, bound, expanding)?)
+            }
+            AtomKind::Fraction {
+                numerator,
+                denominator,
+            } => AtomKind::Fraction {
+                numerator: self.expand_expression_in(numerator, bound, expanding)?,
+                denominator: self.expand_expression_in(denominator, bound, expanding)?,
+            },
+            AtomKind::Power { base, exponent } => AtomKind::Power {
+                base: Box::new(self.expand_atom_in(base, bound, expanding)?),
+                exponent: Box::new(self.expand_atom_in(exponent, bound, expanding)?),
+            },
+            AtomKind::Call { name, args } => {
+                let args = args
+                    .iter()
+                    .map(|argument| self.expand_expression_in(argument, bound, expanding))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match self.definitions.get(name) {
+                    Some(definition) if definition.parameters.is_some() => {
+                        let body = self.expand_definition_in(definition, atom, &args, expanding)?;
+                        return Ok(group(body, atom.span.clone()));
+                    }
+                    _ => AtomKind::Call {
+                        name: name.clone(),
+                        args,
+                    },
+                }
+            }
+        };
+        Ok(Atom {
+            kind,
+            span: atom.span.clone(),
+        })
+    }
+
+    /// Expands the body of a definition used at `atom` and substitutes the arguments for its parameters
+    fn expand_definition_in(
+        &self,
+        definition: &Definition,
+        atom: &Atom,
+        args: &[Expression],
+        expanding: &mut Vec<String>,
+    ) -> Result<Expression, DefinitionError> {
+        let parameters = definition.parameters.as_deref().unwrap_or_default();
+        if parameters.len() != args.len() {
+            return Err(DefinitionError::WrongArgumentCount {
+                name: definition.name.clone(),
+                expected: Arity::Exactly(parameters.len()),
+                got: args.len(),
+                span: atom.span.clone(),
+            });
+        }
+        if expanding.contains(&definition.name) {
+            return Err(DefinitionError::RecursiveDefinition {
+                name: definition.name.clone(),
+                span: atom.span.clone(),
+            });
+        }
+        expanding.push(definition.name.clone());
+        let body = self.expand_expression_in(&definition.body, parameters, expanding)?;
+        expanding.pop();
+        Ok(substitute(
+            &body,
+            &parameters
+                .iter()
+                .cloned()
+                .zip(args.iter().cloned())
+                .collect(),
+        ))
+    }
+
+    fn expand_expression_in(
+        &self,
+        expression: &Expression,
+        bound: &[String],
+        expanding: &mut Vec<String>,
+    ) -> Result<Expression, DefinitionError> {
+        Ok(Expression {
+            terms: expression
+                .terms
+                .iter()
+                .map(|term| {
+                    Ok(Term {
+                        atoms: term
+                            .atoms
+                            .iter()
+                            .map(|atom| self.expand_atom_in(atom, bound, expanding))
+                            .collect::<Result<_, _>>()?,
+                        span: term.span.clone(),
+                    })
+                })
+                .collect::<Result<_, DefinitionError>>()?,
+            span: expression.span.clone(),
+        })
+    }
+
+    pub fn expand_expression(
+        &self,
+        expression: &Expression,
+    ) -> Result<Expression, DefinitionError> {
+        self.expand_expression_in(expression, &[], &mut vec![])
+    }
+
+    pub fn expand_equation(&self, equation: &Equation) -> Result<Equation, DefinitionError> {
+        match equation {
+            Equation::Equality { left, right, span } => Ok(Equation::Equality {
+                left: self.expand_expression(left)?,
+                right: self.expand_expression(right)?,
+                span: span.clone(),
+            }),
+        }
+    }
+
+    pub fn expand_system(&self, system: &System) -> Result<System, DefinitionError> {
+        Ok(System {
+            equations: system
+                .equations
+                .iter()
+                .map(|equation| self.expand_equation(equation))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
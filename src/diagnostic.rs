@@ -0,0 +1,186 @@
+use std::fmt::Write;
+
+use crate::{builtin_functions, LexerError, ParsingError, SourceSpan, TokenKind};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DiagnosticStyle {
+    /// The offending lines of the source with `^^^` under the span, like rustc
+    #[default]
+    Human,
+    /// One line per message in the form `{filepath}:{start}-{end}: {level}: {message}`
+    Machine,
+}
+
+/// An error message that points at the part of the source that caused it
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: SourceSpan,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, span: SourceSpan) -> Diagnostic {
+        Diagnostic {
+            message: message.into(),
+            span,
+            notes: vec![],
+        }
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Renders the diagnostic, `source` must be the text the span was created from
+    pub fn render(&self, source: &str, style: DiagnosticStyle) -> String {
+        match style {
+            DiagnosticStyle::Human => self.render_human(source),
+            DiagnosticStyle::Machine => self.render_machine(),
+        }
+    }
+
+    fn render_human(&self, source: &str) -> String {
+        let SourceSpan { start, end, .. } = &self.span;
+        let lines = source.split('\n').collect::<Vec<_>>();
+        let gutter = " ".repeat(end.line.to_string().len());
+
+        let mut result = String::new();
+        writeln!(result, "error: {}", self.message).unwrap();
+        writeln!(result, "{gutter}--> {}", self.span).unwrap();
+        writeln!(result, "{gutter} |").unwrap();
+        for line_number in start.line..=end.line {
+            let line = lines
+                .get(line_number - 1)
+                .copied()
+                .unwrap_or("")
+                .trim_end_matches('\r');
+            let length = line.chars().count();
+            let first = if line_number == start.line {
+                start.column
+            } else {
+                1
+            };
+            let last = if line_number == end.line {
+                end.column
+            } else {
+                length + 1
+            };
+            // tabs are kept so the carets line up with the text above them
+            let padding = line
+                .chars()
+                .take(first - 1)
+                .map(|chr| if chr == '\t' { '\t' } else { ' ' })
+                .collect::<String>();
+            // an empty span, like the one of EOF, still gets a single caret
+            let carets = "^".repeat(last.saturating_sub(first).max(1));
+            writeln!(
+                result,
+                "{line_number:>width$} | {line}",
+                width = gutter.len()
+            )
+            .unwrap();
+            writeln!(result, "{gutter} | {padding}{carets}").unwrap();
+        }
+        for note in &self.notes {
+            writeln!(result, "{gutter} = note: {note}").unwrap();
+        }
+        result
+    }
+
+    fn render_machine(&self) -> String {
+        let SourceSpan {
+            filepath,
+            start,
+            end,
+        } = &self.span;
+        let mut result = String::new();
+        writeln!(result, "{filepath}:{start}-{end}: error: {}", self.message).unwrap();
+        for note in &self.notes {
+            writeln!(result, "{filepath}:{start}-{end}: note: {note}").unwrap();
+        }
+        result
+    }
+}
+
+impl From<&LexerError> for Diagnostic {
+    fn from(error: &LexerError) -> Diagnostic {
+        match error {
+            LexerError::UnexpectedCharacter { span, character } => {
+                Diagnostic::new(format!("Unexpected character '{character}'"), span.clone())
+            }
+            LexerError::UnknownCommand { span, command } => {
+                Diagnostic::new(format!("Unknown LaTeX command '\\{command}'"), span.clone())
+                    .with_note(
+                        "supported are \\frac, \\sqrt, \\cdot, \\sin and such, and Greek letters",
+                    )
+            }
+            LexerError::MissingArgument { span, after } => Diagnostic::new(
+                format!("Expected an argument after '{after}'"),
+                span.clone(),
+            )
+            .with_note("an argument is a character, a command or a group like `{x + 1}`"),
+            LexerError::UnclosedBrace { span } => Diagnostic::new("Unclosed '{'", span.clone())
+                .with_note("did you forget a closing '}'?"),
+        }
+    }
+}
+
+impl From<&ParsingError> for Diagnostic {
+    fn from(error: &ParsingError) -> Diagnostic {
+        match error {
+            ParsingError::LexerError(error) => error.into(),
+            ParsingError::ExpectedToken { expected, got } => {
+                let diagnostic = Diagnostic::new(
+                    format!("Expected {expected}, but got {got}"),
+                    got.span.clone(),
+                );
+                match expected {
+                    TokenKind::CloseParenthesis => {
+                        diagnostic.with_note("did you forget a closing ')'?")
+                    }
+                    TokenKind::Equal => {
+                        diagnostic.with_note("an equation needs an '=' between its two sides")
+                    }
+                    TokenKind::Define => diagnostic
+                        .with_note("a definition looks like `f(x) := 3x^2 + 1` or `g := 9.81`"),
+                    TokenKind::Comma => diagnostic
+                        .with_note("equations of a system are separated by ',' or new lines"),
+                    _ => diagnostic,
+                }
+            }
+            ParsingError::ExpectedAtom { got } => {
+                let diagnostic =
+                    Diagnostic::new(format!("Expected atom, but got {got}"), got.span.clone());
+                if got.kind == TokenKind::EOF {
+                    diagnostic.with_note("the input ended in the middle of an expression")
+                } else {
+                    diagnostic
+                        .with_note("an atom is a number, a name or a parenthesized expression")
+                }
+            }
+            ParsingError::UnknownFunction { name, span } => {
+                let mut names = builtin_functions()
+                    .map(|function| function.name)
+                    .collect::<Vec<_>>();
+                names.sort();
+                Diagnostic::new(format!("Unknown function '{name}'"), span.clone())
+                    .with_note(format!("the known functions are {}", names.join(", ")))
+            }
+            ParsingError::WrongArgumentCount {
+                name,
+                expected,
+                got,
+                span,
+            } => Diagnostic::new(
+                format!("Function '{name}' takes {expected}, but got {got}"),
+                span.clone(),
+            ),
+            ParsingError::DuplicateParameter { name, span } => Diagnostic::new(
+                format!("Parameter '{name}' is declared more than once"),
+                span.clone(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    parenthesize, print_atom, print_expression, print_term, Atom, Expression, Precedence, Printed,
+    Syntax, Term,
+};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NumberFormat {
+    /// `7/3`
+    Fraction,
+    /// `2 1/3`
+    MixedNumber,
+    /// `2.(3)`, exact with the repeating digits in parentheses
+    RepeatingDecimal,
+    /// Rounded to at most the given number of decimal places, trailing zeros are left out,
+    /// so with 3 places `2` is `2` and `1/2` is `0.5` instead of `2.000` and `0.500`
+    DecimalUpTo(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DisplayOptions {
+    pub number_format: NumberFormat,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> DisplayOptions {
+        DisplayOptions {
+            number_format: NumberFormat::DecimalUpTo(10),
+        }
+    }
+}
+
+/// Repeating decimals with a longer period are cut off with `...`
+const MAX_REPEATING_DIGITS: usize = 100;
+
+fn decimal(value: &BigRational, places: usize) -> String {
+    let scale = BigInt::from(10).pow(places as u32);
+    let scaled = (value.abs() * BigRational::from_integer(scale.clone()))
+        .round()
+        .to_integer();
+    let (integer, fraction) = scaled.div_rem(&scale);
+    // the sign is checked on the rounded value, so `-0.0000000000001` is `0` and not `-0`
+    let sign = if value.is_negative() && !scaled.is_zero() {
+        "-"
+    } else {
+        ""
+    };
+    let fraction = format!("{fraction:0>places$}");
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{sign}{integer}")
+    } else {
+        format!("{sign}{integer}.{fraction}")
+    }
+}
+
+fn repeating_decimal(value: &BigRational) -> String {
+    let sign = if value.is_negative() { "-" } else { "" };
+    let denominator = value.denom().clone();
+    let (integer, mut remainder) = value.numer().abs().div_rem(&denominator);
+    let mut digits = String::new();
+    // where the digits for each remainder started, a remainder that comes back starts the repetition
+    let mut positions = HashMap::new();
+    while !remainder.is_zero() {
+        if let Some(&start) = positions.get(&remainder) {
+            return format!("{sign}{integer}.{}({})", &digits[..start], &digits[start..]);
+        }
+        if digits.len() == MAX_REPEATING_DIGITS {
+            return format!("{sign}{integer}.{digits}...");
+        }
+        positions.insert(remainder.clone(), digits.len());
+        let (digit, next) = (remainder * BigInt::from(10)).div_rem(&denominator);
+        digits.push_str(&digit.to_string());
+        remainder = next;
+    }
+    if digits.is_empty() {
+        format!("{sign}{integer}")
+    } else {
+        format!("{sign}{integer}.{digits}")
+    }
+}
+
+fn mixed_number(value: &BigRational) -> String {
+    let whole = value.trunc();
+    let fraction = (value - &whole).abs();
+    if whole.is_zero() || fraction.is_zero() {
+        value.to_string()
+    } else {
+        format!("{} {}", whole, fraction)
+    }
+}
+
+pub fn format_number(value: &BigRational, format: NumberFormat) -> String {
+    match format {
+        NumberFormat::Fraction => value.to_string(),
+        NumberFormat::MixedNumber => mixed_number(value),
+        NumberFormat::RepeatingDecimal => repeating_decimal(value),
+        NumberFormat::DecimalUpTo(places) => decimal(value, places),
+    }
+}
+
+/// Formatting that depends on `DisplayOptions`, the `Display` impls use the default options
+pub trait DisplayWith {
+    fn fmt_with(&self, f: &mut std::fmt::Formatter, options: &DisplayOptions) -> std::fmt::Result;
+
+    fn with_options(&self, options: DisplayOptions) -> WithOptions<'_, Self> {
+        WithOptions {
+            value: self,
+            options,
+        }
+    }
+}
+
+pub struct WithOptions<'a, T: ?Sized> {
+    value: &'a T,
+    options: DisplayOptions,
+}
+
+impl<T: DisplayWith + ?Sized> Display for WithOptions<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.value.fmt_with(f, &self.options)
+    }
+}
+
+/// Plain text like `2x^2 - 1/2`, which reads back as the same expression
+struct PlainText<'a> {
+    options: &'a DisplayOptions,
+}
+
+impl Syntax for PlainText<'_> {
+    fn parentheses(&self, inner: String) -> String {
+        format!("({inner})")
+    }
+
+    fn number(&self, value: &BigRational) -> Printed {
+        let string = format_number(value, self.options.number_format);
+        let precedence = if string.contains(['/', ' ']) {
+            Precedence::Product
+        } else if string.starts_with('-') {
+            Precedence::Unary
+        } else {
+            Precedence::Atom
+        };
+        (string, precedence)
+    }
+
+    fn variable(&self, name: &str) -> String {
+        name.to_string()
+    }
+
+    fn fraction(&self, numerator: &Expression, denominator: &Expression) -> Printed {
+        (
+            format!(
+                "{}/{}",
+                parenthesize(self, print_expression(self, numerator), Precedence::Product).0,
+                parenthesize(self, print_expression(self, denominator), Precedence::Unary).0,
+            ),
+            Precedence::Product,
+        )
+    }
+
+    // exponents are right associative, so only the base needs parentheses for another power
+    fn power(&self, base: &Atom, exponent: &Atom) -> Printed {
+        (
+            format!(
+                "{}^{}",
+                parenthesize(self, print_atom(self, base), Precedence::Atom).0,
+                parenthesize(self, print_atom(self, exponent), Precedence::Unary).0,
+            ),
+            Precedence::Power,
+        )
+    }
+
+    fn call(&self, name: &str, args: &[Expression]) -> String {
+        format!(
+            "{name}({})",
+            args.iter()
+                .map(|argument| print_expression(self, argument).0)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+
+    fn product(&self, coefficient: &BigRational, factors: Vec<Printed>) -> Printed {
+        // `*` is left associative, but `x*(a/b)` and `x*a/b` have the same value, so products need no parentheses
+        let factors = factors
+            .into_iter()
+            .map(|factor| parenthesize(self, factor, Precedence::Product))
+            .collect::<Vec<_>>();
+        let product = factors
+            .iter()
+            .map(|(factor, _)| factor.as_str())
+            .collect::<Vec<_>>()
+            .join("*");
+        match &factors[..] {
+            _ if coefficient.is_one() => (product, Precedence::Product),
+            [(_, precedence)] if *coefficient == -BigRational::one() => {
+                (format!("-{product}"), (*precedence).min(Precedence::Unary))
+            }
+            _ if *coefficient == -BigRational::one() => {
+                (format!("-{product}"), Precedence::Product)
+            }
+            _ => {
+                let (number, number_precedence) = self.number(coefficient);
+                // a mixed number like `1 2/3` is put in parentheses so the space does not look like a product
+                let number = if number.contains(' ') {
+                    format!("({number})")
+                } else {
+                    number
+                };
+                // juxtaposition is only used where it is read back as multiplication, `2x` and `2(x + 1)` but not `2 3^x`
+                let separator = if number_precedence != Precedence::Product
+                    && product.starts_with(|chr: char| chr.is_alphabetic() || chr == '(')
+                {
+                    ""
+                } else {
+                    "*"
+                };
+                (format!("{number}{separator}{product}"), Precedence::Product)
+            }
+        }
+    }
+
+    fn operator(&self, operator: &str) -> String {
+        format!(" {operator} ")
+    }
+
+    fn row(&self, pieces: Vec<String>) -> String {
+        pieces.concat()
+    }
+}
+
+pub(crate) fn print_atom_with(atom: &Atom, options: &DisplayOptions) -> String {
+    print_atom(&PlainText { options }, atom).0
+}
+
+pub(crate) fn print_term_with(term: &Term, options: &DisplayOptions) -> String {
+    print_term(&PlainText { options }, term).0
+}
+
+pub(crate) fn print_expression_with(expression: &Expression, options: &DisplayOptions) -> String {
+    print_expression(&PlainText { options }, expression).0
+}
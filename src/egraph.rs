@@ -0,0 +1,652 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, Zero};
+
+use crate::{
+    algebra_rule_set, exact_power, wildcard, Atom, AtomKind, Expression, Rule, RuleSet, Term,
+};
+
+/// An e-class of an `EGraph`, all the nodes in it are equal to each other
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Id(usize);
+
+/// A node of an e-graph, its children are e-classes
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ENode {
+    Number(BigRational),
+    Variable(String),
+    Sum([Id; 2]),
+    Product([Id; 2]),
+    Fraction([Id; 2]),
+    Power([Id; 2]),
+    Call { name: String, args: Vec<Id> },
+}
+
+impl ENode {
+    pub fn children(&self) -> &[Id] {
+        match self {
+            ENode::Number(_) | ENode::Variable(_) => &[],
+            ENode::Sum(children)
+            | ENode::Product(children)
+            | ENode::Fraction(children)
+            | ENode::Power(children) => children,
+            ENode::Call { args, .. } => args,
+        }
+    }
+
+    fn map_children(&self, mut f: impl FnMut(Id) -> Id) -> ENode {
+        match self {
+            ENode::Number(_) | ENode::Variable(_) => self.clone(),
+            ENode::Sum([a, b]) => ENode::Sum([f(*a), f(*b)]),
+            ENode::Product([a, b]) => ENode::Product([f(*a), f(*b)]),
+            ENode::Fraction([a, b]) => ENode::Fraction([f(*a), f(*b)]),
+            ENode::Power([a, b]) => ENode::Power([f(*a), f(*b)]),
+            ENode::Call { name, args } => ENode::Call {
+                name: name.clone(),
+                args: args.iter().map(|id| f(*id)).collect(),
+            },
+        }
+    }
+}
+
+/// The cost of an expression, the cheapest of the equal ones is picked
+pub trait CostFunction {
+    type Cost: Ord + Clone;
+
+    fn cost(&self, node: &ENode, children: &[Self::Cost]) -> Self::Cost;
+}
+
+/// The expression with the fewest numbers, variables and operations
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct NodeCount;
+
+impl CostFunction for NodeCount {
+    type Cost = usize;
+
+    fn cost(&self, _: &ENode, children: &[usize]) -> usize {
+        1 + children.iter().sum::<usize>()
+    }
+}
+
+/// The least nested expression, and of those the one with the fewest nodes
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Depth;
+
+impl CostFunction for Depth {
+    type Cost = (usize, usize);
+
+    fn cost(&self, _: &ENode, children: &[(usize, usize)]) -> (usize, usize) {
+        (
+            1 + children.iter().map(|(depth, _)| *depth).max().unwrap_or(0),
+            1 + children.iter().map(|(_, nodes)| nodes).sum::<usize>(),
+        )
+    }
+}
+
+/// The expression with the fewest fractions, and of those the one with the fewest nodes
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct FewestFractions;
+
+impl CostFunction for FewestFractions {
+    type Cost = (usize, usize);
+
+    fn cost(&self, node: &ENode, children: &[(usize, usize)]) -> (usize, usize) {
+        (
+            usize::from(matches!(node, ENode::Fraction(_)))
+                + children
+                    .iter()
+                    .map(|(fractions, _)| fractions)
+                    .sum::<usize>(),
+            1 + children.iter().map(|(_, nodes)| nodes).sum::<usize>(),
+        )
+    }
+}
+
+/// When `simplify_expression_saturated_with` stops applying rules
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SaturationLimits {
+    pub max_nodes: usize,
+    pub max_iterations: usize,
+    pub timeout: Duration,
+}
+
+impl Default for SaturationLimits {
+    fn default() -> SaturationLimits {
+        SaturationLimits {
+            max_nodes: 2_000,
+            max_iterations: 30,
+            timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Operation {
+    Sum,
+    Product,
+    Fraction,
+    Power,
+}
+
+#[derive(Clone, Debug)]
+enum Pattern {
+    Wildcard(String),
+    Number(BigRational),
+    Variable(String),
+    Operation(Operation, Box<[Pattern; 2]>),
+    Call { name: String, args: Vec<Pattern> },
+}
+
+const MAX_FOLDED_EXPONENT: u32 = 64;
+
+fn fold(operation: Operation, a: &BigRational, b: &BigRational) -> Option<BigRational> {
+    match operation {
+        Operation::Sum => Some(a + b),
+        Operation::Product => Some(a * b),
+        Operation::Fraction => (!b.is_zero()).then(|| a / b),
+        Operation::Power => {
+            let bound = BigInt::from(MAX_FOLDED_EXPONENT);
+            (b.numer().abs() <= bound && b.denom() <= &bound)
+                .then(|| exact_power(a, b))
+                .flatten()
+        }
+    }
+}
+
+fn operation(operation: Operation, a: Pattern, b: Pattern) -> Pattern {
+    // parts without variables are folded, so `?a^-1`, which is `?a^(-1*1)`, has the number `-1` as its exponent
+    if let (Pattern::Number(a), Pattern::Number(b)) = (&a, &b) {
+        if let Some(value) = fold(operation, a, b) {
+            return Pattern::Number(value);
+        }
+    }
+    Pattern::Operation(operation, Box::new([a, b]))
+}
+
+fn expression_pattern(expression: &Expression) -> Pattern {
+    expression
+        .terms
+        .iter()
+        .map(term_pattern)
+        .reduce(|a, b| operation(Operation::Sum, a, b))
+        .unwrap_or_else(|| Pattern::Number(BigRational::zero()))
+}
+
+fn term_pattern(term: &Term) -> Pattern {
+    term.atoms
+        .iter()
+        .map(atom_pattern)
+        .reduce(|a, b| operation(Operation::Product, a, b))
+        .unwrap_or_else(|| Pattern::Number(BigRational::from_integer(1.into())))
+}
+
+fn atom_pattern(atom: &Atom) -> Pattern {
+    match &atom.kind {
+        AtomKind::Number(value) => Pattern::Number(value.clone()),
+        AtomKind::Variable(name) => match wildcard(atom) {
+            Some(name) => Pattern::Wildcard(name.to_string()),
+            None => Pattern::Variable(name.clone()),
+        },
+        AtomKind::Group(expression) => expression_pattern(expression),
+        AtomKind::Fraction {
+            numerator,
+            denominator,
+        } => operation(
+            Operation::Fraction,
+            expression_pattern(numerator),
+            expression_pattern(denominator),
+        ),
+        AtomKind::Power { base, exponent } => {
+            operation(Operation::Power, atom_pattern(base), atom_pattern(exponent))
+        }
+        AtomKind::Call { name, args } => Pattern::Call {
+            name: name.clone(),
+            args: args.iter().map(expression_pattern).collect(),
+        },
+    }
+}
+
+type Bindings = HashMap<String, Id>;
+
+#[derive(Clone, Default, Debug)]
+struct EClass {
+    nodes: Vec<ENode>,
+    constant: Option<BigRational>,
+}
+
+#[derive(Default, Debug)]
+struct EGraph {
+    parents: Vec<usize>,
+    classes: Vec<EClass>,
+    memo: HashMap<ENode, Id>,
+}
+
+impl EGraph {
+    fn find(&self, id: Id) -> Id {
+        let mut current = id.0;
+        while self.parents[current] != current {
+            current = self.parents[current];
+        }
+        Id(current)
+    }
+
+    fn size(&self) -> usize {
+        self.memo.len()
+    }
+
+    fn roots(&self) -> impl Iterator<Item = Id> + '_ {
+        (0..self.classes.len())
+            .filter(|&id| self.parents[id] == id)
+            .map(Id)
+    }
+
+    fn constant(&self, id: Id) -> Option<&BigRational> {
+        self.classes[self.find(id).0].constant.as_ref()
+    }
+
+    fn node_constant(&self, node: &ENode) -> Option<BigRational> {
+        let operation = match node {
+            ENode::Number(value) => return Some(value.clone()),
+            ENode::Variable(_) | ENode::Call { .. } => return None,
+            ENode::Sum(_) => Operation::Sum,
+            ENode::Product(_) => Operation::Product,
+            ENode::Fraction(_) => Operation::Fraction,
+            ENode::Power(_) => Operation::Power,
+        };
+        let [a, b] = node.children() else {
+            return None;
+        };
+        fold(operation, self.constant(*a)?, self.constant(*b)?)
+    }
+
+    fn add(&mut self, node: ENode) -> Id {
+        let node = node.map_children(|id| self.find(id));
+        if let Some(id) = self.memo.get(&node) {
+            return self.find(*id);
+        }
+        let id = Id(self.classes.len());
+        let constant = self.node_constant(&node);
+        self.parents.push(id.0);
+        self.classes.push(EClass {
+            nodes: vec![node.clone()],
+            constant: constant.clone(),
+        });
+        self.memo.insert(node.clone(), id);
+        // an operation on numbers is also the number it is equal to
+        if let Some(constant) = constant.filter(|_| !matches!(node, ENode::Number(_))) {
+            let number = self.add(ENode::Number(constant));
+            self.union(id, number);
+        }
+        self.find(id)
+    }
+
+    fn union(&mut self, a: Id, b: Id) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        // the older class stays, so the ids of the classes do not depend on the order things are merged in
+        let (root, merged) = if a < b { (a, b) } else { (b, a) };
+        self.parents[merged.0] = root.0;
+        let merged = std::mem::take(&mut self.classes[merged.0]);
+        let root = &mut self.classes[root.0];
+        root.nodes.extend(merged.nodes);
+        root.constant = root.constant.take().or(merged.constant);
+        true
+    }
+
+    fn rebuild(&mut self) {
+        loop {
+            let mut memo = HashMap::<ENode, Id>::new();
+            let mut unions = vec![];
+            let roots = self.roots().collect::<Vec<_>>();
+            for &id in &roots {
+                let mut nodes = vec![];
+                for node in &self.classes[id.0].nodes {
+                    let node = node.map_children(|child| self.find(child));
+                    match memo.get(&node) {
+                        Some(&other) if other != id => unions.push((other, id)),
+                        Some(_) => {}
+                        None => {
+                            memo.insert(node.clone(), id);
+                            nodes.push(node);
+                        }
+                    }
+                }
+                self.classes[id.0].nodes = nodes;
+            }
+            // constants can be found after merging, `x*0` is `0` once `x` is known to be `2`
+            let mut constants = vec![];
+            for &id in &roots {
+                if self.classes[id.0].constant.is_none() {
+                    if let Some(constant) = self.classes[id.0]
+                        .nodes
+                        .iter()
+                        .find_map(|node| self.node_constant(node))
+                    {
+                        constants.push((id, constant));
+                    }
+                }
+            }
+            self.memo = memo;
+            if unions.is_empty() && constants.is_empty() {
+                return;
+            }
+            for (a, b) in unions {
+                self.union(a, b);
+            }
+            for (id, constant) in constants {
+                let id = self.find(id);
+                if self.classes[id.0].constant.is_none() {
+                    self.classes[id.0].constant = Some(constant.clone());
+                    let number = self.add(ENode::Number(constant));
+                    self.union(id, number);
+                }
+            }
+        }
+    }
+
+    fn add_pattern(&mut self, pattern: &Pattern, bindings: &Bindings) -> Id {
+        match pattern {
+            Pattern::Wildcard(name) => self.find(bindings[name]),
+            Pattern::Number(value) => self.add(ENode::Number(value.clone())),
+            Pattern::Variable(name) => self.add(ENode::Variable(name.clone())),
+            Pattern::Operation(operation, children) => {
+                let children = [
+                    self.add_pattern(&children[0], bindings),
+                    self.add_pattern(&children[1], bindings),
+                ];
+                self.add(match operation {
+                    Operation::Sum => ENode::Sum(children),
+                    Operation::Product => ENode::Product(children),
+                    Operation::Fraction => ENode::Fraction(children),
+                    Operation::Power => ENode::Power(children),
+                })
+            }
+            Pattern::Call { name, args } => {
+                let args = args
+                    .iter()
+                    .map(|argument| self.add_pattern(argument, bindings))
+                    .collect();
+                self.add(ENode::Call {
+                    name: name.clone(),
+                    args,
+                })
+            }
+        }
+    }
+
+    fn search(&self, pattern: &Pattern, id: Id, bindings: Bindings) -> Vec<Bindings> {
+        let id = self.find(id);
+        let class = &self.classes[id.0];
+        // a class that is equal to a number is not rewritten any further, the number is as simple as it gets,
+        // and its other nodes can go on forever, like `0` that is also `x*0` and `x*(x*0)`
+        if class.constant.is_some()
+            && matches!(pattern, Pattern::Operation(..) | Pattern::Call { .. })
+        {
+            return vec![];
+        }
+        match pattern {
+            Pattern::Wildcard(name) => match bindings.get(name) {
+                // a wildcard that is used twice must match the same class twice
+                Some(bound) if self.find(*bound) != id => vec![],
+                Some(_) => vec![bindings],
+                None => {
+                    let mut bindings = bindings;
+                    bindings.insert(name.clone(), id);
+                    vec![bindings]
+                }
+            },
+            Pattern::Number(value) => {
+                if class.constant.as_ref() == Some(value) {
+                    vec![bindings]
+                } else {
+                    vec![]
+                }
+            }
+            Pattern::Variable(name) => {
+                if class.nodes.contains(&ENode::Variable(name.clone())) {
+                    vec![bindings]
+                } else {
+                    vec![]
+                }
+            }
+            Pattern::Operation(operation, children) => {
+                let mut found = vec![];
+                for node in &class.nodes {
+                    let matches = matches!(
+                        (operation, node),
+                        (Operation::Sum, ENode::Sum(_))
+                            | (Operation::Product, ENode::Product(_))
+                            | (Operation::Fraction, ENode::Fraction(_))
+                            | (Operation::Power, ENode::Power(_))
+                    );
+                    if matches {
+                        found.extend(self.search_children(
+                            &children[..],
+                            node.children(),
+                            bindings.clone(),
+                        ));
+                    }
+                }
+                found
+            }
+            Pattern::Call { name, args } => {
+                let mut found = vec![];
+                for node in &class.nodes {
+                    if let ENode::Call {
+                        name: node_name,
+                        args: node_args,
+                    } = node
+                    {
+                        if node_name == name && node_args.len() == args.len() {
+                            found.extend(self.search_children(args, node_args, bindings.clone()));
+                        }
+                    }
+                }
+                found
+            }
+        }
+    }
+
+    fn search_children(
+        &self,
+        patterns: &[Pattern],
+        ids: &[Id],
+        bindings: Bindings,
+    ) -> Vec<Bindings> {
+        let mut found = vec![bindings];
+        for (pattern, id) in patterns.iter().zip(ids) {
+            found = found
+                .into_iter()
+                .flat_map(|bindings| self.search(pattern, *id, bindings))
+                .collect();
+        }
+        found
+    }
+
+    fn cheapest<C: CostFunction>(&self, cost: &C) -> HashMap<Id, (C::Cost, ENode)> {
+        let mut cheapest = HashMap::<Id, (C::Cost, ENode)>::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for id in self.roots() {
+                for node in &self.classes[id.0].nodes {
+                    let Some(children) = node
+                        .children()
+                        .iter()
+                        .map(|child| {
+                            cheapest
+                                .get(&self.find(*child))
+                                .map(|(cost, _)| cost.clone())
+                        })
+                        .collect::<Option<Vec<_>>>()
+                    else {
+                        continue;
+                    };
+                    let node_cost = cost.cost(node, &children);
+                    if cheapest
+                        .get(&id)
+                        .is_none_or(|(cheapest, _)| &node_cost < cheapest)
+                    {
+                        cheapest.insert(id, (node_cost, node.clone()));
+                        changed = true;
+                    }
+                }
+            }
+        }
+        cheapest
+    }
+}
+
+struct Extractor<C: CostFunction> {
+    cheapest: HashMap<Id, (C::Cost, ENode)>,
+}
+
+impl<C: CostFunction> Extractor<C> {
+    fn node(&self, id: Id) -> &ENode {
+        &self.cheapest[&id].1
+    }
+
+    fn expression(&self, egraph: &EGraph, id: Id) -> Expression {
+        let id = egraph.find(id);
+        match self.node(id) {
+            ENode::Sum([a, b]) => {
+                let mut terms = self.expression(egraph, *a).terms;
+                terms.extend(self.expression(egraph, *b).terms);
+                Expression::new(terms)
+            }
+            _ => Expression::new(vec![self.term(egraph, id)]),
+        }
+    }
+
+    fn term(&self, egraph: &EGraph, id: Id) -> Term {
+        let id = egraph.find(id);
+        match self.node(id) {
+            ENode::Product([a, b]) => {
+                let mut atoms = self.term(egraph, *a).atoms;
+                atoms.extend(self.term(egraph, *b).atoms);
+                Term::new(atoms)
+            }
+            _ => Term::new(vec![self.atom(egraph, id)]),
+        }
+    }
+
+    fn atom(&self, egraph: &EGraph, id: Id) -> Atom {
+        let id = egraph.find(id);
+        let kind = match self.node(id) {
+            ENode::Number(value) => AtomKind::Number(value.clone()),
+            ENode::Variable(name) => AtomKind::Variable(name.clone()),
+            ENode::Sum(_) | ENode::Product(_) => AtomKind::Group(self.expression(egraph, id)),
+            ENode::Fraction([numerator, denominator]) => AtomKind::Fraction {
+                numerator: self.expression(egraph, *numerator),
+                denominator: self.expression(egraph, *denominator),
+            },
+            ENode::Power([base, exponent]) => AtomKind::Power {
+                base: Box::new(self.atom(egraph, *base)),
+                exponent: Box::new(self.atom(egraph, *exponent)),
+            },
+            ENode::Call { name, args } => AtomKind::Call {
+                name: name.clone(),
+                args: args
+                    .iter()
+                    .map(|argument| self.expression(egraph, *argument))
+                    .collect(),
+            },
+        };
+        Atom::new(kind)
+    }
+}
+
+pub(crate) fn algebra_rules() -> Vec<Rule> {
+    [
+        ("commute_sum", "?a + ?b -> ?b + ?a"),
+        ("commute_product", "?a*?b -> ?b*?a"),
+        ("associate_sum", "?a + (?b + ?c) -> ?a + ?b + ?c"),
+        ("associate_product", "?a*(?b*?c) -> ?a*?b*?c"),
+        ("double", "?a + ?a -> 2?a"),
+        ("distribute", "?a*(?b + ?c) -> ?a*?b + ?a*?c"),
+        ("factor", "?a*?b + ?a*?c -> ?a*(?b + ?c)"),
+        ("factor_one", "?a*?b + ?a -> ?a*(?b + 1)"),
+        ("add_zero", "?a + 0 -> ?a"),
+        ("multiply_by_one", "?a*1 -> ?a"),
+        ("multiply_by_zero", "?a*0 -> 0"),
+        ("square", "?a*?a -> ?a^2"),
+        ("add_exponents", "?a^?b*?a^?c -> ?a^(?b + ?c)"),
+        ("add_exponent_one", "?a^?b*?a -> ?a^(?b + 1)"),
+        ("power_of_zero", "?a^0 -> 1"),
+        ("power_of_one", "?a^1 -> ?a"),
+        ("divide", "?a/?b -> ?a*?b^-1"),
+        ("multiply_fraction", "?a*(?b/?c) -> ?a*?b/?c"),
+        ("add_fractions", "?a/?c + ?b/?c -> (?a + ?b)/?c"),
+    ]
+    .into_iter()
+    .map(|(name, source)| Rule::parse(name, source).unwrap())
+    .collect()
+}
+
+/// Simplifies with `RuleSet::algebra` and picks the expression with the fewest nodes
+pub fn simplify_expression_saturated(expression: &Expression) -> Expression {
+    simplify_expression_saturated_with(
+        expression,
+        algebra_rule_set(),
+        &SaturationLimits::default(),
+        &NodeCount,
+    )
+}
+
+/// Simplifies with equality saturation, the rules can go both ways and their order does not matter
+pub fn simplify_expression_saturated_with<C: CostFunction>(
+    expression: &Expression,
+    rules: &RuleSet,
+    limits: &SaturationLimits,
+    cost: &C,
+) -> Expression {
+    let start = Instant::now();
+    let rules = rules
+        .rules()
+        .filter_map(Rule::as_rewrite)
+        .map(|(pattern, replacement)| {
+            (expression_pattern(pattern), expression_pattern(replacement))
+        })
+        .collect::<Vec<_>>();
+    let mut egraph = EGraph::default();
+    let root = egraph.add_pattern(&expression_pattern(expression), &Bindings::new());
+    egraph.rebuild();
+    'saturate: for _ in 0..limits.max_iterations {
+        // the matches are all found before any is applied, so the rules that come first are not favored
+        let mut matches = vec![];
+        for (pattern, replacement) in &rules {
+            for id in egraph.roots() {
+                for bindings in egraph.search(pattern, id, Bindings::new()) {
+                    matches.push((id, replacement, bindings));
+                }
+                if start.elapsed() > limits.timeout {
+                    break 'saturate;
+                }
+            }
+        }
+        let mut changed = false;
+        for (id, replacement, bindings) in matches {
+            let rewritten = egraph.add_pattern(replacement, &bindings);
+            changed |= egraph.union(id, rewritten);
+            if egraph.size() > limits.max_nodes || start.elapsed() > limits.timeout {
+                egraph.rebuild();
+                break 'saturate;
+            }
+        }
+        egraph.rebuild();
+        if !changed || start.elapsed() > limits.timeout {
+            break;
+        }
+    }
+    let extractor = Extractor::<C> {
+        cheapest: egraph.cheapest(cost),
+    };
+    extractor.expression(&egraph, root)
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use enum_as_inner::EnumAsInner;
+use num_rational::BigRational;
+
+use crate::Expression;
+
+#[derive(Clone, PartialEq, Debug, EnumAsInner)]
+pub enum Binding {
+    Value(BigRational),
+    /// Evaluated on every use, so it can refer to other bound variables
+    Expression(Expression),
+}
+
+impl From<BigRational> for Binding {
+    fn from(value: BigRational) -> Binding {
+        Binding::Value(value)
+    }
+}
+
+impl From<Expression> for Binding {
+    fn from(expression: Expression) -> Binding {
+        Binding::Expression(expression)
+    }
+}
+
+/// Values for variables, used by `eval_expression_with` and friends
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Environment {
+    bindings: HashMap<String, Binding>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    pub fn bind(&mut self, name: &str, binding: impl Into<Binding>) {
+        self.bindings.insert(name.to_string(), binding.into());
+    }
+
+    pub fn unbind(&mut self, name: &str) -> Option<Binding> {
+        self.bindings.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Binding> {
+        self.bindings.get(name)
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (&String, &Binding)> {
+        self.bindings.iter()
+    }
+}
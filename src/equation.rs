@@ -1,49 +1,27 @@
-use std::{cmp::Ordering, fmt::Display};
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
 
 use derive_more::IsVariant;
 use enum_as_inner::EnumAsInner;
-use num_bigint::BigInt;
 use num_rational::BigRational;
+use num_traits::{Signed, Zero};
 
-// modified version of: https://www.reddit.com/r/rust/comments/2saclr/numrational_help/
-fn rational_to_decimal_string(r: &BigRational, mut max_decimals: usize) -> String {
-    // We get the fractional part. We want to get as many digits as possible from here.
-    let mut fract = r.fract();
-    let mut zeros = 0;
-    while max_decimals > 0 && fract != BigRational::from_integer(0.into()) {
-        if fract.is_integer() {
-            break; // This means we already got all digits available
-        }
-        // By multiplying by 10 we move the digit to the "whole part" of the ratio
-        fract *= BigRational::from_integer(10.into());
-        if fract.to_integer() != 0.into() {
-            max_decimals -= 1;
-        } else {
-            zeros += 1;
-        }
-    }
-    // to_integer() gives us a representation with the decimal values truncated.
-    // fract contains up to max_decimals of the digits after the decimal value as
-    // the whole (before the value) so printing those values will give us the post
-    // decimal digits
-    let mut decimals = fract.round().to_integer();
-    if decimals < 0.into() {
-        decimals *= BigInt::from(-1);
-    }
-    if decimals == 0.into() {
-        format!("{}", r.to_integer())
-    } else {
-        format!(
-            "{}.{}{}",
-            r.to_integer(),
-            std::iter::repeat('0').take(zeros).collect::<String>(),
-            decimals
-        )
-    }
-}
+use crate::{
+    builtin_function, eval_atom, eval_expression, exact_power, print_atom_with,
+    print_expression_with, print_term_with, DisplayOptions, DisplayWith, SourceSpan,
+};
 
-#[derive(Clone, PartialEq, Debug, IsVariant, EnumAsInner)]
-pub enum Atom {
+#[derive(Clone, PartialEq, Eq, Hash, Debug, IsVariant, EnumAsInner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value", rename_all = "snake_case")
+)]
+pub enum AtomKind {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::rational"))]
     Number(BigRational),
     Variable(String),
     Group(Expression),
@@ -51,94 +29,279 @@ pub enum Atom {
         numerator: Expression,
         denominator: Expression,
     },
+    Power {
+        base: Box<Atom>,
+        exponent: Box<Atom>,
+    },
+    Call {
+        name: String,
+        args: Vec<Expression>,
+    },
+}
+
+/// Spans are only used for diagnostics, so they are ignored when comparing and hashing nodes
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Atom {
+    pub kind: AtomKind,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub span: Option<SourceSpan>,
 }
 
 impl Atom {
+    pub fn new(kind: AtomKind) -> Atom {
+        Atom { kind, span: None }
+    }
+
     pub fn contains_variable(&self) -> bool {
-        match self {
-            Atom::Number(_) => false,
-            Atom::Variable(_) => true,
-            Atom::Group(expression) => expression.contains_variable(),
-            Atom::Fraction {
+        match &self.kind {
+            AtomKind::Number(_) => false,
+            AtomKind::Variable(_) => true,
+            AtomKind::Group(expression) => expression.contains_variable(),
+            AtomKind::Fraction {
                 numerator,
                 denominator,
             } => numerator.contains_variable() || denominator.contains_variable(),
+            AtomKind::Power { base, exponent } => {
+                base.contains_variable() || exponent.contains_variable()
+            }
+            AtomKind::Call { args, .. } => args.iter().any(Expression::contains_variable),
+        }
+    }
+
+    pub fn contains_variable_named(&self, name: &str) -> bool {
+        match &self.kind {
+            AtomKind::Number(_) => false,
+            AtomKind::Variable(variable) => variable == name,
+            AtomKind::Group(expression) => expression.contains_variable_named(name),
+            AtomKind::Fraction {
+                numerator,
+                denominator,
+            } => {
+                numerator.contains_variable_named(name) || denominator.contains_variable_named(name)
+            }
+            AtomKind::Power { base, exponent } => {
+                base.contains_variable_named(name) || exponent.contains_variable_named(name)
+            }
+            AtomKind::Call { args, .. } => args
+                .iter()
+                .any(|argument| argument.contains_variable_named(name)),
+        }
+    }
+
+    /// Returns whether evaluating the atom gives an exact rational number (or an error like division by zero),
+    /// which is not the case for variables and irrational powers such as `2^(1/2)`
+    pub fn is_exact(&self) -> bool {
+        match &self.kind {
+            AtomKind::Number(_) => true,
+            AtomKind::Variable(_) => false,
+            AtomKind::Group(expression) => expression.is_exact(),
+            AtomKind::Fraction {
+                numerator,
+                denominator,
+            } => numerator.is_exact() && denominator.is_exact(),
+            AtomKind::Power { base, exponent } => {
+                base.is_exact()
+                    && exponent.is_exact()
+                    && match (eval_atom(base), eval_atom(exponent)) {
+                        (Ok(base), Ok(exponent)) => {
+                            exact_power(&base, &exponent).is_some()
+                                || (base.is_zero() && exponent.is_negative())
+                        }
+                        _ => true,
+                    }
+            }
+            AtomKind::Call { name, args } => {
+                args.iter().all(Expression::is_exact)
+                    && match (
+                        builtin_function(name),
+                        args.iter()
+                            .map(eval_expression)
+                            .collect::<Result<Vec<_>, _>>(),
+                    ) {
+                        (Some(function), Ok(args)) if function.arity.accepts(args.len()) => {
+                            (function.exact)(&args).is_some()
+                        }
+                        // evaluating it gives an error
+                        _ => true,
+                    }
+            }
         }
     }
 }
 
+impl PartialEq for Atom {
+    fn eq(&self, other: &Atom) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl Eq for Atom {}
+
+impl Hash for Atom {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+    }
+}
+
 impl PartialOrd for Atom {
     fn partial_cmp(&self, other: &Atom) -> Option<Ordering> {
         if self == other {
             return Some(Ordering::Equal);
         }
-        match self {
-            Atom::Number(value) => match other {
-                Atom::Number(other_value) => value.partial_cmp(other_value),
-                Atom::Variable(_) => Some(Ordering::Less),
-                Atom::Group(_) => Some(Ordering::Less),
-                Atom::Fraction {
+        match &self.kind {
+            AtomKind::Number(value) => match &other.kind {
+                AtomKind::Number(other_value) => value.partial_cmp(other_value),
+                AtomKind::Variable(_) => Some(Ordering::Less),
+                AtomKind::Group(_) => Some(Ordering::Less),
+                AtomKind::Fraction {
                     numerator: _,
                     denominator: _,
                 } => Some(Ordering::Less),
+                AtomKind::Power {
+                    base: _,
+                    exponent: _,
+                } => Some(Ordering::Less),
+                AtomKind::Call { .. } => Some(Ordering::Less),
             },
-            Atom::Variable(name) => match other {
-                Atom::Number(_) => Some(Ordering::Greater),
-                Atom::Variable(other_name) => name.partial_cmp(other_name),
-                Atom::Group(_) => Some(Ordering::Less),
-                Atom::Fraction {
+            AtomKind::Variable(name) => match &other.kind {
+                AtomKind::Number(_) => Some(Ordering::Greater),
+                AtomKind::Variable(other_name) => name.partial_cmp(other_name),
+                AtomKind::Group(_) => Some(Ordering::Less),
+                AtomKind::Fraction {
                     numerator: _,
                     denominator: _,
                 } => Some(Ordering::Greater),
+                AtomKind::Power {
+                    base: _,
+                    exponent: _,
+                } => Some(Ordering::Less),
+                AtomKind::Call { .. } => Some(Ordering::Less),
             },
-            Atom::Group(expression) => match other {
-                Atom::Number(_) => Some(Ordering::Greater),
-                Atom::Variable(_) => Some(Ordering::Greater),
-                Atom::Group(other_expression) => expression.partial_cmp(other_expression),
-                Atom::Fraction {
+            AtomKind::Group(expression) => match &other.kind {
+                AtomKind::Number(_) => Some(Ordering::Greater),
+                AtomKind::Variable(_) => Some(Ordering::Greater),
+                AtomKind::Group(other_expression) => expression.partial_cmp(other_expression),
+                AtomKind::Fraction {
                     numerator: _,
                     denominator: _,
                 } => Some(Ordering::Greater),
+                AtomKind::Power {
+                    base: _,
+                    exponent: _,
+                } => Some(Ordering::Greater),
+                AtomKind::Call { .. } => Some(Ordering::Greater),
             },
-            Atom::Fraction {
+            AtomKind::Fraction {
                 numerator,
                 denominator,
-            } => match other {
-                Atom::Number(_) => Some(Ordering::Greater),
-                Atom::Variable(_) => Some(Ordering::Less),
-                Atom::Group(_) => Some(Ordering::Less),
-                Atom::Fraction {
+            } => match &other.kind {
+                AtomKind::Number(_) => Some(Ordering::Greater),
+                AtomKind::Variable(_) => Some(Ordering::Less),
+                AtomKind::Group(_) => Some(Ordering::Less),
+                AtomKind::Fraction {
                     numerator: other_numerator,
                     denominator: other_denominator,
                 } => [numerator, denominator].partial_cmp(&[other_numerator, other_denominator]),
+                AtomKind::Power {
+                    base: _,
+                    exponent: _,
+                } => Some(Ordering::Less),
+                AtomKind::Call { .. } => Some(Ordering::Less),
+            },
+            AtomKind::Power { base, exponent } => match &other.kind {
+                AtomKind::Number(_) => Some(Ordering::Greater),
+                AtomKind::Variable(_) => Some(Ordering::Greater),
+                AtomKind::Group(_) => Some(Ordering::Less),
+                AtomKind::Fraction {
+                    numerator: _,
+                    denominator: _,
+                } => Some(Ordering::Greater),
+                AtomKind::Power {
+                    base: other_base,
+                    exponent: other_exponent,
+                } => [base, exponent].partial_cmp(&[other_base, other_exponent]),
+                AtomKind::Call { .. } => Some(Ordering::Less),
+            },
+            AtomKind::Call { name, args } => match &other.kind {
+                AtomKind::Number(_) => Some(Ordering::Greater),
+                AtomKind::Variable(_) => Some(Ordering::Greater),
+                AtomKind::Group(_) => Some(Ordering::Less),
+                AtomKind::Fraction {
+                    numerator: _,
+                    denominator: _,
+                } => Some(Ordering::Greater),
+                AtomKind::Power {
+                    base: _,
+                    exponent: _,
+                } => Some(Ordering::Greater),
+                AtomKind::Call {
+                    name: other_name,
+                    args: other_args,
+                } => (name, args).partial_cmp(&(other_name, other_args)),
             },
         }
     }
 }
 
+impl DisplayWith for Atom {
+    fn fmt_with(&self, f: &mut std::fmt::Formatter, options: &DisplayOptions) -> std::fmt::Result {
+        write!(f, "{}", print_atom_with(self, options))
+    }
+}
+
 impl Display for Atom {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            Atom::Number(value) => write!(f, "{}", rational_to_decimal_string(value, 10)),
-            Atom::Variable(name) => write!(f, "{}", name),
-            Atom::Group(expression) => write!(f, "({})", expression),
-            Atom::Fraction {
-                numerator,
-                denominator,
-            } => write!(f, "(({})/({}))", numerator, denominator),
-        }
+        self.fmt_with(f, &DisplayOptions::default())
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Term {
     pub atoms: Vec<Atom>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub span: Option<SourceSpan>,
 }
 
 impl Term {
+    pub fn new(atoms: Vec<Atom>) -> Term {
+        Term { atoms, span: None }
+    }
+
     pub fn contains_variable(&self) -> bool {
         self.atoms.iter().any(Atom::contains_variable)
     }
+
+    pub fn contains_variable_named(&self, name: &str) -> bool {
+        self.atoms
+            .iter()
+            .any(|atom| atom.contains_variable_named(name))
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.atoms.iter().all(Atom::is_exact)
+    }
+}
+
+impl PartialEq for Term {
+    fn eq(&self, other: &Term) -> bool {
+        self.atoms == other.atoms
+    }
+}
+
+impl Eq for Term {}
+
+impl Hash for Term {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.atoms.hash(state);
+    }
 }
 
 impl PartialOrd for Term {
@@ -147,24 +310,41 @@ impl PartialOrd for Term {
     }
 }
 
+impl DisplayWith for Term {
+    fn fmt_with(&self, f: &mut std::fmt::Formatter, options: &DisplayOptions) -> std::fmt::Result {
+        write!(f, "{}", print_term_with(self, options))
+    }
+}
+
 impl Display for Term {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if self.atoms.len() == 0 {
-            return write!(f, "1");
-        }
-        for (i, atom) in self.atoms.iter().enumerate() {
-            if i > 0 {
-                write!(f, "*")?;
-            }
-            write!(f, "{}", atom)?;
-        }
-        Ok(())
+        self.fmt_with(f, &DisplayOptions::default())
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Expression {
     pub terms: Vec<Term>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub span: Option<SourceSpan>,
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Expression) -> bool {
+        self.terms == other.terms
+    }
+}
+
+impl Eq for Expression {}
+
+impl Hash for Expression {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.terms.hash(state);
+    }
 }
 
 impl PartialOrd for Expression {
@@ -174,45 +354,145 @@ impl PartialOrd for Expression {
 }
 
 impl Expression {
+    pub fn new(terms: Vec<Term>) -> Expression {
+        Expression { terms, span: None }
+    }
+
     pub fn contains_variable(&self) -> bool {
         self.terms.iter().any(Term::contains_variable)
     }
+
+    pub fn contains_variable_named(&self, name: &str) -> bool {
+        self.terms
+            .iter()
+            .any(|term| term.contains_variable_named(name))
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.terms.iter().all(Term::is_exact)
+    }
+}
+
+impl DisplayWith for Expression {
+    fn fmt_with(&self, f: &mut std::fmt::Formatter, options: &DisplayOptions) -> std::fmt::Result {
+        write!(f, "{}", print_expression_with(self, options))
+    }
 }
 
 impl Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if self.terms.len() == 0 {
-            return write!(f, "0");
-        }
-        for (i, term) in self.terms.iter().enumerate() {
-            if i > 0 {
-                write!(f, " + ")?;
-            }
-            write!(f, "{}", term)?;
-        }
-        Ok(())
+        self.fmt_with(f, &DisplayOptions::default())
     }
 }
 
-#[derive(Clone, PartialEq, Debug, IsVariant, EnumAsInner)]
+#[derive(Clone, Debug, IsVariant, EnumAsInner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value", rename_all = "snake_case")
+)]
 pub enum Equation {
-    Equality { left: Expression, right: Expression },
+    Equality {
+        left: Expression,
+        right: Expression,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        span: Option<SourceSpan>,
+    },
+}
+
+impl PartialEq for Equation {
+    fn eq(&self, other: &Equation) -> bool {
+        match (self, other) {
+            (
+                Equation::Equality { left, right, .. },
+                Equation::Equality {
+                    left: other_left,
+                    right: other_right,
+                    ..
+                },
+            ) => left == other_left && right == other_right,
+        }
+    }
+}
+
+impl Eq for Equation {}
+
+impl Hash for Equation {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Equation::Equality { left, right, .. } => {
+                left.hash(state);
+                right.hash(state);
+            }
+        }
+    }
 }
 
 impl Equation {
+    pub fn span(&self) -> Option<&SourceSpan> {
+        match self {
+            Equation::Equality { span, .. } => span.as_ref(),
+        }
+    }
+
     pub fn contains_variable(&self) -> bool {
         match self {
-            Equation::Equality { left, right } => {
+            Equation::Equality { left, right, .. } => {
                 left.contains_variable() || right.contains_variable()
             }
         }
     }
+
+    pub fn contains_variable_named(&self, name: &str) -> bool {
+        match self {
+            Equation::Equality { left, right, .. } => {
+                left.contains_variable_named(name) || right.contains_variable_named(name)
+            }
+        }
+    }
+}
+
+impl DisplayWith for Equation {
+    fn fmt_with(&self, f: &mut std::fmt::Formatter, options: &DisplayOptions) -> std::fmt::Result {
+        match self {
+            Equation::Equality { left, right, .. } => {
+                left.fmt_with(f, options)?;
+                write!(f, " = ")?;
+                right.fmt_with(f, options)
+            }
+        }
+    }
 }
 
 impl Display for Equation {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            Equation::Equality { left, right } => write!(f, "{} = {}", left, right),
+        self.fmt_with(f, &DisplayOptions::default())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct System {
+    pub equations: Vec<Equation>,
+}
+
+impl DisplayWith for System {
+    fn fmt_with(&self, f: &mut std::fmt::Formatter, options: &DisplayOptions) -> std::fmt::Result {
+        for (i, equation) in self.equations.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            equation.fmt_with(f, options)?;
         }
+        Ok(())
+    }
+}
+
+impl Display for System {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.fmt_with(f, &DisplayOptions::default())
     }
 }
@@ -1,23 +1,258 @@
+use derive_more::{Display, IsVariant};
+use enum_as_inner::EnumAsInner;
+use num_bigint::{BigInt, BigUint};
 use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
 
-use crate::{Atom, Expression, Term};
+use crate::{
+    builtin_function, span_prefix, Arity, Atom, AtomKind, Binding, Environment, Expression,
+    SourceSpan, Term,
+};
 
-pub fn eval_atom(atom: &Atom) -> BigRational {
-    match atom {
-        Atom::Number(value) => value.clone(),
-        Atom::Variable(_) => unreachable!("cannot eval variables"),
-        Atom::Group(expression) => eval_expression(expression),
-        Atom::Fraction {
-            numerator,
-            denominator,
-        } => eval_expression(numerator) / eval_expression(denominator),
+fn exact_root(value: &BigInt, n: u32) -> Option<BigInt> {
+    if value.is_negative() && n.is_multiple_of(2) {
+        return None;
+    }
+    let root = value.nth_root(n);
+    (root.pow(n) == *value).then_some(root)
+}
+
+/// Powers with more bits than this are not computed, `2^2000000000` would take forever
+const MAX_POWER_BITS: u64 = 1_000_000;
+
+/// Whether `base^exponent` has too many bits to compute, powers of `0`, `1` and `-1` are never too large
+pub(crate) fn is_too_large_power(base: &BigRational, exponent: &BigRational) -> bool {
+    if base.is_integer() && base.numer().magnitude() <= &BigUint::one() {
+        return false;
+    }
+    let bits = base.numer().bits() + base.denom().bits();
+    let power = exponent.numer().magnitude().to_u64().unwrap_or(u64::MAX);
+    let root = exponent.denom().to_u64().unwrap_or(u64::MAX);
+    bits.saturating_mul(power) / root > MAX_POWER_BITS
+}
+
+/// Raises `base` to `exponent` when the result is a rational number,
+/// returns `None` for irrational results like `2^(1/2)` and for results that are too large to compute
+pub fn exact_power(base: &BigRational, exponent: &BigRational) -> Option<BigRational> {
+    if is_too_large_power(base, exponent) {
+        return None;
+    }
+    let power = exponent.numer().to_i32()?;
+    let root = exponent.denom().to_u32()?;
+    let base = if root == 1 {
+        base.clone()
+    } else {
+        BigRational::new(
+            exact_root(base.numer(), root)?,
+            exact_root(base.denom(), root)?,
+        )
+    };
+    // `unsigned_abs` because `i32::MIN` cannot be negated
+    let magnitude = num_traits::Pow::pow(&base, power.unsigned_abs());
+    if power < 0 {
+        (!base.is_zero()).then(|| magnitude.recip())
+    } else {
+        Some(magnitude)
     }
 }
 
-pub fn eval_term(term: &Term) -> BigRational {
-    term.atoms.iter().map(eval_atom).product()
+fn approximate_power(base: &BigRational, exponent: &BigRational) -> Option<BigRational> {
+    BigRational::from_float(base.to_f64()?.powf(exponent.to_f64()?))
+}
+
+#[derive(Clone, PartialEq, Debug, Display, IsVariant, EnumAsInner)]
+pub enum EvalError {
+    #[display(
+        fmt = "{}Variable '{name}' is not bound to a value",
+        "span_prefix(span)"
+    )]
+    UnboundVariable {
+        name: String,
+        span: Option<SourceSpan>,
+    },
+    #[display(
+        fmt = "{}Variable '{name}' is defined in terms of itself",
+        "span_prefix(span)"
+    )]
+    RecursiveBinding {
+        name: String,
+        span: Option<SourceSpan>,
+    },
+    /// `span` points at the part that evaluated to zero
+    #[display(fmt = "{}Division by zero in '{atom}'", "span_prefix(span)")]
+    DivisionByZero {
+        atom: Atom,
+        span: Option<SourceSpan>,
+    },
+    #[display(fmt = "{}'{atom}' is not a real number", "span_prefix(span)")]
+    NotReal {
+        atom: Atom,
+        span: Option<SourceSpan>,
+    },
+    #[display(fmt = "{}'{atom}' is too large to compute", "span_prefix(span)")]
+    TooLarge {
+        atom: Atom,
+        span: Option<SourceSpan>,
+    },
+    #[display(fmt = "{}Unknown function '{name}'", "span_prefix(span)")]
+    UnknownFunction {
+        name: String,
+        span: Option<SourceSpan>,
+    },
+    #[display(
+        fmt = "{}Function '{name}' takes {expected}, but got {got}",
+        "span_prefix(span)"
+    )]
+    WrongArgumentCount {
+        name: String,
+        expected: Arity,
+        got: usize,
+        span: Option<SourceSpan>,
+    },
+}
+
+fn eval_atom_in(
+    atom: &Atom,
+    environment: &Environment,
+    evaluating: &mut Vec<String>,
+) -> Result<BigRational, EvalError> {
+    Ok(match &atom.kind {
+        AtomKind::Number(value) => value.clone(),
+        AtomKind::Variable(name) => match environment.get(name) {
+            Some(Binding::Value(value)) => value.clone(),
+            Some(Binding::Expression(expression)) => {
+                if evaluating.contains(name) {
+                    return Err(EvalError::RecursiveBinding {
+                        name: name.clone(),
+                        span: atom.span.clone(),
+                    });
+                }
+                evaluating.push(name.clone());
+                let value = eval_expression_in(expression, environment, evaluating)?;
+                evaluating.pop();
+                value
+            }
+            None => {
+                return Err(EvalError::UnboundVariable {
+                    name: name.clone(),
+                    span: atom.span.clone(),
+                })
+            }
+        },
+        AtomKind::Group(expression) => eval_expression_in(expression, environment, evaluating)?,
+        AtomKind::Fraction {
+            numerator,
+            denominator: denominator_expression,
+        } => {
+            let numerator = eval_expression_in(numerator, environment, evaluating)?;
+            let denominator = eval_expression_in(denominator_expression, environment, evaluating)?;
+            if denominator.is_zero() {
+                return Err(EvalError::DivisionByZero {
+                    atom: atom.clone(),
+                    span: denominator_expression.span.clone(),
+                });
+            }
+            numerator / denominator
+        }
+        AtomKind::Power {
+            base: base_atom,
+            exponent,
+        } => {
+            let base = eval_atom_in(base_atom, environment, evaluating)?;
+            let exponent = eval_atom_in(exponent, environment, evaluating)?;
+            if base.is_zero() && exponent.is_negative() {
+                return Err(EvalError::DivisionByZero {
+                    atom: atom.clone(),
+                    span: base_atom.span.clone(),
+                });
+            }
+            exact_power(&base, &exponent)
+                .or_else(|| approximate_power(&base, &exponent))
+                .ok_or_else(|| {
+                    if is_too_large_power(&base, &exponent) {
+                        EvalError::TooLarge {
+                            atom: atom.clone(),
+                            span: atom.span.clone(),
+                        }
+                    } else {
+                        EvalError::NotReal {
+                            atom: atom.clone(),
+                            span: atom.span.clone(),
+                        }
+                    }
+                })?
+        }
+        AtomKind::Call { name, args } => {
+            let function = builtin_function(name).ok_or_else(|| EvalError::UnknownFunction {
+                name: name.clone(),
+                span: atom.span.clone(),
+            })?;
+            if !function.arity.accepts(args.len()) {
+                return Err(EvalError::WrongArgumentCount {
+                    name: name.clone(),
+                    expected: function.arity,
+                    got: args.len(),
+                    span: atom.span.clone(),
+                });
+            }
+            let args = args
+                .iter()
+                .map(|argument| eval_expression_in(argument, environment, evaluating))
+                .collect::<Result<Vec<_>, _>>()?;
+            function.call(&args).ok_or_else(|| EvalError::NotReal {
+                atom: atom.clone(),
+                span: atom.span.clone(),
+            })?
+        }
+    })
+}
+
+fn eval_term_in(
+    term: &Term,
+    environment: &Environment,
+    evaluating: &mut Vec<String>,
+) -> Result<BigRational, EvalError> {
+    term.atoms
+        .iter()
+        .map(|atom| eval_atom_in(atom, environment, evaluating))
+        .product()
+}
+
+fn eval_expression_in(
+    expression: &Expression,
+    environment: &Environment,
+    evaluating: &mut Vec<String>,
+) -> Result<BigRational, EvalError> {
+    expression
+        .terms
+        .iter()
+        .map(|term| eval_term_in(term, environment, evaluating))
+        .sum()
+}
+
+pub fn eval_atom_with(atom: &Atom, environment: &Environment) -> Result<BigRational, EvalError> {
+    eval_atom_in(atom, environment, &mut vec![])
+}
+
+pub fn eval_term_with(term: &Term, environment: &Environment) -> Result<BigRational, EvalError> {
+    eval_term_in(term, environment, &mut vec![])
+}
+
+pub fn eval_expression_with(
+    expression: &Expression,
+    environment: &Environment,
+) -> Result<BigRational, EvalError> {
+    eval_expression_in(expression, environment, &mut vec![])
+}
+
+pub fn eval_atom(atom: &Atom) -> Result<BigRational, EvalError> {
+    eval_atom_with(atom, &Environment::new())
+}
+
+pub fn eval_term(term: &Term) -> Result<BigRational, EvalError> {
+    eval_term_with(term, &Environment::new())
 }
 
-pub fn eval_expression(expression: &Expression) -> BigRational {
-    expression.terms.iter().map(eval_term).sum()
+pub fn eval_expression(expression: &Expression) -> Result<BigRational, EvalError> {
+    eval_expression_with(expression, &Environment::new())
 }
@@ -0,0 +1,179 @@
+use std::fmt::Display;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive};
+use phf::phf_map;
+
+use crate::exact_power;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exactly(arity) => count == arity,
+            Arity::AtLeast(arity) => count >= arity,
+        }
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (prefix, count) = match *self {
+            Arity::Exactly(count) => ("", count),
+            Arity::AtLeast(count) => ("at least ", count),
+        };
+        let plural = if count == 1 { "" } else { "s" };
+        write!(f, "{prefix}{count} argument{plural}")
+    }
+}
+
+pub struct Function {
+    pub name: &'static str,
+    pub arity: Arity,
+    /// The result when it is a rational number, like `sqrt(16)` or `cos(0)`
+    pub exact: fn(&[BigRational]) -> Option<BigRational>,
+    /// Used when there is no exact result, `NaN` or an infinity means the result is not a real number
+    pub approximate: fn(&[f64]) -> f64,
+}
+
+impl Function {
+    /// Calls the function with arguments that match its arity,
+    /// returns `None` when the result is not a real number
+    pub fn call(&self, arguments: &[BigRational]) -> Option<BigRational> {
+        (self.exact)(arguments).or_else(|| {
+            let arguments = arguments
+                .iter()
+                .map(ToPrimitive::to_f64)
+                .collect::<Option<Vec<_>>>()?;
+            BigRational::from_float((self.approximate)(&arguments))
+        })
+    }
+}
+
+fn integer(value: i64) -> BigRational {
+    BigRational::from_integer(BigInt::from(value))
+}
+
+/// The exact result `to` of a function at the single argument `at`, like `cos(0) = 1`
+fn exact_at(arguments: &[BigRational], at: i64, to: i64) -> Option<BigRational> {
+    (arguments[0] == integer(at)).then(|| integer(to))
+}
+
+static FUNCTIONS: phf::Map<&'static str, Function> = phf_map! {
+    "sqrt" => Function {
+        name: "sqrt",
+        arity: Arity::Exactly(1),
+        exact: |arguments| exact_power(&arguments[0], &BigRational::new(1.into(), 2.into())),
+        approximate: |arguments| arguments[0].sqrt(),
+    },
+    "cbrt" => Function {
+        name: "cbrt",
+        arity: Arity::Exactly(1),
+        exact: |arguments| exact_power(&arguments[0], &BigRational::new(1.into(), 3.into())),
+        approximate: |arguments| arguments[0].cbrt(),
+    },
+    "abs" => Function {
+        name: "abs",
+        arity: Arity::Exactly(1),
+        exact: |arguments| Some(arguments[0].abs()),
+        approximate: |arguments| arguments[0].abs(),
+    },
+    "sign" => Function {
+        name: "sign",
+        arity: Arity::Exactly(1),
+        exact: |arguments| Some(arguments[0].signum()),
+        approximate: |arguments| arguments[0].signum(),
+    },
+    "floor" => Function {
+        name: "floor",
+        arity: Arity::Exactly(1),
+        exact: |arguments| Some(arguments[0].floor()),
+        approximate: |arguments| arguments[0].floor(),
+    },
+    "ceil" => Function {
+        name: "ceil",
+        arity: Arity::Exactly(1),
+        exact: |arguments| Some(arguments[0].ceil()),
+        approximate: |arguments| arguments[0].ceil(),
+    },
+    "round" => Function {
+        name: "round",
+        arity: Arity::Exactly(1),
+        exact: |arguments| Some(arguments[0].round()),
+        approximate: |arguments| arguments[0].round(),
+    },
+    "sin" => Function {
+        name: "sin",
+        arity: Arity::Exactly(1),
+        exact: |arguments| exact_at(arguments, 0, 0),
+        approximate: |arguments| arguments[0].sin(),
+    },
+    "cos" => Function {
+        name: "cos",
+        arity: Arity::Exactly(1),
+        exact: |arguments| exact_at(arguments, 0, 1),
+        approximate: |arguments| arguments[0].cos(),
+    },
+    "tan" => Function {
+        name: "tan",
+        arity: Arity::Exactly(1),
+        exact: |arguments| exact_at(arguments, 0, 0),
+        approximate: |arguments| arguments[0].tan(),
+    },
+    "asin" => Function {
+        name: "asin",
+        arity: Arity::Exactly(1),
+        exact: |arguments| exact_at(arguments, 0, 0),
+        approximate: |arguments| arguments[0].asin(),
+    },
+    "acos" => Function {
+        name: "acos",
+        arity: Arity::Exactly(1),
+        exact: |arguments| exact_at(arguments, 1, 0),
+        approximate: |arguments| arguments[0].acos(),
+    },
+    "atan" => Function {
+        name: "atan",
+        arity: Arity::Exactly(1),
+        exact: |arguments| exact_at(arguments, 0, 0),
+        approximate: |arguments| arguments[0].atan(),
+    },
+    "ln" => Function {
+        name: "ln",
+        arity: Arity::Exactly(1),
+        exact: |arguments| exact_at(arguments, 1, 0),
+        approximate: |arguments| arguments[0].ln(),
+    },
+    "exp" => Function {
+        name: "exp",
+        arity: Arity::Exactly(1),
+        exact: |arguments| exact_at(arguments, 0, 1),
+        approximate: |arguments| arguments[0].exp(),
+    },
+    "min" => Function {
+        name: "min",
+        arity: Arity::AtLeast(1),
+        exact: |arguments| arguments.iter().min().cloned(),
+        approximate: |arguments| arguments.iter().copied().fold(f64::INFINITY, f64::min),
+    },
+    "max" => Function {
+        name: "max",
+        arity: Arity::AtLeast(1),
+        exact: |arguments| arguments.iter().max().cloned(),
+        approximate: |arguments| arguments.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+    },
+};
+
+pub fn builtin_function(name: &str) -> Option<&'static Function> {
+    FUNCTIONS.get(name)
+}
+
+pub fn builtin_functions() -> impl Iterator<Item = &'static Function> {
+    FUNCTIONS.values()
+}
@@ -0,0 +1,234 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed};
+
+use crate::{
+    eval_atom, parenthesize, print_atom, print_expression, print_term, Atom, Equation, Expression,
+    Precedence, Printed, Syntax, SystemSolution, Term, GREEK_LETTERS,
+};
+
+/// LaTeX math without the surrounding `$`, with parentheses that grow with what they contain
+struct Latex;
+
+/// `x` stays `x`, `x1` becomes `x_{1}`, Greek letters like `alpha` become `\alpha`
+/// and longer names are kept together as one word like `\mathit{speed}`
+fn latex_name(name: &str) -> String {
+    let letters = name.trim_end_matches(|chr: char| chr.is_ascii_digit());
+    let digits = &name[letters.len()..];
+    let letters = if letters.chars().count() == 1 {
+        letters.to_string()
+    } else if GREEK_LETTERS.contains(&letters) {
+        format!("\\{letters}")
+    } else {
+        format!("\\mathit{{{letters}}}")
+    };
+    if digits.is_empty() || letters.is_empty() {
+        format!("{letters}{digits}")
+    } else {
+        format!("{letters}_{{{digits}}}")
+    }
+}
+
+/// `\sqrt{x}` for an exponent of `1/2`, `\sqrt[n]{x}` for `1/n`
+fn latex_root(base: &Atom, exponent: &Atom) -> Option<String> {
+    if !exponent.is_exact() {
+        return None;
+    }
+    let exponent = eval_atom(exponent).ok()?;
+    if !exponent.numer().is_one() || exponent.denom() <= &BigInt::one() {
+        return None;
+    }
+    let base = print_atom(&Latex, base).0;
+    if exponent.denom() == &BigInt::from(2) {
+        Some(format!("\\sqrt{{{base}}}"))
+    } else {
+        Some(format!("\\sqrt[{}]{{{base}}}", exponent.denom()))
+    }
+}
+
+/// Factors are written next to each other, `\cdot` is only used before a factor starting with a digit,
+/// so `2 \cdot 3^{x}` is not read as `23^{x}`, and a space ends a command like `\alpha x`
+fn juxtapose(left: &str, right: &str) -> String {
+    let ends_with_command = left
+        .trim_end_matches(|chr: char| chr.is_ascii_alphabetic())
+        .ends_with('\\')
+        && left.ends_with(|chr: char| chr.is_ascii_alphabetic());
+    if !left.is_empty() && right.starts_with(|chr: char| chr.is_ascii_digit()) {
+        format!("{left} \\cdot {right}")
+    } else if ends_with_command && right.starts_with(|chr: char| chr.is_ascii_alphabetic()) {
+        format!("{left} {right}")
+    } else {
+        format!("{left}{right}")
+    }
+}
+
+impl Syntax for Latex {
+    fn parentheses(&self, inner: String) -> String {
+        format!("\\left({inner}\\right)")
+    }
+
+    fn number(&self, value: &BigRational) -> Printed {
+        let sign = if value.is_negative() { "-" } else { "" };
+        let string = if value.is_integer() {
+            format!("{sign}{}", value.numer().abs())
+        } else {
+            format!(
+                "{sign}\\frac{{{}}}{{{}}}",
+                value.numer().abs(),
+                value.denom()
+            )
+        };
+        let precedence = if value.is_negative() {
+            Precedence::Unary
+        } else if value.is_integer() {
+            Precedence::Atom
+        } else {
+            Precedence::Power
+        };
+        (string, precedence)
+    }
+
+    fn variable(&self, name: &str) -> String {
+        latex_name(name)
+    }
+
+    // the fraction bar groups both sides, but a fraction needs parentheses as the base of a power,
+    // `\frac{1}{2}^{2}` reads as a power of the denominator
+    fn fraction(&self, numerator: &Expression, denominator: &Expression) -> Printed {
+        (
+            format!(
+                "\\frac{{{}}}{{{}}}",
+                print_expression(self, numerator).0,
+                print_expression(self, denominator).0
+            ),
+            Precedence::Power,
+        )
+    }
+
+    fn power(&self, base: &Atom, exponent: &Atom) -> Printed {
+        match latex_root(base, exponent) {
+            Some(root) => (root, Precedence::Atom),
+            None => (
+                format!(
+                    "{}^{{{}}}",
+                    parenthesize(self, print_atom(self, base), Precedence::Atom).0,
+                    print_atom(self, exponent).0
+                ),
+                Precedence::Power,
+            ),
+        }
+    }
+
+    fn call(&self, name: &str, args: &[Expression]) -> String {
+        let args = args
+            .iter()
+            .map(|argument| print_expression(self, argument).0)
+            .collect::<Vec<_>>();
+        match (name, &args[..]) {
+            ("sqrt", [argument]) => format!("\\sqrt{{{argument}}}"),
+            ("cbrt", [argument]) => format!("\\sqrt[3]{{{argument}}}"),
+            ("abs", [argument]) => format!("\\left|{argument}\\right|"),
+            ("floor", [argument]) => format!("\\left\\lfloor {argument}\\right\\rfloor"),
+            ("ceil", [argument]) => format!("\\left\\lceil {argument}\\right\\rceil"),
+            _ => {
+                // the functions that LaTeX has operators for, every other name is written as an operator
+                let name = match name {
+                    "sin" | "cos" | "tan" | "ln" | "exp" | "min" | "max" => format!("\\{name}"),
+                    "asin" | "acos" | "atan" => format!("\\arc{}", &name[1..]),
+                    _ if name.chars().count() == 1 => name.to_string(),
+                    _ => format!("\\operatorname{{{name}}}"),
+                };
+                format!("{name}\\left({}\\right)", args.join(", "))
+            }
+        }
+    }
+
+    fn product(&self, coefficient: &BigRational, factors: Vec<Printed>) -> Printed {
+        // a factor like `-y` needs parentheses, `x-y` would be a difference
+        let product = factors
+            .into_iter()
+            .map(|factor| parenthesize(self, factor, Precedence::Power).0)
+            .fold(String::new(), |product, factor| {
+                juxtapose(&product, &factor)
+            });
+        if coefficient.is_one() {
+            (product, Precedence::Product)
+        } else if *coefficient == -BigRational::one() {
+            (format!("-{product}"), Precedence::Product)
+        } else {
+            let number = self.number(coefficient).0;
+            (juxtapose(&number, &product), Precedence::Product)
+        }
+    }
+
+    fn operator(&self, operator: &str) -> String {
+        format!(" {operator} ")
+    }
+
+    fn row(&self, pieces: Vec<String>) -> String {
+        pieces.concat()
+    }
+}
+
+impl Atom {
+    /// The atom as LaTeX math, like `\frac{x}{2}`, without the surrounding `$`
+    pub fn to_latex(&self) -> String {
+        print_atom(&Latex, self).0
+    }
+}
+
+impl Term {
+    /// The term as LaTeX math, like `2x^{2}`, without the surrounding `$`
+    pub fn to_latex(&self) -> String {
+        print_term(&Latex, self).0
+    }
+}
+
+impl Expression {
+    /// The expression as LaTeX math, like `\sqrt{x} - 1`, without the surrounding `$`
+    pub fn to_latex(&self) -> String {
+        print_expression(&Latex, self).0
+    }
+}
+
+impl Equation {
+    /// The equation as LaTeX math, like `x^{2} = 4`, without the surrounding `$`
+    pub fn to_latex(&self) -> String {
+        match self {
+            Equation::Equality { left, right, .. } => {
+                format!("{} = {}", left.to_latex(), right.to_latex())
+            }
+        }
+    }
+}
+
+impl SystemSolution {
+    /// The solution as LaTeX math, like `x = 1, y = \frac{1}{2}`, without the surrounding `$`
+    pub fn to_latex(&self) -> String {
+        match self {
+            SystemSolution::Unique(values) => values
+                .iter()
+                .map(|(name, value)| format!("{} = {}", latex_name(name), Latex.number(value).0))
+                .collect::<Vec<_>>()
+                .join(", "),
+            SystemSolution::Inconsistent => "\\text{no solution}".to_string(),
+            SystemSolution::Parametric { free, dependent } => {
+                let mut string = dependent
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", latex_name(name), value.to_latex()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if !string.is_empty() {
+                    string += " ";
+                }
+                string += "\\text{for any } ";
+                string += &free
+                    .iter()
+                    .map(|name| latex_name(name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                string
+            }
+        }
+    }
+}
@@ -0,0 +1,541 @@
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+use crate::{LexerError, SourceLocation, SourceSpan, Token, TokenData, TokenKind, TokenStream};
+
+pub(crate) static GREEK_LETTERS: &[&str] = &[
+    "alpha",
+    "beta",
+    "gamma",
+    "delta",
+    "epsilon",
+    "varepsilon",
+    "zeta",
+    "eta",
+    "theta",
+    "vartheta",
+    "iota",
+    "kappa",
+    "lambda",
+    "mu",
+    "nu",
+    "xi",
+    "pi",
+    "rho",
+    "sigma",
+    "tau",
+    "upsilon",
+    "phi",
+    "varphi",
+    "chi",
+    "psi",
+    "omega",
+    "Gamma",
+    "Delta",
+    "Theta",
+    "Lambda",
+    "Xi",
+    "Pi",
+    "Sigma",
+    "Upsilon",
+    "Phi",
+    "Psi",
+    "Omega",
+];
+
+/// Commands that are names of built-in functions, and the name they have here
+static FUNCTION_COMMANDS: &[(&str, &str)] = &[
+    ("sin", "sin"),
+    ("cos", "cos"),
+    ("tan", "tan"),
+    ("arcsin", "asin"),
+    ("arccos", "acos"),
+    ("arctan", "atan"),
+    ("ln", "ln"),
+    ("exp", "exp"),
+    ("min", "min"),
+    ("max", "max"),
+];
+
+/// Turns LaTeX into the tokens of the plain syntax, so the same parser builds the same AST from both.
+/// `\frac{a}{b}` becomes `((a)/(b))`, `x^{2}` becomes `x^(2)`, `\sqrt{x}` becomes `sqrt((x))`
+/// and juxtaposition that the plain syntax does not read as multiplication, like `xy`, gets a `*`.
+/// The tokens, including the ones that are made up like the `/` of `\frac`, have spans in the LaTeX source
+struct Scanner {
+    filepath: String,
+    source: Vec<char>,
+    position: usize,
+    location: SourceLocation,
+    tokens: Vec<Token>,
+}
+
+impl Scanner {
+    fn span_from(&self, start: SourceLocation) -> SourceSpan {
+        SourceSpan {
+            filepath: self.filepath.clone(),
+            start,
+            end: self.location.clone(),
+        }
+    }
+
+    /// An empty span at the current location, for tokens that close something
+    fn empty_span(&self) -> SourceSpan {
+        self.span_from(self.location.clone())
+    }
+
+    fn push(&mut self, kind: TokenKind, data: TokenData, span: SourceSpan) {
+        let previous = self.tokens.last().map(|token| &token.kind);
+        let ends_operand = matches!(
+            previous,
+            Some(TokenKind::Number | TokenKind::Name | TokenKind::CloseParenthesis)
+        );
+        let starts_operand = matches!(
+            kind,
+            TokenKind::Number | TokenKind::Name | TokenKind::OpenParenthesis
+        );
+        // a name followed by a parenthesis is left to the parser, which knows whether it is a call
+        let call = previous == Some(&TokenKind::Name) && kind == TokenKind::OpenParenthesis;
+        if ends_operand && starts_operand && !call {
+            self.tokens.push(Token {
+                kind: TokenKind::Multiply,
+                data: TokenData::None,
+                span: SourceSpan {
+                    filepath: span.filepath.clone(),
+                    start: span.start.clone(),
+                    end: span.start.clone(),
+                },
+            });
+        }
+        self.tokens.push(Token { kind, data, span });
+    }
+
+    fn push_kind(&mut self, kind: TokenKind, span: SourceSpan) {
+        self.push(kind, TokenData::None, span);
+    }
+
+    fn push_name(&mut self, name: &str, span: SourceSpan) {
+        self.push(TokenKind::Name, TokenData::String(name.to_string()), span);
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek_char().is_whitespace() {
+            self.next_char();
+        }
+    }
+
+    fn scan(&mut self) -> Result<(), LexerError> {
+        loop {
+            self.skip_whitespace();
+            if self.peek_char() == '\0' {
+                return Ok(());
+            }
+            self.scan_item()?;
+        }
+    }
+
+    /// Scans a group in braces, which becomes a parenthesized group
+    fn scan_group(&mut self) -> Result<(), LexerError> {
+        let start = self.location.clone();
+        self.next_char();
+        let open = self.span_from(start);
+        self.push_kind(TokenKind::OpenParenthesis, open.clone());
+        loop {
+            self.skip_whitespace();
+            match self.peek_char() {
+                '\0' => return Err(LexerError::UnclosedBrace { span: open }),
+                '}' => break,
+                _ => self.scan_item()?,
+            }
+        }
+        let start = self.location.clone();
+        self.next_char();
+        let close = self.span_from(start);
+        self.push_kind(TokenKind::CloseParenthesis, close);
+        Ok(())
+    }
+
+    /// Scans the argument of a command or `^`, which is a group in braces or a single character or command,
+    /// `parenthesize` puts a single character or command in parentheses like a group
+    fn scan_argument(&mut self, after: &str, parenthesize: bool) -> Result<(), LexerError> {
+        self.skip_whitespace();
+        let start = self.location.clone();
+        let chr = self.peek_char();
+        if chr == '{' {
+            return self.scan_group();
+        }
+        if !(chr.is_alphanumeric() || chr == '\\') {
+            return Err(LexerError::MissingArgument {
+                span: self.span_from(start),
+                after: after.to_string(),
+            });
+        }
+        if parenthesize {
+            self.push_kind(TokenKind::OpenParenthesis, self.empty_span());
+        }
+        if chr == '\\' {
+            self.scan_item()?;
+        } else {
+            self.next_char();
+            let span = self.span_from(start);
+            match chr.to_digit(10) {
+                Some(digit) => self.push(
+                    TokenKind::Number,
+                    TokenData::Number(BigRational::from_integer(digit.into())),
+                    span,
+                ),
+                None => self.push_name(&chr.to_string(), span),
+            }
+        }
+        if parenthesize {
+            self.push_kind(TokenKind::CloseParenthesis, self.empty_span());
+        }
+        Ok(())
+    }
+
+    fn scan_item(&mut self) -> Result<(), LexerError> {
+        let start = self.location.clone();
+        let chr = self.peek_char();
+        if chr.is_ascii_digit() {
+            self.scan_number();
+        } else if chr.is_alphabetic() {
+            // every letter is a name of its own, `xy` is `x*y`
+            self.next_char();
+            let mut name = chr.to_string();
+            self.scan_subscript(&mut name)?;
+            let span = self.span_from(start);
+            self.push_name(&name, span);
+        } else if chr == '{' {
+            self.scan_group()?;
+        } else if chr == '\\' {
+            self.scan_command()?;
+        } else {
+            self.next_char();
+            let kind = match chr {
+                '(' | '[' => TokenKind::OpenParenthesis,
+                ')' | ']' => TokenKind::CloseParenthesis,
+                '+' => TokenKind::Plus,
+                '-' => TokenKind::Minus,
+                '*' => TokenKind::Multiply,
+                '/' => TokenKind::Divide,
+                '=' => TokenKind::Equal,
+                ',' => TokenKind::Comma,
+                '^' => TokenKind::Power,
+                _ => {
+                    return Err(LexerError::UnexpectedCharacter {
+                        span: self.span_from(start),
+                        character: chr,
+                    })
+                }
+            };
+            self.push_kind(kind.clone(), self.span_from(start));
+            if kind == TokenKind::Power {
+                self.scan_argument("^", false)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_number(&mut self) {
+        let start = self.location.clone();
+        let base = BigInt::from(10);
+        let mut value = BigInt::from(0);
+        let mut denominator = BigInt::from(1);
+        while self.peek_char().is_ascii_digit() {
+            value = value * &base + BigInt::from(self.next_char() as u8 - b'0');
+        }
+        if self.peek_char() == '.' {
+            self.next_char();
+            while self.peek_char().is_ascii_digit() {
+                denominator *= &base;
+                value = value * &base + BigInt::from(self.next_char() as u8 - b'0');
+            }
+        }
+        let span = self.span_from(start);
+        self.push(
+            TokenKind::Number,
+            TokenData::Number(BigRational::new(value, denominator)),
+            span,
+        );
+    }
+
+    /// Appends a subscript like the `1` of `x_1` or `x_{12}` to the name, so it is read as `x1`
+    fn scan_subscript(&mut self, name: &mut String) -> Result<(), LexerError> {
+        if self.peek_char() != '_' {
+            return Ok(());
+        }
+        let start = self.location.clone();
+        self.next_char();
+        let braced = self.peek_char() == '{';
+        if braced {
+            self.next_char();
+        }
+        let mut subscript = String::new();
+        while self.peek_char().is_alphanumeric() && (braced || subscript.is_empty()) {
+            subscript.push(self.next_char());
+        }
+        if subscript.is_empty() || (braced && self.next_char() != '}') {
+            return Err(LexerError::MissingArgument {
+                span: self.span_from(start),
+                after: "_".to_string(),
+            });
+        }
+        name.push_str(&subscript);
+        Ok(())
+    }
+
+    /// The name of a command after the `\`, which is either letters or a single other character like `\,`
+    fn scan_command_name(&mut self) -> String {
+        self.next_char();
+        if !self.peek_char().is_alphabetic() {
+            return self.next_char().to_string();
+        }
+        let mut command = String::new();
+        while self.peek_char().is_alphabetic() {
+            command.push(self.next_char());
+        }
+        command
+    }
+
+    /// The letters of a name in braces, like the `speed` of `\mathit{speed}`
+    fn scan_braced_name(&mut self, after: &str) -> Result<String, LexerError> {
+        self.skip_whitespace();
+        let start = self.location.clone();
+        let mut name = String::new();
+        if self.peek_char() == '{' {
+            self.next_char();
+            while self.peek_char().is_alphanumeric() {
+                name.push(self.next_char());
+            }
+            if self.peek_char() == '}' {
+                self.next_char();
+                if !name.is_empty() {
+                    return Ok(name);
+                }
+            }
+        }
+        Err(LexerError::MissingArgument {
+            span: self.span_from(start),
+            after: after.to_string(),
+        })
+    }
+
+    fn scan_command(&mut self) -> Result<(), LexerError> {
+        let start = self.location.clone();
+        let command = self.scan_command_name();
+        let span = self.span_from(start.clone());
+        let after = format!("\\{command}");
+        match command.as_str() {
+            // spacing
+            "," | ";" | ":" | "!" | " " | "quad" | "qquad" => {}
+            "cdot" | "times" | "ast" => self.push_kind(TokenKind::Multiply, span),
+            "div" => self.push_kind(TokenKind::Divide, span),
+            "frac" | "dfrac" | "tfrac" => {
+                self.push_kind(TokenKind::OpenParenthesis, span.clone());
+                self.scan_argument(&after, false)?;
+                self.push_kind(TokenKind::Divide, span);
+                self.scan_argument(&after, false)?;
+                self.push_kind(TokenKind::CloseParenthesis, self.empty_span());
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                if self.peek_char() == '[' {
+                    self.scan_root(span)?;
+                } else {
+                    self.push_name("sqrt", span);
+                    self.scan_argument(&after, true)?;
+                }
+            }
+            "left" | "right" => self.scan_delimiter(&command)?,
+            "lfloor" | "lceil" => {
+                self.push_name(&command[1..], span.clone());
+                self.push_kind(TokenKind::OpenParenthesis, span);
+            }
+            "rfloor" | "rceil" => self.push_kind(TokenKind::CloseParenthesis, span),
+            "operatorname" | "mathit" | "mathrm" => {
+                let name = self.scan_braced_name(&after)?;
+                let span = self.span_from(start);
+                self.push_name(&name, span);
+            }
+            _ if GREEK_LETTERS.contains(&command.as_str()) => {
+                let mut name = command.clone();
+                self.scan_subscript(&mut name)?;
+                let span = self.span_from(start);
+                self.push_name(&name, span);
+            }
+            _ => match FUNCTION_COMMANDS
+                .iter()
+                .find(|(latex, _)| *latex == command)
+            {
+                Some((_, name)) => {
+                    self.push_name(name, span);
+                    // `\sin x` is `sin(x)`, like `\sin(x)` and `\sin\left(x\right)`
+                    self.skip_whitespace();
+                    if !(self.peek_char() == '(' || self.starts_with("\\left")) {
+                        self.scan_argument(&after, true)?;
+                    }
+                }
+                None => return Err(LexerError::UnknownCommand { span, command }),
+            },
+        }
+        Ok(())
+    }
+
+    /// `\sqrt[n]{x}` becomes `((x)^(1/(n)))`, the index is scanned first but its tokens go after the radicand
+    fn scan_root(&mut self, span: SourceSpan) -> Result<(), LexerError> {
+        self.push_kind(TokenKind::OpenParenthesis, span.clone());
+        let open = self.location.clone();
+        self.next_char();
+        let mark = self.tokens.len();
+        self.push_kind(TokenKind::OpenParenthesis, self.span_from(open.clone()));
+        loop {
+            self.skip_whitespace();
+            match self.peek_char() {
+                '\0' => {
+                    return Err(LexerError::MissingArgument {
+                        span: self.span_from(open),
+                        after: "\\sqrt".to_string(),
+                    })
+                }
+                ']' => break,
+                _ => self.scan_item()?,
+            }
+        }
+        let close = self.location.clone();
+        self.next_char();
+        self.push_kind(TokenKind::CloseParenthesis, self.span_from(close));
+        let index = self.tokens.split_off(mark);
+        self.scan_argument("\\sqrt", true)?;
+        self.push_kind(TokenKind::Power, span.clone());
+        self.push_kind(TokenKind::OpenParenthesis, span.clone());
+        self.push(
+            TokenKind::Number,
+            TokenData::Number(BigRational::from_integer(1.into())),
+            span.clone(),
+        );
+        self.push_kind(TokenKind::Divide, span);
+        self.tokens.extend(index);
+        self.push_kind(TokenKind::CloseParenthesis, self.empty_span());
+        self.push_kind(TokenKind::CloseParenthesis, self.empty_span());
+        Ok(())
+    }
+
+    /// The delimiter after `\left` or `\right`, `\left| x \right|` is `abs(x)`
+    fn scan_delimiter(&mut self, side: &str) -> Result<(), LexerError> {
+        self.skip_whitespace();
+        let start = self.location.clone();
+        let delimiter = if self.peek_char() == '\\' {
+            self.scan_command_name()
+        } else {
+            self.next_char().to_string()
+        };
+        let span = self.span_from(start.clone());
+        match (side, delimiter.as_str()) {
+            ("left", "(" | "[") => self.push_kind(TokenKind::OpenParenthesis, span),
+            ("left", "|" | "lfloor" | "lceil") => {
+                let name = match delimiter.as_str() {
+                    "|" => "abs",
+                    "lfloor" => "floor",
+                    _ => "ceil",
+                };
+                self.push_name(name, span.clone());
+                self.push_kind(TokenKind::OpenParenthesis, span);
+            }
+            ("right", ")" | "]" | "|" | "rfloor" | "rceil") => {
+                self.push_kind(TokenKind::CloseParenthesis, span)
+            }
+            _ => {
+                return Err(LexerError::MissingArgument {
+                    span,
+                    after: format!("\\{side}"),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(i, chr)| self.source.get(self.position + i) == Some(&chr))
+    }
+
+    fn peek_char(&self) -> char {
+        if self.position < self.source.len() {
+            self.source[self.position]
+        } else {
+            '\0'
+        }
+    }
+
+    fn next_char(&mut self) -> char {
+        let current = self.peek_char();
+        if current != '\0' {
+            self.position += 1;
+            self.location.column += 1;
+            if current == '\n' {
+                self.location.line += 1;
+                self.location.column = 1;
+            }
+        }
+        current
+    }
+}
+
+/// A lexer for a subset of LaTeX math, like `\frac{x+1}{2} = 3`, to use with the same parsing functions as `Lexer`
+#[derive(Clone)]
+pub struct LatexLexer {
+    tokens: Rc<Vec<Token>>,
+    /// The error that stopped scanning, returned instead of any token,
+    /// since a missing `}` or argument changes what every token before it means
+    error: Option<LexerError>,
+    position: usize,
+    location: SourceLocation,
+    previous: Option<TokenKind>,
+}
+
+impl LatexLexer {
+    pub fn new(filepath: &str, source: &str) -> LatexLexer {
+        let mut scanner = Scanner {
+            filepath: filepath.to_string(),
+            source: source.chars().collect(),
+            position: 0,
+            location: SourceLocation { line: 1, column: 1 },
+            tokens: vec![],
+        };
+        let error = scanner.scan().err();
+        let eof = scanner.empty_span();
+        scanner.push_kind(TokenKind::EOF, eof);
+        LatexLexer {
+            tokens: scanner.tokens.into(),
+            error,
+            position: 0,
+            location: SourceLocation { line: 1, column: 1 },
+            previous: None,
+        }
+    }
+}
+
+impl TokenStream for LatexLexer {
+    fn location(&self) -> &SourceLocation {
+        &self.location
+    }
+
+    fn previous_kind(&self) -> Option<&TokenKind> {
+        self.previous.as_ref()
+    }
+
+    fn next_token(&mut self) -> Result<Token, LexerError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        let token = self.tokens[self.position].clone();
+        if token.kind != TokenKind::EOF {
+            self.position += 1;
+        }
+        self.location = token.span.end.clone();
+        self.previous = Some(token.kind.clone());
+        Ok(token)
+    }
+}
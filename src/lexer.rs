@@ -17,13 +17,36 @@ static SINGLE_CHAR_TOKENS: phf::Map<char, TokenKind> = phf_map! {
     '-' => TokenKind::Minus,
     '*' => TokenKind::Multiply,
     '/' => TokenKind::Divide,
+    '^' => TokenKind::Power,
     '=' => TokenKind::Equal,
+    ',' => TokenKind::Comma,
 };
 
 #[derive(Clone, PartialEq, Debug, Display, IsVariant, EnumAsInner)]
 pub enum LexerError {
     #[display(fmt = "{span}: Unexpected character '{character}'")]
     UnexpectedCharacter { span: SourceSpan, character: char },
+    #[display(fmt = "{span}: Unknown LaTeX command '\\{command}'")]
+    UnknownCommand { span: SourceSpan, command: String },
+    #[display(fmt = "{span}: Expected an argument after '{after}'")]
+    MissingArgument { span: SourceSpan, after: String },
+    #[display(fmt = "{span}: Unclosed '{{'")]
+    UnclosedBrace { span: SourceSpan },
+}
+
+/// Where the parser gets its tokens from, `Lexer` for plain input and `LatexLexer` for LaTeX
+pub trait TokenStream: Clone {
+    /// The location right after the last consumed token
+    fn location(&self) -> &SourceLocation;
+
+    /// The kind of the last consumed token
+    fn previous_kind(&self) -> Option<&TokenKind>;
+
+    fn next_token(&mut self) -> Result<Token, LexerError>;
+
+    fn peek_token(&self) -> Result<Token, LexerError> {
+        self.clone().next_token()
+    }
 }
 
 #[derive(Clone)]
@@ -32,6 +55,9 @@ pub struct Lexer {
     source: Rc<Vec<char>>,
     position: usize,
     location: SourceLocation,
+    previous: Option<TokenKind>,
+    /// Whether names can start with `?`, like the wildcards of rewrite rules
+    wildcards: bool,
 }
 
 impl Lexer {
@@ -41,14 +67,20 @@ impl Lexer {
             source: source.chars().collect::<Vec<_>>().into(),
             position: 0,
             location: SourceLocation { line: 1, column: 1 },
+            previous: None,
+            wildcards: false,
         }
     }
 
-    pub fn peek_token(&self) -> Result<Token, LexerError> {
-        self.clone().next_token()
+    /// A lexer that reads `?a` as a name, for the patterns of rewrite rules
+    pub(crate) fn with_wildcards(filepath: &str, source: &str) -> Lexer {
+        Lexer {
+            wildcards: true,
+            ..Lexer::new(filepath, source)
+        }
     }
 
-    pub fn next_token(&mut self) -> Result<Token, LexerError> {
+    fn lex_token(&mut self) -> Result<Token, LexerError> {
         while self.peek_char().is_whitespace() {
             self.next_char();
         }
@@ -60,14 +92,14 @@ impl Lexer {
             let mut denominator = BigInt::from(1);
 
             while self.peek_char().is_ascii_digit() {
-                let digit_value = self.next_char() as u8 - '0' as u8;
+                let digit_value = self.next_char() as u8 - b'0';
                 value *= base.clone();
                 value += BigInt::from(digit_value);
             }
             if self.peek_char() == '.' {
                 self.next_char();
                 while self.peek_char().is_ascii_digit() {
-                    let digit_value = self.next_char() as u8 - '0' as u8;
+                    let digit_value = self.next_char() as u8 - b'0';
                     denominator *= base.clone();
                     value *= base.clone();
                     value += BigInt::from(digit_value);
@@ -83,8 +115,18 @@ impl Lexer {
                     end: self.location.clone(),
                 },
             })
-        } else if self.peek_char().is_alphanumeric() {
+        } else if self.peek_char().is_alphanumeric()
+            || (self.wildcards
+                && self.peek_char() == '?'
+                && self
+                    .source
+                    .get(self.position + 1)
+                    .is_some_and(|chr| chr.is_alphanumeric()))
+        {
             let mut name = String::new();
+            if self.peek_char() == '?' {
+                name.push(self.next_char());
+            }
             while self.peek_char().is_alphanumeric() {
                 name.push(self.next_char());
             }
@@ -97,6 +139,28 @@ impl Lexer {
                     end: self.location.clone(),
                 },
             })
+        } else if self.peek_char() == ':' {
+            self.next_char();
+            if self.peek_char() != '=' {
+                return Err(LexerError::UnexpectedCharacter {
+                    span: SourceSpan {
+                        filepath: self.filepath.clone(),
+                        start: start_location,
+                        end: self.location.clone(),
+                    },
+                    character: ':',
+                });
+            }
+            self.next_char();
+            Ok(Token {
+                kind: TokenKind::Define,
+                data: TokenData::None,
+                span: SourceSpan {
+                    filepath: self.filepath.clone(),
+                    start: start_location,
+                    end: self.location.clone(),
+                },
+            })
         } else {
             let chr = self.next_char();
             if SINGLE_CHAR_TOKENS.contains_key(&chr) {
@@ -143,3 +207,19 @@ impl Lexer {
         current
     }
 }
+
+impl TokenStream for Lexer {
+    fn location(&self) -> &SourceLocation {
+        &self.location
+    }
+
+    fn previous_kind(&self) -> Option<&TokenKind> {
+        self.previous.as_ref()
+    }
+
+    fn next_token(&mut self) -> Result<Token, LexerError> {
+        let token = self.lex_token()?;
+        self.previous = Some(token.kind.clone());
+        Ok(token)
+    }
+}
@@ -1,15 +1,54 @@
+#![allow(clippy::result_large_err)]
+
+mod definitions;
+mod diagnostic;
+mod display;
+mod egraph;
+mod environment;
 mod equation;
 mod eval;
+mod functions;
+mod latex;
+mod latex_lexer;
 mod lexer;
 mod location;
+mod mathml;
 mod parsing;
+mod pattern;
+mod polynomial;
+mod printer;
+mod roots;
+mod rules;
+#[cfg(feature = "serde")]
+mod serialization;
+mod sexpr;
 mod simplify;
+mod solve;
+mod system;
 mod token;
 
+pub use definitions::*;
+pub use diagnostic::*;
+pub use display::*;
+pub use egraph::*;
+pub use environment::*;
 pub use equation::*;
 pub use eval::*;
+pub use functions::*;
+pub use latex_lexer::*;
 pub use lexer::*;
 pub use location::*;
+pub use mathml::*;
 pub use parsing::*;
+use pattern::*;
+pub use polynomial::*;
+use printer::*;
+pub use roots::*;
+pub use rules::*;
+#[cfg(feature = "serde")]
+pub use serialization::*;
+pub use sexpr::*;
 pub use simplify::*;
+pub use solve::*;
+pub use system::*;
 pub use token::*;
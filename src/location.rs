@@ -1,6 +1,7 @@
 use derive_more::Display;
 
-#[derive(Clone, PartialEq, Debug, Display)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Display)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "{line}:{column}")]
 pub struct SourceLocation {
     pub line: usize,
@@ -8,10 +9,43 @@ pub struct SourceLocation {
 }
 
 #[derive(Clone, PartialEq, Debug, Display)]
-// #[display(fmt = "{filepath}:{start}-{end}")]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "{filepath}:{start}")]
 pub struct SourceSpan {
     pub filepath: String,
     pub start: SourceLocation,
     pub end: SourceLocation,
 }
+
+impl SourceSpan {
+    /// The smallest span that covers both spans
+    pub fn merge(&self, other: &SourceSpan) -> SourceSpan {
+        SourceSpan {
+            filepath: self.filepath.clone(),
+            start: self.start.clone().min(other.start.clone()),
+            end: self.end.clone().max(other.end.clone()),
+        }
+    }
+
+    /// Merges all of the spans, or returns `None` when there are none
+    pub fn merge_all<'a>(spans: impl IntoIterator<Item = &'a SourceSpan>) -> Option<SourceSpan> {
+        spans
+            .into_iter()
+            .fold(None, |merged: Option<SourceSpan>, span| {
+                Some(merged.map_or_else(|| span.clone(), |merged| merged.merge(span)))
+            })
+    }
+}
+
+/// Merges the spans that are present, for nodes built from other nodes
+pub fn merge_spans<'a>(
+    spans: impl IntoIterator<Item = &'a Option<SourceSpan>>,
+) -> Option<SourceSpan> {
+    SourceSpan::merge_all(spans.into_iter().flatten())
+}
+
+/// Formats an optional span as the `"{span}: "` prefix of an error message
+pub(crate) fn span_prefix(span: &Option<SourceSpan>) -> String {
+    span.as_ref()
+        .map_or_else(String::new, |span| format!("{span}: "))
+}
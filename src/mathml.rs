@@ -0,0 +1,670 @@
+use derive_more::{Display, IsVariant};
+use enum_as_inner::EnumAsInner;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+use roxmltree::{Document, Node};
+
+use crate::{
+    atom_expression, builtin_function, eval_atom, into_atom, into_term, merge_spans, negative_one,
+    parenthesize, print_atom, print_expression, term_expression, Arity, Atom, AtomKind, Equation,
+    Expression, Precedence, Printed, SourceLocation, SourceSpan, Syntax, Term,
+};
+
+const NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+
+/// An error in Content MathML input, `path` is the element it is about, like `/math/apply/apply[2]/cn`
+#[derive(Clone, PartialEq, Debug, Display, IsVariant, EnumAsInner)]
+pub enum MathmlError {
+    #[display(fmt = "{span}: Invalid XML: {message}")]
+    InvalidXml { message: String, span: SourceSpan },
+    #[display(fmt = "{path}: Unknown element <{name}>")]
+    UnknownElement { path: String, name: String },
+    #[display(fmt = "{path}: Expected an element")]
+    MissingElement { path: String },
+    #[display(fmt = "{path}: <{operator}/> takes {expected}, but got {got}")]
+    WrongOperandCount {
+        path: String,
+        operator: String,
+        expected: Arity,
+        got: usize,
+    },
+    #[display(fmt = "{path}: Invalid number '{text}'")]
+    InvalidNumber { path: String, text: String },
+    #[display(fmt = "{path}: Expected a name")]
+    MissingName { path: String },
+    #[display(fmt = "{path}: Invalid name '{name}'")]
+    InvalidName { path: String, name: String },
+    #[display(fmt = "{path}: Expected an equation, which is an <apply> of <eq/>")]
+    ExpectedEquation { path: String },
+    #[display(fmt = "{path}: <eq/> can only be used for the whole equation")]
+    NestedEquation { path: String },
+}
+
+/// Content MathML operators that are built-in functions, and the name of the function
+static FUNCTION_ELEMENTS: &[(&str, &str)] = &[
+    ("abs", "abs"),
+    ("floor", "floor"),
+    ("ceiling", "ceil"),
+    ("sin", "sin"),
+    ("cos", "cos"),
+    ("tan", "tan"),
+    ("arcsin", "asin"),
+    ("arccos", "acos"),
+    ("arctan", "atan"),
+    ("ln", "ln"),
+    ("exp", "exp"),
+    ("min", "min"),
+    ("max", "max"),
+];
+
+struct Reader<'a, 'input> {
+    filepath: &'a str,
+    document: &'a Document<'input>,
+}
+
+impl<'a, 'input> Reader<'a, 'input> {
+    fn location(&self, position: usize) -> SourceLocation {
+        let position = self.document.text_pos_at(position);
+        SourceLocation {
+            line: position.row as usize,
+            column: position.col as usize,
+        }
+    }
+
+    fn span(&self, node: Node) -> SourceSpan {
+        let range = node.range();
+        SourceSpan {
+            filepath: self.filepath.to_string(),
+            start: self.location(range.start),
+            end: self.location(range.end),
+        }
+    }
+
+    fn expression(&self, node: Node, path: &str) -> Result<Expression, MathmlError> {
+        let span = Some(self.span(node));
+        match node.tag_name().name() {
+            "cn" => Ok(atom_expression(Atom {
+                kind: AtomKind::Number(number(node, path)?),
+                span,
+            })),
+            "ci" => {
+                let name = node.text().unwrap_or_default().trim();
+                if name.is_empty() {
+                    return Err(MathmlError::MissingName {
+                        path: path.to_string(),
+                    });
+                }
+                let mut chars = name.chars();
+                if !chars.next().is_some_and(char::is_alphabetic)
+                    || !chars.all(char::is_alphanumeric)
+                {
+                    return Err(MathmlError::InvalidName {
+                        path: path.to_string(),
+                        name: name.to_string(),
+                    });
+                }
+                Ok(atom_expression(Atom {
+                    kind: AtomKind::Variable(name.to_string()),
+                    span,
+                }))
+            }
+            "pi" => Ok(atom_expression(Atom {
+                kind: AtomKind::Variable("pi".to_string()),
+                span,
+            })),
+            "exponentiale" => Ok(atom_expression(Atom {
+                kind: AtomKind::Variable("e".to_string()),
+                span,
+            })),
+            // the annotations of `<semantics>` are left out, only the first child is the expression itself
+            "semantics" => {
+                let child = first_child(node, path)?;
+                self.expression(child, &child_path(path, child))
+            }
+            "apply" => self.apply(node, path),
+            name => Err(MathmlError::UnknownElement {
+                path: path.to_string(),
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    fn apply(&self, node: Node, path: &str) -> Result<Expression, MathmlError> {
+        let span = Some(self.span(node));
+        let operator = first_child(node, path)?;
+        let operator_name = operator.tag_name().name();
+        // a `<degree>` is a qualifier of `<root/>` and not one of its operands
+        let mut degree = None;
+        let mut operands = vec![];
+        for child in node.children().filter(Node::is_element).skip(1) {
+            let operand_path = child_path(path, child);
+            if child.tag_name().name() == "degree" {
+                let value = first_child(child, &operand_path)?;
+                degree = Some(self.expression(value, &child_path(&operand_path, value))?);
+            } else {
+                operands.push(self.expression(child, &operand_path)?);
+            }
+        }
+        let check_operands = |expected: Arity| {
+            if expected.accepts(operands.len()) {
+                Ok(())
+            } else {
+                Err(MathmlError::WrongOperandCount {
+                    path: path.to_string(),
+                    operator: operator_name.to_string(),
+                    expected,
+                    got: operands.len(),
+                })
+            }
+        };
+        let expression = match operator_name {
+            "plus" => {
+                check_operands(Arity::AtLeast(1))?;
+                Expression {
+                    terms: operands
+                        .into_iter()
+                        .flat_map(|operand| operand.terms)
+                        .collect(),
+                    span,
+                }
+            }
+            "minus" if operands.len() == 1 => {
+                let operand = into_term(operands.remove(0));
+                term_expression(Term {
+                    atoms: std::iter::once(negative_one(operand.span.clone()))
+                        .chain(operand.atoms)
+                        .collect(),
+                    span,
+                })
+            }
+            "minus" => {
+                check_operands(Arity::Exactly(2))?;
+                let subtrahend = operands.pop().unwrap();
+                let minuend = operands.pop().unwrap();
+                Expression {
+                    terms: minuend
+                        .terms
+                        .into_iter()
+                        .chain(subtrahend.terms.into_iter().map(|mut term| {
+                            term.atoms.push(negative_one(term.span.clone()));
+                            term
+                        }))
+                        .collect(),
+                    span,
+                }
+            }
+            "times" => {
+                check_operands(Arity::AtLeast(1))?;
+                term_expression(Term {
+                    atoms: operands
+                        .into_iter()
+                        .flat_map(|operand| into_term(operand).atoms)
+                        .collect(),
+                    span,
+                })
+            }
+            "divide" => {
+                check_operands(Arity::Exactly(2))?;
+                let denominator = operands.pop().unwrap();
+                let numerator = operands.pop().unwrap();
+                atom_expression(Atom {
+                    kind: AtomKind::Fraction {
+                        numerator,
+                        denominator,
+                    },
+                    span,
+                })
+            }
+            "power" => {
+                check_operands(Arity::Exactly(2))?;
+                let exponent = operands.pop().unwrap();
+                let base = operands.pop().unwrap();
+                atom_expression(Atom {
+                    kind: AtomKind::Power {
+                        base: Box::new(into_atom(base)),
+                        exponent: Box::new(into_atom(exponent)),
+                    },
+                    span,
+                })
+            }
+            "root" => {
+                check_operands(Arity::Exactly(1))?;
+                let radicand = operands.pop().unwrap();
+                let kind = match degree {
+                    // a square root is the `sqrt` function, like it is in the plain syntax
+                    None => AtomKind::Call {
+                        name: "sqrt".to_string(),
+                        args: vec![radicand],
+                    },
+                    Some(degree) => AtomKind::Power {
+                        base: Box::new(into_atom(radicand)),
+                        exponent: Box::new(Atom {
+                            span: degree.span.clone(),
+                            kind: AtomKind::Fraction {
+                                numerator: atom_expression(Atom::new(AtomKind::Number(
+                                    BigRational::one(),
+                                ))),
+                                denominator: degree,
+                            },
+                        }),
+                    },
+                };
+                atom_expression(Atom { kind, span })
+            }
+            "eq" => {
+                return Err(MathmlError::NestedEquation {
+                    path: path.to_string(),
+                })
+            }
+            _ => {
+                let function = FUNCTION_ELEMENTS
+                    .iter()
+                    .find(|(element, _)| *element == operator_name)
+                    .and_then(|(_, name)| builtin_function(name))
+                    .ok_or_else(|| MathmlError::UnknownElement {
+                        path: child_path(path, operator),
+                        name: operator_name.to_string(),
+                    })?;
+                check_operands(function.arity)?;
+                atom_expression(Atom {
+                    kind: AtomKind::Call {
+                        name: function.name.to_string(),
+                        args: operands,
+                    },
+                    span,
+                })
+            }
+        };
+        Ok(expression)
+    }
+
+    fn equation(&self, node: Node, path: &str) -> Result<Equation, MathmlError> {
+        let is_equation = node.tag_name().name() == "apply"
+            && node
+                .children()
+                .find(Node::is_element)
+                .is_some_and(|operator| operator.tag_name().name() == "eq");
+        if !is_equation {
+            return Err(MathmlError::ExpectedEquation {
+                path: path.to_string(),
+            });
+        }
+        let operands = node
+            .children()
+            .filter(Node::is_element)
+            .skip(1)
+            .collect::<Vec<_>>();
+        let [left, right] = operands[..] else {
+            return Err(MathmlError::WrongOperandCount {
+                path: path.to_string(),
+                operator: "eq".to_string(),
+                expected: Arity::Exactly(2),
+                got: operands.len(),
+            });
+        };
+        let left = self.expression(left, &child_path(path, left))?;
+        let right = self.expression(right, &child_path(path, right))?;
+        let span = merge_spans([&left.span, &right.span]);
+        Ok(Equation::Equality { left, right, span })
+    }
+}
+
+fn first_child<'a, 'input>(
+    node: Node<'a, 'input>,
+    path: &str,
+) -> Result<Node<'a, 'input>, MathmlError> {
+    node.children()
+        .find(Node::is_element)
+        .ok_or_else(|| MathmlError::MissingElement {
+            path: path.to_string(),
+        })
+}
+
+/// The path of an element, with the position among its siblings of the same name when there are several
+fn child_path(path: &str, child: Node) -> String {
+    let name = child.tag_name().name();
+    let same_name = |sibling: &Node| sibling.is_element() && sibling.tag_name().name() == name;
+    let siblings = child
+        .parent()
+        .map_or(1, |parent| parent.children().filter(same_name).count());
+    if siblings > 1 {
+        let index = child.prev_siblings().filter(same_name).count();
+        format!("{path}/{name}[{index}]")
+    } else {
+        format!("{path}/{name}")
+    }
+}
+
+/// A decimal number like `-2.5`
+fn decimal(text: &str) -> Option<BigRational> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if integer.is_empty() && fraction.is_empty()
+        || !(integer.chars().chain(fraction.chars())).all(|chr| chr.is_ascii_digit())
+    {
+        return None;
+    }
+    let value = format!("{integer}{fraction}").parse::<BigInt>().ok()?;
+    let value = BigRational::new(value, BigInt::from(10).pow(fraction.len() as u32));
+    Some(if negative { -value } else { value })
+}
+
+/// A `<cn>`, which is a decimal number, or a fraction like `<cn type="rational">1<sep/>3</cn>`
+fn number(node: Node, path: &str) -> Result<BigRational, MathmlError> {
+    let texts = node
+        .children()
+        .filter(Node::is_text)
+        .map(|text| text.text().unwrap_or_default().trim())
+        .collect::<Vec<_>>();
+    let invalid = || MathmlError::InvalidNumber {
+        path: path.to_string(),
+        text: texts.join(" "),
+    };
+    match (node.attribute("type"), &texts[..]) {
+        (None | Some("integer" | "real" | "double"), [text]) => decimal(text).ok_or_else(invalid),
+        (Some("rational"), [numerator, denominator]) => {
+            let numerator = decimal(numerator).filter(|value| value.is_integer());
+            let denominator = decimal(denominator).filter(|value| value.is_integer());
+            match (numerator, denominator) {
+                (Some(numerator), Some(denominator)) if !denominator.is_zero() => {
+                    Ok(numerator / denominator)
+                }
+                _ => Err(invalid()),
+            }
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// The element with the expression or equation, which is the root or the only child of `<math>`
+fn root<'a, 'input>(
+    document: &'a Document<'input>,
+) -> Result<(Node<'a, 'input>, String), MathmlError> {
+    let root = document.root_element();
+    let path = format!("/{}", root.tag_name().name());
+    if root.tag_name().name() == "math" {
+        let child = first_child(root, &path)?;
+        let child_path = child_path(&path, child);
+        Ok((child, child_path))
+    } else {
+        Ok((root, path))
+    }
+}
+
+fn parse_document<'input>(
+    filepath: &str,
+    source: &'input str,
+) -> Result<Document<'input>, MathmlError> {
+    Document::parse(source).map_err(|error| {
+        let position = error.pos();
+        let location = SourceLocation {
+            line: position.row as usize,
+            column: position.col as usize,
+        };
+        MathmlError::InvalidXml {
+            message: error.to_string(),
+            span: SourceSpan {
+                filepath: filepath.to_string(),
+                start: location.clone(),
+                end: location,
+            },
+        }
+    })
+}
+
+/// Reads an expression from Content MathML, like `<apply><plus/><ci>x</ci><cn>1</cn></apply>`,
+/// the spans of the expression point into `source`
+pub fn parse_mathml_expression(filepath: &str, source: &str) -> Result<Expression, MathmlError> {
+    let document = parse_document(filepath, source)?;
+    let reader = Reader {
+        filepath,
+        document: &document,
+    };
+    let (node, path) = root(&document)?;
+    reader.expression(node, &path)
+}
+
+/// Reads an equation from Content MathML, which is an `<apply>` of `<eq/>` to both sides
+pub fn parse_mathml_equation(filepath: &str, source: &str) -> Result<Equation, MathmlError> {
+    let document = parse_document(filepath, source)?;
+    let reader = Reader {
+        filepath,
+        document: &document,
+    };
+    let (node, path) = root(&document)?;
+    reader.equation(node, &path)
+}
+
+/// Presentation MathML elements, a piece that is more than one element is wrapped in an `<mrow>`
+fn row(pieces: Vec<String>) -> String {
+    if pieces.len() == 1 {
+        pieces.into_iter().next().unwrap()
+    } else {
+        format!("<mrow>{}</mrow>", pieces.concat())
+    }
+}
+
+fn operator(operator: &str) -> String {
+    format!("<mo>{operator}</mo>")
+}
+
+/// The text with `&`, `<` and `>` written as entities, so it can be the content of an element
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+const MINUS: &str = "&#x2212;";
+const INVISIBLE_TIMES: &str = "&#x2062;";
+const DOT: &str = "&#x22C5;";
+const FUNCTION_APPLICATION: &str = "&#x2061;";
+
+/// Presentation MathML, an element per number, name and operator
+struct Mathml;
+
+/// `x1` is `x` with the subscript `1`, like it is written in LaTeX
+fn mathml_name(name: &str) -> String {
+    let letters = name.trim_end_matches(|chr: char| chr.is_ascii_digit());
+    let digits = &name[letters.len()..];
+    if letters.is_empty() || digits.is_empty() {
+        format!("<mi>{}</mi>", escape(name))
+    } else {
+        format!("<msub><mi>{}</mi><mn>{digits}</mn></msub>", escape(letters))
+    }
+}
+
+fn mathml_root(base: &Atom, exponent: &Atom) -> Option<String> {
+    if !exponent.is_exact() {
+        return None;
+    }
+    let exponent = eval_atom(exponent).ok()?;
+    if !exponent.numer().is_one() || exponent.denom() <= &BigInt::one() {
+        return None;
+    }
+    let base = print_atom(&Mathml, base).0;
+    if exponent.denom() == &BigInt::from(2) {
+        Some(format!("<msqrt>{base}</msqrt>"))
+    } else {
+        Some(format!(
+            "<mroot>{base}<mn>{}</mn></mroot>",
+            exponent.denom()
+        ))
+    }
+}
+
+/// Whether the markup starts with a number, so a `⋅` is needed to tell it apart from the number before it
+fn starts_with_number(mut markup: &str) -> bool {
+    while let Some(rest) = markup
+        .strip_prefix("<mrow>")
+        .or_else(|| markup.strip_prefix("<msup>"))
+    {
+        markup = rest;
+    }
+    markup.starts_with("<mn>")
+}
+
+/// The factors with an invisible times between them, or a `⋅` before a factor that starts with a number
+fn product(pieces: &mut Vec<String>, factor: String) {
+    if !pieces.is_empty() {
+        pieces.push(operator(if starts_with_number(&factor) {
+            DOT
+        } else {
+            INVISIBLE_TIMES
+        }));
+    }
+    pieces.push(factor);
+}
+
+impl Syntax for Mathml {
+    fn parentheses(&self, inner: String) -> String {
+        row(vec![operator("("), inner, operator(")")])
+    }
+
+    // a fraction needs parentheses as the base of a power, like in `(1/2)^2`
+    fn number(&self, value: &BigRational) -> Printed {
+        let (magnitude, precedence) = if value.is_integer() {
+            (
+                format!("<mn>{}</mn>", value.numer().abs()),
+                Precedence::Atom,
+            )
+        } else {
+            (
+                format!(
+                    "<mfrac><mn>{}</mn><mn>{}</mn></mfrac>",
+                    value.numer().abs(),
+                    value.denom()
+                ),
+                Precedence::Power,
+            )
+        };
+        if value.is_negative() {
+            (row(vec![operator(MINUS), magnitude]), Precedence::Unary)
+        } else {
+            (magnitude, precedence)
+        }
+    }
+
+    fn variable(&self, name: &str) -> String {
+        mathml_name(name)
+    }
+
+    fn fraction(&self, numerator: &Expression, denominator: &Expression) -> Printed {
+        (
+            format!(
+                "<mfrac>{}{}</mfrac>",
+                print_expression(self, numerator).0,
+                print_expression(self, denominator).0
+            ),
+            Precedence::Power,
+        )
+    }
+
+    fn power(&self, base: &Atom, exponent: &Atom) -> Printed {
+        match mathml_root(base, exponent) {
+            Some(root) => (root, Precedence::Atom),
+            None => (
+                format!(
+                    "<msup>{}{}</msup>",
+                    parenthesize(self, print_atom(self, base), Precedence::Atom).0,
+                    print_atom(self, exponent).0
+                ),
+                Precedence::Power,
+            ),
+        }
+    }
+
+    fn call(&self, name: &str, args: &[Expression]) -> String {
+        let args = args
+            .iter()
+            .map(|argument| print_expression(self, argument).0)
+            .collect::<Vec<_>>();
+        let fenced = |open: &str, argument: &str, close: &str| {
+            row(vec![operator(open), argument.to_string(), operator(close)])
+        };
+        match (name, &args[..]) {
+            ("sqrt", [argument]) => format!("<msqrt>{argument}</msqrt>"),
+            ("cbrt", [argument]) => format!("<mroot>{argument}<mn>3</mn></mroot>"),
+            ("abs", [argument]) => fenced("|", argument, "|"),
+            ("floor", [argument]) => fenced("&#x230A;", argument, "&#x230B;"),
+            ("ceil", [argument]) => fenced("&#x2308;", argument, "&#x2309;"),
+            _ => {
+                let name = match name {
+                    "asin" | "acos" | "atan" => format!("arc{}", &name[1..]),
+                    _ => name.to_string(),
+                };
+                let mut arguments = vec![operator("(")];
+                for (i, argument) in args.into_iter().enumerate() {
+                    if i > 0 {
+                        arguments.push(operator(","));
+                    }
+                    arguments.push(argument);
+                }
+                arguments.push(operator(")"));
+                row(vec![
+                    format!("<mi>{}</mi>", escape(&name)),
+                    operator(FUNCTION_APPLICATION),
+                    row(arguments),
+                ])
+            }
+        }
+    }
+
+    fn product(&self, coefficient: &BigRational, factors: Vec<Printed>) -> Printed {
+        let mut factors = factors
+            .into_iter()
+            .map(|factor| parenthesize(self, factor, Precedence::Power).0);
+        let mut pieces = vec![];
+        if *coefficient == -BigRational::one() {
+            // the minus sign is not a factor, so there is no times after it
+            pieces.push(operator(MINUS));
+            pieces.extend(factors.next());
+        } else if !coefficient.is_one() {
+            pieces.push(self.number(coefficient).0);
+        }
+        for factor in factors {
+            product(&mut pieces, factor);
+        }
+        (row(pieces), Precedence::Product)
+    }
+
+    fn operator(&self, symbol: &str) -> String {
+        if symbol == "-" {
+            operator(MINUS)
+        } else {
+            operator(&escape(symbol))
+        }
+    }
+
+    fn row(&self, pieces: Vec<String>) -> String {
+        row(pieces)
+    }
+}
+
+fn math(markup: String) -> String {
+    format!("<math xmlns=\"{NAMESPACE}\">{markup}</math>")
+}
+
+impl Expression {
+    /// The expression as a Presentation MathML `<math>` element
+    pub fn to_mathml(&self) -> String {
+        math(print_expression(&Mathml, self).0)
+    }
+}
+
+impl Equation {
+    /// The equation as a Presentation MathML `<math>` element
+    pub fn to_mathml(&self) -> String {
+        match self {
+            Equation::Equality { left, right, .. } => math(row(vec![
+                print_expression(&Mathml, left).0,
+                operator("="),
+                print_expression(&Mathml, right).0,
+            ])),
+        }
+    }
+}
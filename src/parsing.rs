@@ -2,7 +2,10 @@ use derive_more::{Display, IsVariant};
 use enum_as_inner::EnumAsInner;
 use num_rational::BigRational;
 
-use crate::{Atom, Equation, Expression, Lexer, LexerError, Term, Token, TokenKind};
+use crate::{
+    builtin_function, merge_spans, Arity, Atom, AtomKind, Definition, Definitions, Equation,
+    Expression, LexerError, SourceSpan, System, Term, Token, TokenKind, TokenStream,
+};
 
 #[derive(Clone, PartialEq, Debug, Display, IsVariant, EnumAsInner)]
 pub enum ParsingError {
@@ -16,6 +19,23 @@ pub enum ParsingError {
     ExpectedAtom {
         got: Token,
     },
+    #[display(fmt = "{span}: Unknown function '{name}'")]
+    UnknownFunction {
+        name: String,
+        span: SourceSpan,
+    },
+    #[display(fmt = "{span}: Function '{name}' takes {expected}, but got {got}")]
+    WrongArgumentCount {
+        name: String,
+        expected: Arity,
+        got: usize,
+        span: SourceSpan,
+    },
+    #[display(fmt = "{span}: Parameter '{name}' is declared more than once")]
+    DuplicateParameter {
+        name: String,
+        span: SourceSpan,
+    },
 }
 
 impl From<LexerError> for ParsingError {
@@ -24,23 +44,70 @@ impl From<LexerError> for ParsingError {
     }
 }
 
-fn parse_atom(lexer: &mut Lexer) -> Result<Atom, ParsingError> {
+fn parse_atom_in(
+    lexer: &mut impl TokenStream,
+    definitions: &Definitions,
+) -> Result<Atom, ParsingError> {
     Ok(match lexer.peek_token()?.kind {
         TokenKind::Number => {
             let token = expect_token(lexer, TokenKind::Number)?;
-            Atom::Number(token.data.into_number().unwrap())
+            Atom {
+                kind: AtomKind::Number(token.data.into_number().unwrap()),
+                span: Some(token.span),
+            }
         }
 
         TokenKind::Name => {
             let token = expect_token(lexer, TokenKind::Name)?;
-            Atom::Variable(token.data.into_string().unwrap())
+            let name = token.data.into_string().unwrap();
+            if lexer.peek_token()?.kind == TokenKind::OpenParenthesis {
+                let arity = builtin_function(&name)
+                    .map(|function| function.arity)
+                    .or_else(|| definitions.function_arity(&name));
+                match arity {
+                    Some(arity) => {
+                        let (args, close) = parse_arguments(lexer, definitions)?;
+                        let span = token.span.merge(&close.span);
+                        if !arity.accepts(args.len()) {
+                            return Err(ParsingError::WrongArgumentCount {
+                                name,
+                                expected: arity,
+                                got: args.len(),
+                                span,
+                            });
+                        }
+                        return Ok(Atom {
+                            kind: AtomKind::Call { name, args },
+                            span: Some(span),
+                        });
+                    }
+                    // one argument is left to implicit multiplication, like `x(y + 1)`,
+                    // which parses the group, so it is only counted here
+                    None => match count_arguments(lexer.clone()) {
+                        Some((count, close)) if count != 1 => {
+                            return Err(ParsingError::UnknownFunction {
+                                name,
+                                span: token.span.merge(&close.span),
+                            })
+                        }
+                        _ => {}
+                    },
+                }
+            }
+            Atom {
+                kind: AtomKind::Variable(name),
+                span: Some(token.span),
+            }
         }
 
         TokenKind::OpenParenthesis => {
-            expect_token(lexer, TokenKind::OpenParenthesis)?;
-            let expression = parse_expression(lexer)?;
-            expect_token(lexer, TokenKind::CloseParenthesis)?;
-            Atom::Group(expression)
+            let open = expect_token(lexer, TokenKind::OpenParenthesis)?;
+            let expression = parse_expression_in(lexer, definitions)?;
+            let close = expect_token(lexer, TokenKind::CloseParenthesis)?;
+            Atom {
+                kind: AtomKind::Group(expression),
+                span: Some(open.span.merge(&close.span)),
+            }
         }
 
         _ => {
@@ -51,71 +118,471 @@ fn parse_atom(lexer: &mut Lexer) -> Result<Atom, ParsingError> {
     })
 }
 
-fn parse_term(lexer: &mut Lexer) -> Result<Term, ParsingError> {
-    let mut atoms = vec![];
-    if lexer.peek_token()?.kind == TokenKind::Minus {
-        lexer.next_token()?;
-        atoms.push(Atom::Number(BigRational::from_float(-1.0).unwrap()));
-    }
-    atoms.push(parse_atom(lexer)?);
-    while matches!(
-        lexer.peek_token()?.kind,
-        TokenKind::Multiply | TokenKind::Divide
-    ) {
-        let operator = lexer.next_token()?;
-        match operator.kind {
-            TokenKind::Multiply => {
-                let atom = parse_atom(lexer)?;
-                atoms.push(atom);
-            }
-            TokenKind::Divide => {
-                atoms = vec![Atom::Fraction {
-                    numerator: Expression {
-                        terms: vec![Term { atoms }],
-                    },
-                    denominator: Expression {
-                        terms: vec![Term {
-                            atoms: vec![parse_atom(lexer)?],
-                        }],
-                    },
-                }];
+/// Counts the comma separated arguments of a call without parsing them, and returns the closing parenthesis.
+/// `None` when the parentheses are not closed, parsing them reports that
+fn count_arguments(mut lexer: impl TokenStream) -> Option<(usize, Token)> {
+    lexer.next_token().ok()?;
+    let mut depth = 0;
+    let mut commas = 0;
+    let mut empty = true;
+    loop {
+        let token = lexer.next_token().ok()?;
+        match token.kind {
+            TokenKind::CloseParenthesis if depth == 0 => {
+                return Some((if empty { 0 } else { commas + 1 }, token))
             }
-            _ => unreachable!(),
+            TokenKind::OpenParenthesis => depth += 1,
+            TokenKind::CloseParenthesis => depth -= 1,
+            TokenKind::Comma if depth == 0 => commas += 1,
+            TokenKind::EOF => return None,
+            _ => {}
+        }
+        empty = false;
+    }
+}
+
+/// Parses the comma separated arguments of a call and the closing parenthesis
+fn parse_arguments(
+    lexer: &mut impl TokenStream,
+    definitions: &Definitions,
+) -> Result<(Vec<Expression>, Token), ParsingError> {
+    expect_token(lexer, TokenKind::OpenParenthesis)?;
+    let mut args = vec![];
+    if lexer.peek_token()?.kind != TokenKind::CloseParenthesis {
+        args.push(parse_expression_in(lexer, definitions)?);
+        while lexer.peek_token()?.kind == TokenKind::Comma {
+            lexer.next_token()?;
+            args.push(parse_expression_in(lexer, definitions)?);
+        }
+    }
+    let close = expect_token(lexer, TokenKind::CloseParenthesis)?;
+    Ok((args, close))
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum BinaryOperation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum UnaryOperation {
+    Identity,
+    Negate,
+}
+
+struct BinaryOperator {
+    token: TokenKind,
+    operation: BinaryOperation,
+    precedence: u8,
+    associativity: Associativity,
+}
+
+struct UnaryOperator {
+    token: TokenKind,
+    operation: UnaryOperation,
+    precedence: u8,
+}
+
+/// Operators that bind tighter have a higher precedence
+static BINARY_OPERATORS: &[BinaryOperator] = &[
+    BinaryOperator {
+        token: TokenKind::Plus,
+        operation: BinaryOperation::Add,
+        precedence: 1,
+        associativity: Associativity::Left,
+    },
+    BinaryOperator {
+        token: TokenKind::Minus,
+        operation: BinaryOperation::Subtract,
+        precedence: 1,
+        associativity: Associativity::Left,
+    },
+    BinaryOperator {
+        token: TokenKind::Multiply,
+        operation: BinaryOperation::Multiply,
+        precedence: 2,
+        associativity: Associativity::Left,
+    },
+    BinaryOperator {
+        token: TokenKind::Divide,
+        operation: BinaryOperation::Divide,
+        precedence: 2,
+        associativity: Associativity::Left,
+    },
+    BinaryOperator {
+        token: TokenKind::Power,
+        operation: BinaryOperation::Power,
+        precedence: 4,
+        associativity: Associativity::Right,
+    },
+];
+
+/// Juxtaposition, like `2x`, `3(x+1)` or `(a)(b)`, has the same precedence as `*` and `/`,
+/// so it is also left associative with them: `1/2x` is `(1/2)*x` and `6/2(1+2)` is `(6/2)*(1+2)`
+static IMPLICIT_MULTIPLICATION: BinaryOperator = BinaryOperator {
+    token: TokenKind::Multiply,
+    operation: BinaryOperation::Multiply,
+    precedence: 2,
+    associativity: Associativity::Left,
+};
+
+/// Whether `next` starts the right side of an implicit multiplication after a `previous` token,
+/// which is the case for a number followed by a name, and anything but an operator followed by a group
+fn is_implicit_multiplication(previous: Option<&TokenKind>, next: &TokenKind) -> bool {
+    matches!(
+        (previous, next),
+        (Some(TokenKind::Number), TokenKind::Name)
+            | (
+                Some(TokenKind::Number | TokenKind::Name | TokenKind::CloseParenthesis),
+                TokenKind::OpenParenthesis
+            )
+    )
+}
+
+/// Prefix operators bind looser than `^`, so `-2^2` is `-(2^2)`,
+/// but tighter than `*` and `/`, so `2^-1*3` is `(2^-1)*3`
+static UNARY_OPERATORS: &[UnaryOperator] = &[
+    UnaryOperator {
+        token: TokenKind::Plus,
+        operation: UnaryOperation::Identity,
+        precedence: 3,
+    },
+    UnaryOperator {
+        token: TokenKind::Minus,
+        operation: UnaryOperation::Negate,
+        precedence: 3,
+    },
+];
+
+pub(crate) fn negative_one(span: Option<SourceSpan>) -> Atom {
+    Atom {
+        kind: AtomKind::Number(BigRational::from_float(-1.0).unwrap()),
+        span,
+    }
+}
+
+/// Uses the expression as a single atom, wrapping it in a group when it is not one already
+pub(crate) fn into_atom(mut expression: Expression) -> Atom {
+    if expression.terms.len() == 1 && expression.terms[0].atoms.len() == 1 {
+        expression.terms.remove(0).atoms.remove(0)
+    } else {
+        Atom {
+            span: expression.span.clone(),
+            kind: AtomKind::Group(expression),
+        }
+    }
+}
+
+/// Uses the expression as a single term, wrapping it in a group when it has several terms
+pub(crate) fn into_term(mut expression: Expression) -> Term {
+    if expression.terms.len() == 1 {
+        expression.terms.remove(0)
+    } else {
+        Term {
+            span: expression.span.clone(),
+            atoms: vec![into_atom(expression)],
+        }
+    }
+}
+
+pub(crate) fn atom_expression(atom: Atom) -> Expression {
+    let span = atom.span.clone();
+    Expression {
+        terms: vec![Term {
+            atoms: vec![atom],
+            span: span.clone(),
+        }],
+        span,
+    }
+}
+
+pub(crate) fn term_expression(term: Term) -> Expression {
+    Expression {
+        span: term.span.clone(),
+        terms: vec![term],
+    }
+}
+
+fn apply_binary(
+    operation: BinaryOperation,
+    left: Expression,
+    right: Expression,
+    operator_span: Option<SourceSpan>,
+) -> Expression {
+    let span = merge_spans([&left.span, &right.span]);
+    match operation {
+        BinaryOperation::Add => Expression {
+            terms: left.terms.into_iter().chain(right.terms).collect(),
+            span,
+        },
+        BinaryOperation::Subtract => Expression {
+            terms: left
+                .terms
+                .into_iter()
+                .chain(right.terms.into_iter().map(|mut term| {
+                    term.atoms.push(negative_one(operator_span.clone()));
+                    term.span = merge_spans([&operator_span, &term.span]);
+                    term
+                }))
+                .collect(),
+            span,
+        },
+        BinaryOperation::Multiply => {
+            let left = into_term(left);
+            let right = into_term(right);
+            term_expression(Term {
+                atoms: left.atoms.into_iter().chain(right.atoms).collect(),
+                span,
+            })
+        }
+        BinaryOperation::Divide => atom_expression(Atom {
+            kind: AtomKind::Fraction {
+                numerator: left,
+                denominator: right,
+            },
+            span,
+        }),
+        BinaryOperation::Power => atom_expression(Atom {
+            kind: AtomKind::Power {
+                base: Box::new(into_atom(left)),
+                exponent: Box::new(into_atom(right)),
+            },
+            span,
+        }),
+    }
+}
+
+fn apply_unary(operation: UnaryOperation, operand: Expression, operator: Token) -> Expression {
+    match operation {
+        UnaryOperation::Identity => operand,
+        UnaryOperation::Negate => {
+            let operand = into_term(operand);
+            let span = merge_spans([&Some(operator.span.clone()), &operand.span]);
+            term_expression(Term {
+                atoms: std::iter::once(negative_one(Some(operator.span)))
+                    .chain(operand.atoms)
+                    .collect(),
+                span,
+            })
         }
     }
-    Ok(Term { atoms })
-}
-
-fn parse_expression(lexer: &mut Lexer) -> Result<Expression, ParsingError> {
-    let mut terms = vec![parse_term(lexer)?];
-    while matches!(
-        lexer.peek_token()?.kind,
-        TokenKind::Plus | TokenKind::Minus | TokenKind::Divide
-    ) {
-        let operator = lexer.next_token()?;
-        match operator.kind {
-            TokenKind::Plus => terms.push(parse_term(lexer)?),
-            TokenKind::Minus => {
-                let mut term = parse_term(lexer)?;
-                term.atoms
-                    .push(Atom::Number(BigRational::from_float(-1.0).unwrap()));
-                terms.push(term);
+}
+
+fn parse_prefix(
+    lexer: &mut impl TokenStream,
+    definitions: &Definitions,
+) -> Result<Expression, ParsingError> {
+    let token = lexer.peek_token()?;
+    if let Some(operator) = UNARY_OPERATORS
+        .iter()
+        .find(|operator| operator.token == token.kind)
+    {
+        let token = lexer.next_token()?;
+        let operand = parse_binary(lexer, definitions, operator.precedence)?;
+        Ok(apply_unary(operator.operation, operand, token))
+    } else {
+        Ok(atom_expression(parse_atom_in(lexer, definitions)?))
+    }
+}
+
+/// Precedence climbing, only operators with at least `min_precedence` are parsed
+fn parse_binary(
+    lexer: &mut impl TokenStream,
+    definitions: &Definitions,
+    min_precedence: u8,
+) -> Result<Expression, ParsingError> {
+    let mut left = parse_prefix(lexer, definitions)?;
+    loop {
+        let token = lexer.peek_token()?;
+        let (operator, implicit) = match BINARY_OPERATORS
+            .iter()
+            .find(|operator| operator.token == token.kind)
+        {
+            Some(operator) => (operator, false),
+            None if is_implicit_multiplication(lexer.previous_kind(), &token.kind) => {
+                (&IMPLICIT_MULTIPLICATION, true)
             }
-            _ => unreachable!(),
+            None => break,
+        };
+        if operator.precedence < min_precedence {
+            break;
         }
+        // implicit multiplication has no token of its own to consume
+        let operator_span = if implicit {
+            None
+        } else {
+            Some(lexer.next_token()?.span)
+        };
+        let right = parse_binary(
+            lexer,
+            definitions,
+            match operator.associativity {
+                Associativity::Left => operator.precedence + 1,
+                Associativity::Right => operator.precedence,
+            },
+        )?;
+        left = apply_binary(operator.operation, left, right, operator_span);
     }
-    Ok(Expression { terms })
+    Ok(left)
+}
+
+fn parse_expression_in(
+    lexer: &mut impl TokenStream,
+    definitions: &Definitions,
+) -> Result<Expression, ParsingError> {
+    parse_binary(lexer, definitions, 0)
 }
 
-pub fn parse_equation(lexer: &mut Lexer) -> Result<Equation, ParsingError> {
-    let left = parse_expression(lexer)?;
+fn parse_equality(
+    lexer: &mut impl TokenStream,
+    definitions: &Definitions,
+) -> Result<Equation, ParsingError> {
+    let left = parse_expression_in(lexer, definitions)?;
     expect_token(lexer, TokenKind::Equal)?;
-    let right = parse_expression(lexer)?;
+    let right = parse_expression_in(lexer, definitions)?;
+    let span = merge_spans([&left.span, &right.span]);
+    Ok(Equation::Equality { left, right, span })
+}
+
+/// Parses an equation that can call the functions in `definitions`
+pub fn parse_equation_with(
+    lexer: &mut impl TokenStream,
+    definitions: &Definitions,
+) -> Result<Equation, ParsingError> {
+    let equation = parse_equality(lexer, definitions)?;
     expect_token(lexer, TokenKind::EOF)?;
-    Ok(Equation::Equality { left, right })
+    Ok(equation)
+}
+
+/// Parses equations separated by commas or newlines, which can call the functions in `definitions`
+pub fn parse_system_with(
+    lexer: &mut impl TokenStream,
+    definitions: &Definitions,
+) -> Result<System, ParsingError> {
+    let mut equations = vec![parse_equality(lexer, definitions)?];
+    loop {
+        let line = lexer.location().line;
+        let token = lexer.peek_token()?;
+        match token.kind {
+            TokenKind::EOF => break,
+            TokenKind::Comma => {
+                lexer.next_token()?;
+                equations.push(parse_equality(lexer, definitions)?);
+            }
+            _ if token.span.start.line > line => {
+                equations.push(parse_equality(lexer, definitions)?)
+            }
+            _ => {
+                return Err(ParsingError::ExpectedToken {
+                    expected: TokenKind::Comma,
+                    got: lexer.next_token()?,
+                })
+            }
+        }
+    }
+    Ok(System { equations })
+}
+
+/// Parses a definition like `f(x, y) := x + y` or `g := 9.81`,
+/// its body can call the function itself and the functions in `definitions`
+pub fn parse_definition_with(
+    lexer: &mut impl TokenStream,
+    definitions: &Definitions,
+) -> Result<Definition, ParsingError> {
+    let name = expect_token(lexer, TokenKind::Name)?;
+    let parameters = if lexer.peek_token()?.kind == TokenKind::OpenParenthesis {
+        lexer.next_token()?;
+        let mut parameters: Vec<String> = vec![];
+        if lexer.peek_token()?.kind != TokenKind::CloseParenthesis {
+            loop {
+                let parameter = expect_token(lexer, TokenKind::Name)?;
+                let parameter_name = parameter.data.into_string().unwrap();
+                if parameters.contains(&parameter_name) {
+                    return Err(ParsingError::DuplicateParameter {
+                        name: parameter_name,
+                        span: parameter.span,
+                    });
+                }
+                parameters.push(parameter_name);
+                if lexer.peek_token()?.kind != TokenKind::Comma {
+                    break;
+                }
+                lexer.next_token()?;
+            }
+        }
+        expect_token(lexer, TokenKind::CloseParenthesis)?;
+        Some(parameters)
+    } else {
+        None
+    };
+    expect_token(lexer, TokenKind::Define)?;
+    let mut definition = Definition {
+        name: name.data.into_string().unwrap(),
+        parameters,
+        body: Expression::new(vec![]),
+        span: None,
+    };
+    // declared before parsing the body, so recursive calls are parsed as calls and reported when defining it
+    let mut definitions = definitions.clone();
+    definitions.declare(definition.clone());
+    definition.body = parse_expression_in(lexer, &definitions)?;
+    definition.span = merge_spans([&Some(name.span), &definition.body.span]);
+    expect_token(lexer, TokenKind::EOF)?;
+    Ok(definition)
+}
+
+/// Parses a whole expression that can call the functions in `definitions`
+pub fn parse_expression_with(
+    lexer: &mut impl TokenStream,
+    definitions: &Definitions,
+) -> Result<Expression, ParsingError> {
+    let expression = parse_expression_in(lexer, definitions)?;
+    expect_token(lexer, TokenKind::EOF)?;
+    Ok(expression)
+}
+
+/// Parses a single atom, like a number, a name, a call or a parenthesized expression,
+/// which must be the whole input
+pub fn parse_atom_with(
+    lexer: &mut impl TokenStream,
+    definitions: &Definitions,
+) -> Result<Atom, ParsingError> {
+    let atom = parse_atom_in(lexer, definitions)?;
+    expect_token(lexer, TokenKind::EOF)?;
+    Ok(atom)
+}
+
+pub fn parse_expression(lexer: &mut impl TokenStream) -> Result<Expression, ParsingError> {
+    parse_expression_with(lexer, &Definitions::new())
+}
+
+pub fn parse_atom(lexer: &mut impl TokenStream) -> Result<Atom, ParsingError> {
+    parse_atom_with(lexer, &Definitions::new())
+}
+
+pub fn parse_equation(lexer: &mut impl TokenStream) -> Result<Equation, ParsingError> {
+    parse_equation_with(lexer, &Definitions::new())
+}
+
+pub fn parse_system(lexer: &mut impl TokenStream) -> Result<System, ParsingError> {
+    parse_system_with(lexer, &Definitions::new())
+}
+
+pub fn parse_definition(lexer: &mut impl TokenStream) -> Result<Definition, ParsingError> {
+    parse_definition_with(lexer, &Definitions::new())
 }
 
-fn expect_token(lexer: &mut Lexer, kind: TokenKind) -> Result<Token, ParsingError> {
+fn expect_token(lexer: &mut impl TokenStream, kind: TokenKind) -> Result<Token, ParsingError> {
     let token = lexer.next_token()?;
     if token.kind != kind {
         return Err(ParsingError::ExpectedToken {
@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use crate::{into_atom, Atom, AtomKind, Expression, Term};
+
+/// What the wildcards of a pattern matched, by their name with the `?`.
+/// A wildcard that is a whole expression, like the argument of `sin(?a)`, matches a whole expression,
+/// one that is a whole term of a sum matches a whole term, and anywhere else it matches a single atom
+pub(crate) type Bindings = HashMap<String, Expression>;
+
+pub(crate) fn wildcard(atom: &Atom) -> Option<&str> {
+    atom.kind
+        .as_variable()
+        .filter(|name| name.starts_with('?'))
+        .map(String::as_str)
+}
+
+/// The wildcard of a term that is nothing but that wildcard
+fn term_wildcard(term: &Term) -> Option<&str> {
+    match &term.atoms[..] {
+        [atom] => wildcard(atom),
+        _ => None,
+    }
+}
+
+/// The wildcards in the expression, in the order they are used
+pub(crate) fn wildcards(expression: &Expression) -> Vec<&Atom> {
+    fn atom_wildcards<'a>(atom: &'a Atom, found: &mut Vec<&'a Atom>) {
+        match &atom.kind {
+            AtomKind::Number(_) => {}
+            AtomKind::Variable(_) => {
+                if wildcard(atom).is_some() {
+                    found.push(atom);
+                }
+            }
+            AtomKind::Group(expression) => expression_wildcards(expression, found),
+            AtomKind::Fraction {
+                numerator,
+                denominator,
+            } => {
+                expression_wildcards(numerator, found);
+                expression_wildcards(denominator, found);
+            }
+            AtomKind::Power { base, exponent } => {
+                atom_wildcards(base, found);
+                atom_wildcards(exponent, found);
+            }
+            AtomKind::Call { args, .. } => {
+                for argument in args {
+                    expression_wildcards(argument, found);
+                }
+            }
+        }
+    }
+    fn expression_wildcards<'a>(expression: &'a Expression, found: &mut Vec<&'a Atom>) {
+        for term in &expression.terms {
+            for atom in &term.atoms {
+                atom_wildcards(atom, found);
+            }
+        }
+    }
+    let mut found = vec![];
+    expression_wildcards(expression, &mut found);
+    found
+}
+
+fn bind(name: &str, value: Expression, mut bindings: Bindings) -> Option<Bindings> {
+    match bindings.get(name) {
+        // a wildcard that is used twice must match the same thing twice
+        Some(bound) => (bound == &value).then_some(bindings),
+        None => {
+            bindings.insert(name.to_string(), value);
+            Some(bindings)
+        }
+    }
+}
+
+fn match_atom(pattern: &Atom, atom: &Atom, bindings: Bindings) -> Option<Bindings> {
+    if let Some(name) = wildcard(pattern) {
+        return bind(
+            name,
+            Expression::new(vec![Term::new(vec![atom.clone()])]),
+            bindings,
+        );
+    }
+    match (&pattern.kind, &atom.kind) {
+        (AtomKind::Number(a), AtomKind::Number(b)) => (a == b).then_some(bindings),
+        (AtomKind::Variable(a), AtomKind::Variable(b)) => (a == b).then_some(bindings),
+        (AtomKind::Group(a), AtomKind::Group(b)) => {
+            match_expression(a, b, bindings, false).map(|(bindings, _)| bindings)
+        }
+        (
+            AtomKind::Fraction {
+                numerator: a_numerator,
+                denominator: a_denominator,
+            },
+            AtomKind::Fraction {
+                numerator: b_numerator,
+                denominator: b_denominator,
+            },
+        ) => {
+            let (bindings, _) = match_expression(a_numerator, b_numerator, bindings, false)?;
+            match_expression(a_denominator, b_denominator, bindings, false)
+                .map(|(bindings, _)| bindings)
+        }
+        (
+            AtomKind::Power {
+                base: a_base,
+                exponent: a_exponent,
+            },
+            AtomKind::Power {
+                base: b_base,
+                exponent: b_exponent,
+            },
+        ) => match_atom(
+            a_exponent,
+            b_exponent,
+            match_atom(a_base, b_base, bindings)?,
+        ),
+        (
+            AtomKind::Call {
+                name: a_name,
+                args: a_args,
+            },
+            AtomKind::Call {
+                name: b_name,
+                args: b_args,
+            },
+        ) if a_name == b_name && a_args.len() == b_args.len() => a_args
+            .iter()
+            .zip(b_args)
+            .try_fold(bindings, |bindings, (a, b)| {
+                match_expression(a, b, bindings, false).map(|(bindings, _)| bindings)
+            }),
+        _ => None,
+    }
+}
+
+/// Matches the pattern atoms to different atoms of the term in any order, as multiplication is commutative.
+/// With `partial` the term can have atoms that are not matched, the matched ones are returned by index
+pub(crate) fn match_term(
+    pattern: &Term,
+    term: &Term,
+    bindings: Bindings,
+    partial: bool,
+) -> Option<(Bindings, Vec<usize>)> {
+    fn assign(
+        patterns: &[Atom],
+        term: &Term,
+        bindings: Bindings,
+        used: &mut Vec<usize>,
+    ) -> Option<Bindings> {
+        let Some((pattern, rest)) = patterns.split_first() else {
+            return Some(bindings);
+        };
+        for (i, atom) in term.atoms.iter().enumerate() {
+            if used.contains(&i) {
+                continue;
+            }
+            if let Some(bindings) = match_atom(pattern, atom, bindings.clone()) {
+                used.push(i);
+                if let Some(bindings) = assign(rest, term, bindings, used) {
+                    return Some(bindings);
+                }
+                used.pop();
+            }
+        }
+        None
+    }
+
+    if !partial {
+        if let Some(name) = term_wildcard(pattern) {
+            let bindings = bind(name, Expression::new(vec![term.clone()]), bindings)?;
+            return Some((bindings, (0..term.atoms.len()).collect()));
+        }
+        if pattern.atoms.len() != term.atoms.len() {
+            return None;
+        }
+    }
+    let mut used = vec![];
+    let bindings = assign(&pattern.atoms, term, bindings, &mut used)?;
+    Some((bindings, used))
+}
+
+/// Matches the pattern terms to different terms of the expression in any order, as addition is commutative.
+/// With `partial` the expression can have terms that are not matched, the matched ones are returned by index
+pub(crate) fn match_expression(
+    pattern: &Expression,
+    expression: &Expression,
+    bindings: Bindings,
+    partial: bool,
+) -> Option<(Bindings, Vec<usize>)> {
+    fn assign(
+        patterns: &[Term],
+        expression: &Expression,
+        bindings: Bindings,
+        used: &mut Vec<usize>,
+    ) -> Option<Bindings> {
+        let Some((pattern, rest)) = patterns.split_first() else {
+            return Some(bindings);
+        };
+        for (i, term) in expression.terms.iter().enumerate() {
+            if used.contains(&i) {
+                continue;
+            }
+            if let Some((bindings, _)) = match_term(pattern, term, bindings.clone(), false) {
+                used.push(i);
+                if let Some(bindings) = assign(rest, expression, bindings, used) {
+                    return Some(bindings);
+                }
+                used.pop();
+            }
+        }
+        None
+    }
+
+    if !partial {
+        if let [term] = &pattern.terms[..] {
+            if let Some(name) = term_wildcard(term) {
+                let bindings = bind(name, expression.clone(), bindings)?;
+                return Some((bindings, (0..expression.terms.len()).collect()));
+            }
+        }
+        if pattern.terms.len() != expression.terms.len() {
+            return None;
+        }
+    }
+    let mut used = vec![];
+    let bindings = assign(&pattern.terms, expression, bindings, &mut used)?;
+    Some((bindings, used))
+}
+
+/// Replaces the wildcards with what they matched, a match that is more than one atom
+/// becomes part of the surrounding product or sum when it can, and a group when it cannot
+pub(crate) fn substitute_wildcards(replacement: &Expression, bindings: &Bindings) -> Expression {
+    let mut terms = vec![];
+    for term in &replacement.terms {
+        match term_wildcard(term).and_then(|name| bindings.get(name)) {
+            Some(value) => terms.extend(value.terms.iter().cloned()),
+            None => terms.push(substitute_term(term, bindings)),
+        }
+    }
+    Expression::new(terms)
+}
+
+fn substitute_term(term: &Term, bindings: &Bindings) -> Term {
+    let mut atoms = vec![];
+    for atom in &term.atoms {
+        match wildcard(atom).and_then(|name| bindings.get(name)) {
+            Some(value) if value.terms.len() == 1 => {
+                atoms.extend(value.terms[0].atoms.iter().cloned())
+            }
+            _ => atoms.push(substitute_atom(atom, bindings)),
+        }
+    }
+    Term::new(atoms)
+}
+
+fn substitute_atom(atom: &Atom, bindings: &Bindings) -> Atom {
+    let kind = match &atom.kind {
+        AtomKind::Number(_) => atom.kind.clone(),
+        AtomKind::Variable(_) => match wildcard(atom).and_then(|name| bindings.get(name)) {
+            Some(value) => return into_atom(value.clone()),
+            None => atom.kind.clone(),
+        },
+        AtomKind::Group(expression) => AtomKind::Group(substitute_wildcards(expression, bindings)),
+        AtomKind::Fraction {
+            numerator,
+            denominator,
+        } => AtomKind::Fraction {
+            numerator: substitute_wildcards(numerator, bindings),
+            denominator: substitute_wildcards(denominator, bindings),
+        },
+        AtomKind::Power { base, exponent } => AtomKind::Power {
+            base: Box::new(substitute_atom(base, bindings)),
+            exponent: Box::new(substitute_atom(exponent, bindings)),
+        },
+        AtomKind::Call { name, args } => AtomKind::Call {
+            name: name.clone(),
+            args: args
+                .iter()
+                .map(|argument| substitute_wildcards(argument, bindings))
+                .collect(),
+        },
+    };
+    Atom::new(kind)
+}
+
+/// Rewrites the first place in the expression that the pattern matches.
+/// A pattern of one term can match some of the atoms of a term, like `?a^1` in `3x^1`,
+/// and a pattern of more terms can match some of the terms of the sum
+pub(crate) fn rewrite(
+    expression: &Expression,
+    pattern: &Expression,
+    replacement: &Expression,
+) -> Option<Expression> {
+    let rewritten = if let [pattern_term] = &pattern.terms[..] {
+        expression.terms.iter().enumerate().find_map(|(i, term)| {
+            let (bindings, used) = match_term(pattern_term, term, Bindings::new(), true)?;
+            let replacement = substitute_wildcards(replacement, &bindings);
+            // the atoms that were not matched stay around the replacement, which takes the place of the first matched atom
+            let position = *used.iter().min()?;
+            let before = (0..position).filter(|i| !used.contains(i));
+            let after = (position..term.atoms.len()).filter(|i| !used.contains(i));
+            let terms = replacement.terms.iter().map(|replacement_term| Term {
+                atoms: before
+                    .clone()
+                    .map(|i| term.atoms[i].clone())
+                    .chain(replacement_term.atoms.iter().cloned())
+                    .chain(after.clone().map(|i| term.atoms[i].clone()))
+                    .collect(),
+                span: term.span.clone(),
+            });
+            Some(Expression {
+                terms: expression.terms[..i]
+                    .iter()
+                    .cloned()
+                    .chain(terms)
+                    .chain(expression.terms[i + 1..].iter().cloned())
+                    .collect(),
+                span: expression.span.clone(),
+            })
+        })?
+    } else {
+        let (bindings, used) = match_expression(pattern, expression, Bindings::new(), true)?;
+        let replacement = substitute_wildcards(replacement, &bindings);
+        let position = *used.iter().min()?;
+        let mut terms = vec![];
+        for (i, term) in expression.terms.iter().enumerate() {
+            if i == position {
+                terms.extend(replacement.terms.iter().cloned());
+            } else if !used.contains(&i) {
+                terms.push(term.clone());
+            }
+        }
+        Expression {
+            terms,
+            span: expression.span.clone(),
+        }
+    };
+    // a rule that changes nothing, like `?a -> ?a`, does not count as applied
+    (&rewritten != expression).then_some(rewritten)
+}
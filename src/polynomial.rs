@@ -0,0 +1,380 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    ops::{Add, Neg, Sub},
+};
+
+use derive_more::{Display, IsVariant};
+use enum_as_inner::EnumAsInner;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::{
+    eval_atom, span_prefix, Atom, AtomKind, DisplayOptions, DisplayWith, EvalError, Expression,
+    SourceSpan, Term,
+};
+
+#[derive(Clone, PartialEq, Debug, Display, IsVariant, EnumAsInner)]
+pub enum PolynomialError {
+    EvalError(EvalError),
+    /// Calls, divisions by variables and powers that are not whole numbers, like `sqrt(x)`, `1/x` and `x^-1`
+    #[display(fmt = "{}'{atom}' is not a polynomial", "span_prefix(span)")]
+    NotPolynomial {
+        atom: Atom,
+        span: Option<SourceSpan>,
+    },
+    /// An exponent that does not fit in a `u32`, or too many terms or digits, like `(x + y + 1)^2000`
+    #[display(fmt = "{}'{atom}' is too large to expand", "span_prefix(span)")]
+    TooLarge {
+        atom: Atom,
+        span: Option<SourceSpan>,
+    },
+}
+
+/// Products are not computed when their coefficients could have more bits than this
+const MAX_COEFFICIENT_BITS: u64 = 10_000;
+
+/// Products are not computed when the pairs of terms times the bits of their coefficients are more than this
+const MAX_PRODUCT_SIZE: u64 = 1_000_000;
+
+impl From<EvalError> for PolynomialError {
+    fn from(error: EvalError) -> PolynomialError {
+        PolynomialError::EvalError(error)
+    }
+}
+
+/// A product of variables, like `x^2*y`, a variable with the exponent 0 is left out
+#[derive(Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Monomial {
+    exponents: BTreeMap<String, u32>,
+}
+
+impl Monomial {
+    /// The monomial of a constant, which has no variables
+    pub fn one() -> Monomial {
+        Monomial::default()
+    }
+
+    pub fn variable(name: &str) -> Monomial {
+        Monomial {
+            exponents: BTreeMap::from([(name.to_string(), 1)]),
+        }
+    }
+
+    /// The variables in alphabetical order with their exponents
+    pub fn variables(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.exponents
+            .iter()
+            .map(|(name, exponent)| (name.as_str(), *exponent))
+    }
+
+    pub fn exponent(&self, name: &str) -> u32 {
+        self.exponents.get(name).copied().unwrap_or(0)
+    }
+
+    /// The sum of the exponents, `x^2*y` has degree 3
+    pub fn degree(&self) -> u32 {
+        self.exponents.values().sum()
+    }
+
+    /// `None` when an exponent or the degree does not fit in a `u32`
+    fn checked_multiply(&self, other: &Monomial) -> Option<Monomial> {
+        self.degree().checked_add(other.degree())?;
+        let mut exponents = self.exponents.clone();
+        for (name, exponent) in &other.exponents {
+            let sum = exponents.entry(name.clone()).or_insert(0);
+            *sum = sum.checked_add(*exponent)?;
+        }
+        Some(Monomial { exponents })
+    }
+}
+
+impl Ord for Monomial {
+    /// The order the terms of a polynomial are printed in, higher degrees first,
+    /// and between equal degrees higher exponents of the alphabetically first variable, so `x^2 + x*y + y^2 + x + 1`
+    fn cmp(&self, other: &Monomial) -> Ordering {
+        other.degree().cmp(&self.degree()).then_with(|| {
+            self.exponents
+                .keys()
+                .chain(other.exponents.keys())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|name| other.exponent(name).cmp(&self.exponent(name)))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        })
+    }
+}
+
+impl PartialOrd for Monomial {
+    fn partial_cmp(&self, other: &Monomial) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A sum of monomials with rational coefficients, like `3x^2*y - 1/2`, in the order of `Monomial`
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Polynomial {
+    terms: BTreeMap<Monomial, BigRational>,
+}
+
+impl Polynomial {
+    pub fn zero() -> Polynomial {
+        Polynomial::default()
+    }
+
+    pub fn constant(value: BigRational) -> Polynomial {
+        Polynomial::monomial(Monomial::one(), value)
+    }
+
+    pub fn variable(name: &str) -> Polynomial {
+        Polynomial::monomial(Monomial::variable(name), BigRational::one())
+    }
+
+    pub fn monomial(monomial: Monomial, coefficient: BigRational) -> Polynomial {
+        let mut polynomial = Polynomial::zero();
+        polynomial.add_term(monomial, coefficient);
+        polynomial
+    }
+
+    fn add_term(&mut self, monomial: Monomial, coefficient: BigRational) {
+        let sum = self.coefficient(&monomial) + coefficient;
+        if sum.is_zero() {
+            self.terms.remove(&monomial);
+        } else {
+            self.terms.insert(monomial, sum);
+        }
+    }
+
+    /// The terms in canonical order
+    pub fn terms(&self) -> impl Iterator<Item = (&Monomial, &BigRational)> {
+        self.terms.iter()
+    }
+
+    pub fn coefficient(&self, monomial: &Monomial) -> BigRational {
+        self.terms
+            .get(monomial)
+            .cloned()
+            .unwrap_or_else(BigRational::zero)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The most bits of the numerator and denominator of a coefficient
+    fn coefficient_bits(&self) -> u64 {
+        self.terms
+            .values()
+            .map(|coefficient| coefficient.numer().bits() + coefficient.denom().bits())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The highest degree of the terms, `None` for the zero polynomial
+    pub fn degree(&self) -> Option<u32> {
+        self.terms.keys().map(Monomial::degree).max()
+    }
+
+    /// `None` when an exponent does not fit in a `u32` or the product has too many terms or digits
+    pub fn checked_mul(&self, other: &Polynomial) -> Option<Polynomial> {
+        let bits = self.coefficient_bits() + other.coefficient_bits();
+        let pairs = (self.terms.len() as u64).saturating_mul(other.terms.len() as u64);
+        if bits > MAX_COEFFICIENT_BITS || pairs.saturating_mul(bits) > MAX_PRODUCT_SIZE {
+            return None;
+        }
+        let mut result = Polynomial::zero();
+        for (a, a_coefficient) in self.terms() {
+            for (b, b_coefficient) in other.terms() {
+                result.add_term(a.checked_multiply(b)?, a_coefficient * b_coefficient);
+            }
+        }
+        Some(result)
+    }
+
+    /// `None` when the power is too large, like `checked_mul`
+    pub fn checked_pow(&self, mut exponent: u32) -> Option<Polynomial> {
+        let mut result = Polynomial::constant(BigRational::one());
+        let mut square = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.checked_mul(&square)?;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                square = square.checked_mul(&square)?;
+            }
+        }
+        Some(result)
+    }
+
+    /// Multiplies out the products and whole number powers of the expression
+    pub fn from_expression(expression: &Expression) -> Result<Polynomial, PolynomialError> {
+        expression
+            .terms
+            .iter()
+            .try_fold(Polynomial::zero(), |result, term| {
+                Ok(&result + &term_polynomial(term)?)
+            })
+    }
+
+    /// The polynomial as a sum of terms like `3x^2*y`, in canonical order
+    pub fn to_expression(&self) -> Expression {
+        if self.is_zero() {
+            return Expression::new(vec![Term::new(vec![number(BigRational::zero())])]);
+        }
+        Expression::new(
+            self.terms()
+                .map(|(monomial, coefficient)| {
+                    let variables = monomial.variables().map(|(name, exponent)| {
+                        let variable = Atom::new(AtomKind::Variable(name.to_string()));
+                        if exponent == 1 {
+                            variable
+                        } else {
+                            Atom::new(AtomKind::Power {
+                                base: Box::new(variable),
+                                exponent: Box::new(number(BigRational::from_integer(
+                                    exponent.into(),
+                                ))),
+                            })
+                        }
+                    });
+                    // a coefficient of 1 is left out, unless it is the whole term
+                    let coefficient = (!coefficient.is_one() || monomial.degree() == 0)
+                        .then(|| number(coefficient.clone()));
+                    Term::new(coefficient.into_iter().chain(variables).collect())
+                })
+                .collect(),
+        )
+    }
+}
+
+fn number(value: BigRational) -> Atom {
+    Atom::new(AtomKind::Number(value))
+}
+
+fn not_polynomial(atom: &Atom) -> PolynomialError {
+    PolynomialError::NotPolynomial {
+        atom: atom.clone(),
+        span: atom.span.clone(),
+    }
+}
+
+fn too_large(atom: &Atom) -> PolynomialError {
+    PolynomialError::TooLarge {
+        atom: atom.clone(),
+        span: atom.span.clone(),
+    }
+}
+
+fn atom_polynomial(atom: &Atom) -> Result<Polynomial, PolynomialError> {
+    if atom.is_exact() {
+        return Ok(Polynomial::constant(eval_atom(atom)?));
+    }
+    match &atom.kind {
+        AtomKind::Number(_) => unreachable!(),
+        AtomKind::Variable(name) => Ok(Polynomial::variable(name)),
+        AtomKind::Group(expression) => Polynomial::from_expression(expression),
+        AtomKind::Fraction {
+            numerator,
+            denominator,
+        } => {
+            let denominator = Polynomial::from_expression(denominator)?;
+            if denominator.degree() != Some(0) {
+                return Err(if denominator.is_zero() {
+                    EvalError::DivisionByZero {
+                        atom: atom.clone(),
+                        span: atom.span.clone(),
+                    }
+                    .into()
+                } else {
+                    not_polynomial(atom)
+                });
+            }
+            let inverse = denominator.coefficient(&Monomial::one()).recip();
+            Polynomial::from_expression(numerator)?
+                .checked_mul(&Polynomial::constant(inverse))
+                .ok_or_else(|| too_large(atom))
+        }
+        AtomKind::Power { base, exponent } => {
+            if !exponent.is_exact() {
+                return Err(not_polynomial(atom));
+            }
+            let exponent = eval_atom(exponent)?;
+            if !exponent.is_integer() || exponent.is_negative() {
+                return Err(not_polynomial(atom));
+            }
+            let exponent = exponent
+                .to_integer()
+                .to_u32()
+                .ok_or_else(|| too_large(atom))?;
+            atom_polynomial(base)?
+                .checked_pow(exponent)
+                .ok_or_else(|| too_large(atom))
+        }
+        AtomKind::Call { .. } => Err(not_polynomial(atom)),
+    }
+}
+
+fn term_polynomial(term: &Term) -> Result<Polynomial, PolynomialError> {
+    term.atoms
+        .iter()
+        .try_fold(Polynomial::constant(BigRational::one()), |result, atom| {
+            result
+                .checked_mul(&atom_polynomial(atom)?)
+                .ok_or_else(|| too_large(atom))
+        })
+}
+
+impl Add for &Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, other: &Polynomial) -> Polynomial {
+        let mut result = self.clone();
+        for (monomial, coefficient) in other.terms() {
+            result.add_term(monomial.clone(), coefficient.clone());
+        }
+        result
+    }
+}
+
+impl Neg for &Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Polynomial {
+        Polynomial {
+            terms: self
+                .terms()
+                .map(|(monomial, coefficient)| (monomial.clone(), -coefficient))
+                .collect(),
+        }
+    }
+}
+
+impl Sub for &Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, other: &Polynomial) -> Polynomial {
+        self + &-other
+    }
+}
+
+impl DisplayWith for Polynomial {
+    fn fmt_with(&self, f: &mut std::fmt::Formatter, options: &DisplayOptions) -> std::fmt::Result {
+        self.to_expression().fmt_with(f, options)
+    }
+}
+
+impl Display for Polynomial {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.fmt_with(f, &DisplayOptions::default())
+    }
+}
+
+impl Expression {
+    /// Multiplies out all products and whole number powers, like `(x + 1)^2` to `x^2 + 2x + 1`
+    pub fn expand(&self) -> Result<Expression, PolynomialError> {
+        Ok(Polynomial::from_expression(self)?.to_expression())
+    }
+}
@@ -0,0 +1,127 @@
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+
+use crate::{Atom, AtomKind, Expression, Term};
+
+/// How tightly a printed piece binds, a piece is put in parentheses
+/// when it binds looser than the place it is printed in needs
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum Precedence {
+    Sum,
+    Product,
+    /// A leading `-`, like `-x`
+    Unary,
+    Power,
+    Atom,
+}
+
+/// A printed piece and how tightly it binds
+pub(crate) type Printed = (String, Precedence);
+
+/// The output syntax of a printer, like plain text, LaTeX or MathML.
+/// The printer decides where parentheses and signs go, the syntax how the pieces are written
+pub(crate) trait Syntax {
+    fn parentheses(&self, inner: String) -> String;
+
+    fn number(&self, value: &BigRational) -> Printed;
+
+    fn variable(&self, name: &str) -> String;
+
+    fn fraction(&self, numerator: &Expression, denominator: &Expression) -> Printed;
+
+    fn power(&self, base: &Atom, exponent: &Atom) -> Printed;
+
+    fn call(&self, name: &str, args: &[Expression]) -> String;
+
+    /// A term that is more than a single atom, `coefficient` is left out when it is 1 and `-1` is only a sign
+    fn product(&self, coefficient: &BigRational, factors: Vec<Printed>) -> Printed;
+
+    /// `+`, `-` or `=` with the space around it
+    fn operator(&self, operator: &str) -> String;
+
+    /// Pieces written one after the other
+    fn row(&self, pieces: Vec<String>) -> String;
+}
+
+pub(crate) fn parenthesize(
+    syntax: &impl Syntax,
+    (string, precedence): Printed,
+    needed: Precedence,
+) -> Printed {
+    if precedence < needed {
+        (syntax.parentheses(string), Precedence::Atom)
+    } else {
+        (string, precedence)
+    }
+}
+
+pub(crate) fn coefficient(term: &Term) -> BigRational {
+    term.atoms
+        .iter()
+        .filter_map(|atom| atom.kind.as_number())
+        .product()
+}
+
+pub(crate) fn print_atom(syntax: &impl Syntax, atom: &Atom) -> Printed {
+    match &atom.kind {
+        AtomKind::Number(value) => syntax.number(value),
+        AtomKind::Variable(name) => (syntax.variable(name), Precedence::Atom),
+        // the parentheses of a group are only printed when they are needed where it is used
+        AtomKind::Group(expression) => print_expression(syntax, expression),
+        AtomKind::Fraction {
+            numerator,
+            denominator,
+        } => syntax.fraction(numerator, denominator),
+        AtomKind::Power { base, exponent } => syntax.power(base, exponent),
+        AtomKind::Call { name, args } => (syntax.call(name, args), Precedence::Atom),
+    }
+}
+
+/// Prints the numbers of the term as one coefficient in front of the other atoms, like `-2x*y`
+pub(crate) fn print_term(syntax: &impl Syntax, term: &Term) -> Printed {
+    let coefficient = coefficient(term);
+    let mut atoms = term.atoms.iter().filter(|atom| !atom.kind.is_number());
+    if let (true, Some(atom), None) = (coefficient.is_one(), atoms.next(), atoms.next()) {
+        return print_atom(syntax, atom);
+    }
+    let factors = term
+        .atoms
+        .iter()
+        .filter(|atom| !atom.kind.is_number())
+        .map(|atom| print_atom(syntax, atom))
+        .collect::<Vec<_>>();
+    if factors.is_empty() {
+        syntax.number(&coefficient)
+    } else {
+        syntax.product(&coefficient, factors)
+    }
+}
+
+pub(crate) fn print_expression(syntax: &impl Syntax, expression: &Expression) -> Printed {
+    match &expression.terms[..] {
+        [] => syntax.number(&BigRational::zero()),
+        [term] => print_term(syntax, term),
+        [first, rest @ ..] => {
+            let mut pieces = vec![print_term(syntax, first).0];
+            for term in rest {
+                if coefficient(term).is_negative() {
+                    // subtracting the negated term, which must bind tighter than the `-`
+                    let negated = Term {
+                        atoms: std::iter::once(Atom::new(AtomKind::Number(-BigRational::one())))
+                            .chain(term.atoms.iter().cloned())
+                            .collect(),
+                        span: None,
+                    };
+                    pieces.push(syntax.operator("-"));
+                    pieces.push(
+                        parenthesize(syntax, print_term(syntax, &negated), Precedence::Product).0,
+                    );
+                } else {
+                    pieces.push(syntax.operator("+"));
+                    pieces.push(print_term(syntax, term).0);
+                }
+            }
+            (syntax.row(pieces), Precedence::Sum)
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use std::fmt::Display;
+
+use enum_as_inner::EnumAsInner;
+use num_bigint::BigInt;
+use num_integer::{Integer, Roots as _};
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::{
+    eval_atom, eval_expression, exact_power, Atom, AtomKind, DisplayOptions, DisplayWith, Equation,
+    EvalError, Expression, SolveError, Term,
+};
+
+/// Polynomials of a higher degree are not solved
+pub const MAX_DEGREE: usize = 1_000;
+
+const MAX_COEFFICIENT_BITS: u64 = 48;
+
+const MAX_ROOT_CANDIDATES: usize = 100_000;
+
+#[derive(Clone, PartialEq, Debug, EnumAsInner)]
+pub enum Roots {
+    /// The real roots in ascending order, empty when there are none
+    Finite(Vec<Expression>),
+    /// The rational roots, and the factor whose roots could not be found exactly
+    Partial {
+        roots: Vec<Expression>,
+        remaining: Expression,
+    },
+    AllValues,
+}
+
+impl DisplayWith for Roots {
+    fn fmt_with(&self, f: &mut std::fmt::Formatter, options: &DisplayOptions) -> std::fmt::Result {
+        let write_set = |f: &mut std::fmt::Formatter, roots: &[Expression]| {
+            write!(f, "{{")?;
+            for (i, root) in roots.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                root.fmt_with(f, options)?;
+            }
+            write!(f, "}}")
+        };
+        match self {
+            Roots::Finite(roots) => write_set(f, roots),
+            Roots::Partial { roots, remaining } => {
+                write_set(f, roots)?;
+                write!(f, " and the roots of ")?;
+                remaining.fmt_with(f, options)?;
+                write!(f, " = 0")
+            }
+            Roots::AllValues => write!(f, "all values"),
+        }
+    }
+}
+
+impl Display for Roots {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.fmt_with(f, &DisplayOptions::default())
+    }
+}
+
+fn add_polynomials(a: &[BigRational], b: &[BigRational]) -> Vec<BigRational> {
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            a.get(i).cloned().unwrap_or_else(BigRational::zero)
+                + b.get(i).cloned().unwrap_or_else(BigRational::zero)
+        })
+        .collect()
+}
+
+fn multiply_polynomials(a: &[BigRational], b: &[BigRational]) -> Vec<BigRational> {
+    let mut result = vec![BigRational::zero(); (a.len() + b.len()).saturating_sub(1)];
+    for (i, a) in a.iter().enumerate() {
+        for (j, b) in b.iter().enumerate() {
+            result[i + j] += a * b;
+        }
+    }
+    result
+}
+
+fn trim(mut coefficients: Vec<BigRational>) -> Vec<BigRational> {
+    while coefficients.last().is_some_and(Zero::is_zero) {
+        coefficients.pop();
+    }
+    coefficients
+}
+
+fn atom_coefficients(atom: &Atom, variable: &str) -> Result<Vec<BigRational>, SolveError> {
+    let not_polynomial = || SolveError::NotPolynomial {
+        variable: variable.to_string(),
+        span: atom.span.clone(),
+    };
+    if atom.is_exact() {
+        return Ok(vec![eval_atom(atom)?]);
+    }
+    match &atom.kind {
+        AtomKind::Number(_) => unreachable!(),
+        AtomKind::Variable(name) if name == variable => {
+            Ok(vec![BigRational::zero(), BigRational::one()])
+        }
+        AtomKind::Variable(_) => Err(not_polynomial()),
+        AtomKind::Group(expression) => expression_coefficients(expression, variable),
+        AtomKind::Fraction {
+            numerator,
+            denominator,
+        } => {
+            let denominator_span = &denominator.span;
+            let denominator = trim(expression_coefficients(denominator, variable)?);
+            if denominator.is_empty() {
+                return Err(EvalError::DivisionByZero {
+                    atom: atom.clone(),
+                    span: denominator_span.clone(),
+                }
+                .into());
+            }
+            if denominator.len() != 1 {
+                return Err(not_polynomial());
+            }
+            Ok(expression_coefficients(numerator, variable)?
+                .into_iter()
+                .map(|coefficient| coefficient / &denominator[0])
+                .collect())
+        }
+        AtomKind::Power { base, exponent } => {
+            if !exponent.is_exact() {
+                return Err(not_polynomial());
+            }
+            let exponent = eval_atom(exponent)?;
+            if !exponent.is_integer() || exponent.is_negative() {
+                return Err(not_polynomial());
+            }
+            let exponent = exponent
+                .to_integer()
+                .to_usize()
+                .ok_or_else(not_polynomial)?;
+            let base = trim(atom_coefficients(base, variable)?);
+            if base.len() > 1 && (base.len() - 1).saturating_mul(exponent) > MAX_DEGREE {
+                return Err(SolveError::DegreeTooHigh {
+                    variable: variable.to_string(),
+                    span: atom.span.clone(),
+                });
+            }
+            if base.len() == 1 {
+                // a constant, which can only be a huge power when the exponent is
+                return Ok(vec![exact_power(
+                    &base[0],
+                    &BigRational::from_integer(exponent.into()),
+                )
+                .ok_or_else(|| EvalError::TooLarge {
+                    atom: atom.clone(),
+                    span: atom.span.clone(),
+                })?]);
+            }
+            let mut result = vec![BigRational::one()];
+            for _ in 0..exponent {
+                result = multiply_polynomials(&result, &base);
+            }
+            Ok(result)
+        }
+        AtomKind::Call { .. } => Err(not_polynomial()),
+    }
+}
+
+fn term_coefficients(term: &Term, variable: &str) -> Result<Vec<BigRational>, SolveError> {
+    term.atoms
+        .iter()
+        .try_fold(vec![BigRational::one()], |result, atom| {
+            Ok(multiply_polynomials(
+                &result,
+                &atom_coefficients(atom, variable)?,
+            ))
+        })
+}
+
+fn expression_coefficients(
+    expression: &Expression,
+    variable: &str,
+) -> Result<Vec<BigRational>, SolveError> {
+    expression.terms.iter().try_fold(vec![], |result, term| {
+        Ok(add_polynomials(
+            &result,
+            &term_coefficients(term, variable)?,
+        ))
+    })
+}
+
+/// The coefficients of `left - right` in `variable`, from the constant term upwards
+pub fn polynomial_coefficients(
+    equation: &Equation,
+    variable: &str,
+) -> Result<Vec<BigRational>, SolveError> {
+    match equation {
+        Equation::Equality { left, right, .. } => {
+            let left = expression_coefficients(left, variable)?;
+            let right = expression_coefficients(right, variable)?
+                .into_iter()
+                .map(|coefficient| -coefficient)
+                .collect::<Vec<_>>();
+            Ok(trim(add_polynomials(&left, &right)))
+        }
+    }
+}
+
+fn divisors(value: u64) -> Vec<u64> {
+    let mut small = vec![];
+    let mut large = vec![];
+    let mut i = 1;
+    while i * i <= value {
+        if value.is_multiple_of(i) {
+            if i * i != value {
+                large.push(value / i);
+            }
+            small.push(i);
+        }
+        i += 1;
+    }
+    small.extend(large.into_iter().rev());
+    small
+}
+
+fn small(value: &BigInt) -> Option<u64> {
+    (value.bits() <= MAX_COEFFICIENT_BITS)
+        .then(|| value.magnitude().to_u64())
+        .flatten()
+}
+
+fn evaluate_polynomial(coefficients: &[BigRational], x: &BigRational) -> BigRational {
+    coefficients
+        .iter()
+        .rev()
+        .fold(BigRational::zero(), |result, coefficient| {
+            result * x + coefficient
+        })
+}
+
+fn deflate(coefficients: &[BigRational], root: &BigRational) -> Vec<BigRational> {
+    let mut quotient = vec![BigRational::zero(); coefficients.len() - 1];
+    let mut carry = BigRational::zero();
+    for i in (1..coefficients.len()).rev() {
+        carry = carry * root + &coefficients[i];
+        quotient[i - 1] = carry.clone();
+    }
+    quotient
+}
+
+fn find_rational_root(coefficients: &[BigRational]) -> Option<Option<BigRational>> {
+    if coefficients[0].is_zero() {
+        return Some(Some(BigRational::zero()));
+    }
+    // scale to integer coefficients, so every rational root is `p/q` with `p | a0` and `q | an`
+    let scale = coefficients
+        .iter()
+        .fold(BigInt::one(), |scale, coefficient| {
+            scale.lcm(coefficient.denom())
+        });
+    let integers = coefficients
+        .iter()
+        .map(|coefficient| (coefficient * &scale).to_integer())
+        .collect::<Vec<_>>();
+    let numerators = divisors(small(&integers[0])?);
+    let denominators = divisors(small(integers.last().unwrap())?);
+    if numerators.len().saturating_mul(denominators.len()) > MAX_ROOT_CANDIDATES {
+        return None;
+    }
+    // a root `p/q` in lowest terms has `q - p` dividing the value at 1 and `q + p` the value at -1
+    let at_one = integers.iter().sum::<BigInt>();
+    let at_minus_one = integers
+        .iter()
+        .enumerate()
+        .map(|(i, integer)| {
+            if i.is_multiple_of(2) {
+                integer.clone()
+            } else {
+                -integer
+            }
+        })
+        .sum::<BigInt>();
+    for numerator in numerators {
+        for denominator in &denominators {
+            if numerator.gcd(denominator) != 1 {
+                continue;
+            }
+            for sign in [1, -1] {
+                let numerator = BigInt::from(numerator) * sign;
+                let denominator = BigInt::from(*denominator);
+                if !at_one.is_multiple_of(&(&denominator - &numerator))
+                    || !at_minus_one.is_multiple_of(&(&denominator + &numerator))
+                {
+                    continue;
+                }
+                let candidate = BigRational::new(numerator, denominator);
+                if evaluate_polynomial(coefficients, &candidate).is_zero() {
+                    return Some(Some(candidate));
+                }
+            }
+        }
+    }
+    Some(None)
+}
+
+fn binomial_roots(coefficients: &[BigRational]) -> Option<Vec<BigRational>> {
+    let degree = coefficients.len() - 1;
+    let value = -&coefficients[0] / &coefficients[degree];
+    let even = degree.is_multiple_of(2);
+    if even && value.is_negative() {
+        return Some(vec![]);
+    }
+    let root = exact_power(&value.abs(), &BigRational::new(1.into(), degree.into()))?;
+    Some(if even {
+        vec![-&root, root]
+    } else if value.is_negative() {
+        vec![-root]
+    } else {
+        vec![root]
+    })
+}
+
+fn number(value: BigRational) -> Atom {
+    Atom::new(AtomKind::Number(value))
+}
+
+fn scaled(coefficient: BigRational, atom: Atom) -> Vec<Atom> {
+    if coefficient.is_one() {
+        vec![atom]
+    } else {
+        vec![number(coefficient), atom]
+    }
+}
+
+fn simplify_square_root(value: &BigRational) -> Option<(BigRational, BigInt)> {
+    // sqrt(n/d) = sqrt(n*d)/d
+    let mut radicand = small(&(value.numer() * value.denom()))?;
+    let mut factor = 1;
+    let mut i = 2;
+    // once `i^3` is larger, what remains has at most two prime factors, so it is square free or a square
+    while i * i * i <= radicand {
+        while radicand.is_multiple_of(i * i) {
+            radicand /= i * i;
+            factor *= i;
+        }
+        i += 1;
+    }
+    let root = radicand.sqrt();
+    if root * root == radicand {
+        factor *= root;
+        radicand = 1;
+    }
+    Some((
+        BigRational::new(factor.into(), value.denom().clone()),
+        radicand.into(),
+    ))
+}
+
+fn surd(rational: &BigRational, factor: &BigRational, radicand: &BigInt) -> Expression {
+    let denominator = rational.denom().lcm(factor.denom());
+    let numerator = Expression::new(
+        [
+            (rational * &denominator).to_integer(),
+            (factor * &denominator).to_integer(),
+        ]
+        .into_iter()
+        .enumerate()
+        .filter(|(_, value)| !value.is_zero())
+        .map(|(i, value)| {
+            Term::new(if i == 0 {
+                vec![number(value.into())]
+            } else {
+                scaled(
+                    value.into(),
+                    Atom::new(AtomKind::Power {
+                        base: Box::new(number(radicand.clone().into())),
+                        exponent: Box::new(number(BigRational::new(1.into(), 2.into()))),
+                    }),
+                )
+            })
+        })
+        .collect(),
+    );
+    if denominator.is_one() {
+        numerator
+    } else {
+        Expression::new(vec![Term::new(vec![Atom::new(AtomKind::Fraction {
+            numerator,
+            denominator: Expression::new(vec![Term::new(vec![number(denominator.into())])]),
+        })])])
+    }
+}
+
+fn solve_quadratic(coefficients: &[BigRational]) -> Option<Vec<Expression>> {
+    let (c, b, a) = (&coefficients[0], &coefficients[1], &coefficients[2]);
+    let discriminant = b * b - BigRational::from_integer(4.into()) * a * c;
+    if discriminant.is_negative() {
+        return Some(vec![]);
+    }
+    let two_a = BigRational::from_integer(2.into()) * a;
+    let center = -b / &two_a;
+    if let Some(root) = exact_power(&discriminant, &BigRational::new(1.into(), 2.into())) {
+        let offset = root / &two_a;
+        let mut roots = vec![&center - &offset, &center + &offset];
+        roots.dedup();
+        return Some(
+            roots
+                .into_iter()
+                .map(|root| Expression::new(vec![Term::new(vec![number(root)])]))
+                .collect(),
+        );
+    }
+    let (factor, radicand) = simplify_square_root(&discriminant)?;
+    let factor = factor / &two_a;
+    Some(vec![
+        surd(&center, &-&factor, &radicand),
+        surd(&center, &factor, &radicand),
+    ])
+}
+
+fn polynomial_expression(coefficients: &[BigRational], variable: &str) -> Expression {
+    Expression::new(
+        coefficients
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, coefficient)| !coefficient.is_zero())
+            .map(|(power, coefficient)| {
+                Term::new(match power {
+                    0 => vec![number(coefficient.clone())],
+                    1 => scaled(
+                        coefficient.clone(),
+                        Atom::new(AtomKind::Variable(variable.to_string())),
+                    ),
+                    _ => scaled(
+                        coefficient.clone(),
+                        Atom::new(AtomKind::Power {
+                            base: Box::new(Atom::new(AtomKind::Variable(variable.to_string()))),
+                            exponent: Box::new(number(BigRational::from_integer(power.into()))),
+                        }),
+                    ),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Finds the exact real roots of a polynomial equation in `variable`
+pub fn solve_polynomial(equation: &Equation, variable: &str) -> Result<Roots, SolveError> {
+    let mut coefficients = polynomial_coefficients(equation, variable)?;
+    if coefficients.is_empty() {
+        return Ok(Roots::AllValues);
+    }
+    if coefficients.len() > MAX_DEGREE + 1 {
+        return Err(SolveError::DegreeTooHigh {
+            variable: variable.to_string(),
+            span: equation.span().cloned(),
+        });
+    }
+    let too_large = || SolveError::CoefficientTooLarge {
+        variable: variable.to_string(),
+        span: equation.span().cloned(),
+    };
+
+    let mut rational_roots = vec![];
+    if coefficients.len() > 3
+        && !coefficients[0].is_zero()
+        && coefficients[1..coefficients.len() - 1]
+            .iter()
+            .all(Zero::is_zero)
+    {
+        // without the other coefficients the roots are `n`th roots, which are rational or not at all
+        match binomial_roots(&coefficients) {
+            Some(roots) => {
+                rational_roots = roots;
+                coefficients = vec![BigRational::one()];
+            }
+            None => {
+                return Ok(Roots::Partial {
+                    roots: vec![],
+                    remaining: polynomial_expression(&coefficients, variable),
+                })
+            }
+        }
+    }
+    while coefficients.len() > 3 {
+        match find_rational_root(&coefficients).ok_or_else(too_large)? {
+            Some(root) => {
+                coefficients = deflate(&coefficients, &root);
+                rational_roots.push(root);
+            }
+            None => break,
+        }
+    }
+
+    let mut roots = rational_roots
+        .into_iter()
+        .map(|root| Expression::new(vec![Term::new(vec![number(root)])]))
+        .collect::<Vec<_>>();
+    let remaining = match coefficients.len() {
+        1 => None,
+        2 => {
+            roots.push(Expression::new(vec![Term::new(vec![number(
+                -&coefficients[0] / &coefficients[1],
+            )])]));
+            None
+        }
+        3 => {
+            roots.extend(solve_quadratic(&coefficients).ok_or_else(too_large)?);
+            None
+        }
+        _ => Some(polynomial_expression(&coefficients, variable)),
+    };
+
+    roots.sort_by_cached_key(|root| eval_expression(root).ok());
+    roots.dedup();
+    Ok(match remaining {
+        Some(remaining) => Roots::Partial { roots, remaining },
+        None => Roots::Finite(roots),
+    })
+}
@@ -0,0 +1,300 @@
+use std::sync::OnceLock;
+
+use derive_more::{Display, IsVariant};
+use enum_as_inner::EnumAsInner;
+
+use crate::{
+    algebra_rules, builtin_rules, into_atom, parse_expression, rewrite, wildcards, Atom, AtomKind,
+    Budget, Expression, Lexer, ParsingError, RewriteError, SourceSpan, Term,
+};
+
+#[derive(Clone, PartialEq, Debug, Display, IsVariant, EnumAsInner)]
+pub enum RuleError {
+    ParsingError(ParsingError),
+    #[display(fmt = "Rule '{name}' must look like 'pattern -> replacement'")]
+    MissingArrow {
+        name: String,
+    },
+    #[display(fmt = "{span}: Wildcard '{wildcard}' is not in the pattern")]
+    UnboundWildcard {
+        wildcard: String,
+        span: SourceSpan,
+    },
+}
+
+impl From<ParsingError> for RuleError {
+    fn from(error: ParsingError) -> RuleError {
+        RuleError::ParsingError(error)
+    }
+}
+
+/// A rule written in Rust, for simplifications that compute something, like adding numbers.
+/// It returns `None` when it does not apply, and stops with an error when it goes over the budget of the pass
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Builtin {
+    Atom(fn(&Atom, &Budget) -> Result<Option<Atom>, RewriteError>),
+    Term(fn(&Term, &Budget) -> Result<Option<Term>, RewriteError>),
+    Expression(fn(&Expression, &Budget) -> Result<Option<Expression>, RewriteError>),
+}
+
+#[derive(Clone, Debug)]
+enum RuleKind {
+    Rewrite {
+        pattern: Expression,
+        replacement: Expression,
+    },
+    Builtin(Builtin),
+}
+
+/// A simplification, like `?a^1 -> ?a`
+#[derive(Clone, Debug)]
+pub struct Rule {
+    name: String,
+    kind: RuleKind,
+}
+
+impl Rule {
+    /// Parses a rule like `?a*(?b + ?c) -> ?a*?b + ?a*?c`, where `?a` is a wildcard.
+    /// A wildcard that is a whole expression, like the argument of `sin(?a)`, matches a whole expression,
+    /// one that is a whole term of a sum matches a whole term, anywhere else it matches a single atom,
+    /// and terms and atoms are matched in any order, so `?a*(?b + ?c)` also matches `(x + 1)*2`.
+    /// The name is used as the file path of errors
+    pub fn parse(name: &str, source: &str) -> Result<Rule, RuleError> {
+        let Some((pattern, _)) = source.split_once("->") else {
+            return Err(RuleError::MissingArrow {
+                name: name.to_string(),
+            });
+        };
+        // the pattern and the arrow are blanked out instead of cut off, so the spans of the replacement are right
+        let blanked = source
+            .chars()
+            .enumerate()
+            .map(|(i, chr)| {
+                if i < pattern.chars().count() + 2 && chr != '\n' {
+                    ' '
+                } else {
+                    chr
+                }
+            })
+            .collect::<String>();
+        let pattern = parse_expression(&mut Lexer::with_wildcards(name, pattern))?;
+        let replacement = parse_expression(&mut Lexer::with_wildcards(name, &blanked))?;
+        let bound = wildcards(&pattern);
+        if let Some(unbound) = wildcards(&replacement)
+            .into_iter()
+            .find(|wildcard| !bound.iter().any(|bound| bound.kind == wildcard.kind))
+        {
+            return Err(RuleError::UnboundWildcard {
+                wildcard: unbound.kind.as_variable().unwrap().clone(),
+                span: unbound.span.clone().unwrap(),
+            });
+        }
+        Ok(Rule {
+            name: name.to_string(),
+            kind: RuleKind::Rewrite {
+                pattern,
+                replacement,
+            },
+        })
+    }
+
+    pub(crate) fn builtin(name: &str, builtin: Builtin) -> Rule {
+        Rule {
+            name: name.to_string(),
+            kind: RuleKind::Builtin(builtin),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the rule is one of the simplifications written in Rust
+    pub fn is_builtin(&self) -> bool {
+        matches!(self.kind, RuleKind::Builtin(_))
+    }
+
+    /// The pattern and replacement of a parsed rule
+    pub(crate) fn as_rewrite(&self) -> Option<(&Expression, &Expression)> {
+        match &self.kind {
+            RuleKind::Rewrite {
+                pattern,
+                replacement,
+            } => Some((pattern, replacement)),
+            RuleKind::Builtin(_) => None,
+        }
+    }
+}
+
+/// The builtin rules are made once, as `simplify` uses them on every call
+static BUILTIN_RULE_SET: OnceLock<RuleSet> = OnceLock::new();
+
+pub(crate) fn builtin_rule_set() -> &'static RuleSet {
+    BUILTIN_RULE_SET.get_or_init(|| RuleSet {
+        rules: builtin_rules(),
+    })
+}
+
+static ALGEBRA_RULE_SET: OnceLock<RuleSet> = OnceLock::new();
+
+pub(crate) fn algebra_rule_set() -> &'static RuleSet {
+    ALGEBRA_RULE_SET.get_or_init(|| RuleSet {
+        rules: algebra_rules(),
+    })
+}
+
+/// The rules that `simplify_with` uses, in the order they are tried
+#[derive(Clone, Debug)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl Default for RuleSet {
+    /// The rules of `simplify`, see `RuleSet::builtin`
+    fn default() -> RuleSet {
+        RuleSet::builtin()
+    }
+}
+
+impl RuleSet {
+    /// A rule set without any rules
+    pub fn new() -> RuleSet {
+        RuleSet { rules: vec![] }
+    }
+
+    /// The rules of `simplify`, which evaluate constants, collect coefficients, combine like terms,
+    /// distribute products over sums, turn divisions by numbers into multiplications and remove powers of 0 and 1
+    pub fn builtin() -> RuleSet {
+        builtin_rule_set().clone()
+    }
+
+    /// The identities of `simplify_expression_saturated`, like `?a + ?b -> ?b + ?a` and `?a*?b + ?a*?c -> ?a*(?b + ?c)`.
+    /// They go both ways, which would make the fast rewriting of `simplify_with` loop
+    pub fn algebra() -> RuleSet {
+        algebra_rule_set().clone()
+    }
+
+    /// Adds a rule, which is tried after the rules that are already there
+    pub fn add(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Rule> {
+        let index = self.rules.iter().position(|rule| rule.name == name)?;
+        Some(self.rules.remove(index))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter()
+    }
+
+    /// Rewrites every part of the expression once, each rule that applies to a part is used on it in order,
+    /// and then the parts of the result are rewritten
+    pub(crate) fn rewrite_expression(
+        &self,
+        expression: &Expression,
+        budget: &Budget,
+    ) -> Result<Expression, RewriteError> {
+        let mut expression = expression.clone();
+        for rule in &self.rules {
+            let rewritten = match &rule.kind {
+                RuleKind::Rewrite {
+                    pattern,
+                    replacement,
+                } => rewrite(&expression, pattern, replacement),
+                RuleKind::Builtin(Builtin::Expression(builtin)) => builtin(&expression, budget)?,
+                RuleKind::Builtin(_) => None,
+            };
+            if let Some(rewritten) = rewritten {
+                expression = rewritten;
+            }
+        }
+        Ok(Expression {
+            terms: expression
+                .terms
+                .iter()
+                .map(|term| self.rewrite_term(term, budget))
+                .collect::<Result<_, _>>()?,
+            span: expression.span.clone(),
+        })
+    }
+
+    fn rewrite_term(&self, term: &Term, budget: &Budget) -> Result<Term, RewriteError> {
+        let mut term = term.clone();
+        for rule in &self.rules {
+            if let RuleKind::Builtin(Builtin::Term(builtin)) = &rule.kind {
+                if let Some(rewritten) = builtin(&term, budget)? {
+                    term = rewritten;
+                }
+            }
+        }
+        Ok(Term {
+            atoms: term
+                .atoms
+                .iter()
+                .map(|atom| self.rewrite_atom(atom, false, budget))
+                .collect::<Result<_, _>>()?,
+            span: term.span.clone(),
+        })
+    }
+
+    /// `alone` is set for the base and exponent of a power, which are not part of an expression,
+    /// so the rewrite rules are tried on them as an expression of their own
+    fn rewrite_atom(
+        &self,
+        atom: &Atom,
+        alone: bool,
+        budget: &Budget,
+    ) -> Result<Atom, RewriteError> {
+        for rule in &self.rules {
+            let rewritten = match &rule.kind {
+                RuleKind::Builtin(Builtin::Atom(builtin)) => builtin(atom, budget)?,
+                RuleKind::Rewrite {
+                    pattern,
+                    replacement,
+                } if alone => rewrite(
+                    &Expression::new(vec![Term::new(vec![atom.clone()])]),
+                    pattern,
+                    replacement,
+                )
+                .map(into_atom),
+                _ => None,
+            };
+            if let Some(rewritten) = rewritten {
+                return Ok(rewritten);
+            }
+        }
+        let kind = match &atom.kind {
+            AtomKind::Number(_) | AtomKind::Variable(_) => return Ok(atom.clone()),
+            AtomKind::Group(expression) => {
+                AtomKind::Group(self.rewrite_expression(expression, budget)?)
+            }
+            AtomKind::Fraction {
+                numerator,
+                denominator,
+            } => AtomKind::Fraction {
+                numerator: self.rewrite_expression(numerator, budget)?,
+                denominator: self.rewrite_expression(denominator, budget)?,
+            },
+            AtomKind::Power { base, exponent } => AtomKind::Power {
+                base: Box::new(self.rewrite_atom(base, true, budget)?),
+                exponent: Box::new(self.rewrite_atom(exponent, true, budget)?),
+            },
+            AtomKind::Call { name, args } => AtomKind::Call {
+                name: name.clone(),
+                args: args
+                    .iter()
+                    .map(|argument| self.rewrite_expression(argument, budget))
+                    .collect::<Result<_, _>>()?,
+            },
+        };
+        Ok(Atom {
+            kind,
+            span: atom.span.clone(),
+        })
+    }
+}
@@ -0,0 +1,100 @@
+use derive_more::{Display, IsVariant};
+use enum_as_inner::EnumAsInner;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The version of the JSON shape, it changes whenever a document of the old shape would be read differently
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Rationals are written as `{"num": "-7", "den": "3"}` with both integers as decimal strings,
+/// so they keep their precision in JSON parsers that read numbers as floats
+pub(crate) mod rational {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Rational {
+        num: String,
+        den: String,
+    }
+
+    pub fn serialize<S: Serializer>(value: &BigRational, serializer: S) -> Result<S::Ok, S::Error> {
+        Rational {
+            num: value.numer().to_string(),
+            den: value.denom().to_string(),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BigRational, D::Error> {
+        use serde::de::Error;
+
+        let Rational { num, den } = Rational::deserialize(deserializer)?;
+        let num = num.parse::<BigInt>().map_err(D::Error::custom)?;
+        let den = den.parse::<BigInt>().map_err(D::Error::custom)?;
+        if den == BigInt::from(0) {
+            return Err(D::Error::custom("the denominator of a rational is 0"));
+        }
+        Ok(BigRational::new(num, den))
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Display, IsVariant, EnumAsInner)]
+pub enum JsonError {
+    #[display(fmt = "Invalid JSON: {_0}")]
+    Invalid(String),
+    #[display(
+        fmt = "JSON schema version {version} is not supported, the supported version is {}",
+        JSON_SCHEMA_VERSION
+    )]
+    UnsupportedVersion { version: u32 },
+}
+
+#[derive(Serialize)]
+struct Document<'a, T> {
+    version: u32,
+    value: &'a T,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct OwnedDocument<T> {
+    value: T,
+}
+
+/// Writes a value, like an `Equation`, as a JSON document of the form `{"version": 1, "value": ...}`.
+///
+/// In version 1 of the shape
+/// - structs are objects with their fields, like `{"kind": ..., "span": ...}` for an `Atom`,
+///   and a `span` that is `None` is left out
+/// - enums with data, like `AtomKind`, are `{"type": "fraction", "value": {"numerator": ..., "denominator": ...}}`,
+///   with the name of the variant in snake case, and `"value"` left out for variants without data
+/// - enums without data, like `TokenKind`, are the name of the variant in snake case, like `"open_parenthesis"`
+/// - rationals are `{"num": "-7", "den": "3"}`, with the integers as decimal strings
+pub fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(&Document {
+        version: JSON_SCHEMA_VERSION,
+        value,
+    })
+    .expect("the AST has no maps with keys that are not strings")
+}
+
+/// Reads a value written by `to_json`, which must have a supported version
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, JsonError> {
+    let header = serde_json::from_str::<Header>(json)
+        .map_err(|error| JsonError::Invalid(error.to_string()))?;
+    if header.version != JSON_SCHEMA_VERSION {
+        return Err(JsonError::UnsupportedVersion {
+            version: header.version,
+        });
+    }
+    serde_json::from_str::<OwnedDocument<T>>(json)
+        .map(|document| document.value)
+        .map_err(|error| JsonError::Invalid(error.to_string()))
+}
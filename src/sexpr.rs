@@ -0,0 +1,390 @@
+use derive_more::{Display, IsVariant};
+use enum_as_inner::EnumAsInner;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+use crate::{Arity, Atom, AtomKind, Equation, Expression, SourceLocation, Term};
+
+#[derive(Clone, PartialEq, Debug, Display, IsVariant, EnumAsInner)]
+pub enum SexprError {
+    #[display(fmt = "{location}: Unexpected end of input")]
+    UnexpectedEnd { location: SourceLocation },
+    #[display(fmt = "{location}: Unexpected ')'")]
+    UnexpectedCloseParenthesis { location: SourceLocation },
+    #[display(fmt = "{location}: Expected the end of input")]
+    TrailingInput { location: SourceLocation },
+    #[display(fmt = "{location}: Expected an operator or function name at the start of the list")]
+    ExpectedOperator { location: SourceLocation },
+    #[display(fmt = "{location}: '{operator}' takes {expected}, but got {got}")]
+    WrongOperandCount {
+        location: SourceLocation,
+        operator: String,
+        expected: Arity,
+        got: usize,
+    },
+    #[display(fmt = "{location}: Expected an equation like (= left right)")]
+    ExpectedEquation { location: SourceLocation },
+    /// A symbol that is neither a number nor a name the parser accepts, like `2x` or `x+1`
+    #[display(fmt = "{location}: '{text}' is not a number or a name")]
+    InvalidSymbol {
+        location: SourceLocation,
+        text: String,
+    },
+    #[display(fmt = "{location}: The number '{text}' has a denominator of 0")]
+    ZeroDenominator {
+        location: SourceLocation,
+        text: String,
+    },
+}
+
+fn is_sum_or_product(sexpr: &str) -> bool {
+    sexpr.starts_with("(+") || sexpr.starts_with("(*")
+}
+
+fn list(head: &str, items: impl IntoIterator<Item = String>) -> String {
+    let mut result = format!("({head}");
+    for item in items {
+        result.push(' ');
+        result.push_str(&item);
+    }
+    result.push(')');
+    result
+}
+
+fn print_atom(atom: &Atom) -> String {
+    match &atom.kind {
+        AtomKind::Number(value) => value.to_string(),
+        AtomKind::Variable(name) => name.clone(),
+        // a group must be a `+` or `*` list to be read back as a group
+        AtomKind::Group(expression) => {
+            let sexpr = print_expression(expression);
+            if is_sum_or_product(&sexpr) {
+                sexpr
+            } else {
+                list("+", [sexpr])
+            }
+        }
+        AtomKind::Fraction {
+            numerator,
+            denominator,
+        } => list(
+            "/",
+            [print_expression(numerator), print_expression(denominator)],
+        ),
+        AtomKind::Power { base, exponent } => list("^", [print_atom(base), print_atom(exponent)]),
+        AtomKind::Call { name, args } => list(name, args.iter().map(print_expression)),
+    }
+}
+
+fn print_term(term: &Term) -> String {
+    if let [atom] = &term.atoms[..] {
+        let sexpr = print_atom(atom);
+        if !is_sum_or_product(&sexpr) {
+            return sexpr;
+        }
+    }
+    list("*", term.atoms.iter().map(print_atom))
+}
+
+fn print_expression(expression: &Expression) -> String {
+    if let [term] = &expression.terms[..] {
+        let sexpr = print_term(term);
+        if !sexpr.starts_with("(+") {
+            return sexpr;
+        }
+    }
+    list("+", expression.terms.iter().map(print_term))
+}
+
+impl Atom {
+    /// The atom as an S-expression, like `(^ x 2)`
+    pub fn to_sexpr(&self) -> String {
+        print_atom(self)
+    }
+}
+
+impl Term {
+    /// The term as an S-expression, like `(* 2 x)`
+    pub fn to_sexpr(&self) -> String {
+        print_term(self)
+    }
+}
+
+impl Expression {
+    /// The expression as an S-expression, like `(+ (* 2 x) 3)`.
+    ///
+    /// The position of a list says what node it is, an expression is `(+ term...)` or a single term,
+    /// a term is `(* atom...)` or a single atom, and an atom that is a `+` or `*` list is a group,
+    /// like the `(+ x 1)` of `(* 2 (+ x 1))`.
+    /// Fractions are `(/ numerator denominator)`, powers are `(^ base exponent)`,
+    /// calls are `(name argument...)` and numbers are exact, like `-1/3`
+    pub fn to_sexpr(&self) -> String {
+        print_expression(self)
+    }
+}
+
+impl Equation {
+    /// The equation as an S-expression, like `(= (+ (* 2 x) 3) 7)`
+    pub fn to_sexpr(&self) -> String {
+        match self {
+            Equation::Equality { left, right, .. } => {
+                list("=", [print_expression(left), print_expression(right)])
+            }
+        }
+    }
+}
+
+enum Sexpr {
+    Symbol {
+        text: String,
+        location: SourceLocation,
+    },
+    List {
+        items: Vec<Sexpr>,
+        location: SourceLocation,
+    },
+}
+
+impl Sexpr {
+    fn location(&self) -> &SourceLocation {
+        match self {
+            Sexpr::Symbol { location, .. } | Sexpr::List { location, .. } => location,
+        }
+    }
+}
+
+struct Reader {
+    source: Vec<char>,
+    position: usize,
+    location: SourceLocation,
+}
+
+impl Reader {
+    fn skip_whitespace(&mut self) {
+        while self.peek_char().is_some_and(char::is_whitespace) {
+            self.next_char();
+        }
+    }
+
+    fn read(&mut self) -> Result<Sexpr, SexprError> {
+        self.skip_whitespace();
+        let location = self.location.clone();
+        match self.peek_char() {
+            None => Err(SexprError::UnexpectedEnd { location }),
+            Some(')') => Err(SexprError::UnexpectedCloseParenthesis { location }),
+            Some('(') => {
+                self.next_char();
+                let mut items = vec![];
+                loop {
+                    self.skip_whitespace();
+                    if self.peek_char() == Some(')') {
+                        self.next_char();
+                        return Ok(Sexpr::List { items, location });
+                    }
+                    items.push(self.read()?);
+                }
+            }
+            Some(_) => {
+                let mut text = String::new();
+                while let Some(chr) = self
+                    .peek_char()
+                    .filter(|chr| !chr.is_whitespace() && !"()".contains(*chr))
+                {
+                    text.push(chr);
+                    self.next_char();
+                }
+                Ok(Sexpr::Symbol { text, location })
+            }
+        }
+    }
+
+    /// Reads the only S-expression of the source
+    fn read_all(source: &str) -> Result<Sexpr, SexprError> {
+        let mut reader = Reader {
+            source: source.chars().collect(),
+            position: 0,
+            location: SourceLocation { line: 1, column: 1 },
+        };
+        let sexpr = reader.read()?;
+        reader.skip_whitespace();
+        if reader.peek_char().is_some() {
+            return Err(SexprError::TrailingInput {
+                location: reader.location,
+            });
+        }
+        Ok(sexpr)
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.source.get(self.position).copied()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let current = self.peek_char()?;
+        self.position += 1;
+        self.location.column += 1;
+        if current == '\n' {
+            self.location.line += 1;
+            self.location.column = 1;
+        }
+        Some(current)
+    }
+}
+
+/// A number like `2`, `-1/3` or `2.5`, `None` for anything else
+fn number(text: &str, location: &SourceLocation) -> Result<Option<BigRational>, SexprError> {
+    if let Some((numerator, denominator)) = text.split_once('/') {
+        let (Ok(numerator), Ok(denominator)) =
+            (numerator.parse::<BigInt>(), denominator.parse::<BigInt>())
+        else {
+            return Ok(None);
+        };
+        if denominator == BigInt::from(0) {
+            return Err(SexprError::ZeroDenominator {
+                location: location.clone(),
+                text: text.to_string(),
+            });
+        }
+        return Ok(Some(BigRational::new(numerator, denominator)));
+    }
+    let (integer, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if !fraction.chars().all(|chr| chr.is_ascii_digit()) {
+        return Ok(None);
+    }
+    let Ok(value) = format!("{integer}{fraction}").parse::<BigInt>() else {
+        return Ok(None);
+    };
+    Ok(Some(BigRational::new(
+        value,
+        BigInt::from(10).pow(fraction.len() as u32),
+    )))
+}
+
+/// A name like the parser reads it, a letter followed by letters and digits
+fn name(text: &str, location: &SourceLocation) -> Result<String, SexprError> {
+    let mut chars = text.chars();
+    if chars.next().is_some_and(char::is_alphabetic) && chars.all(char::is_alphanumeric) {
+        Ok(text.to_string())
+    } else {
+        Err(SexprError::InvalidSymbol {
+            location: location.clone(),
+            text: text.to_string(),
+        })
+    }
+}
+
+/// The operator at the start of a list and its operands
+fn operator(items: &[Sexpr], location: &SourceLocation) -> Result<(String, usize), SexprError> {
+    match items.first() {
+        Some(Sexpr::Symbol { text, .. }) => Ok((text.clone(), items.len() - 1)),
+        _ => Err(SexprError::ExpectedOperator {
+            location: location.clone(),
+        }),
+    }
+}
+
+fn check_operands(
+    operator: &str,
+    expected: Arity,
+    got: usize,
+    location: &SourceLocation,
+) -> Result<(), SexprError> {
+    if expected.accepts(got) {
+        Ok(())
+    } else {
+        Err(SexprError::WrongOperandCount {
+            location: location.clone(),
+            operator: operator.to_string(),
+            expected,
+            got,
+        })
+    }
+}
+
+fn read_atom(sexpr: &Sexpr) -> Result<Atom, SexprError> {
+    let (items, location) = match sexpr {
+        Sexpr::Symbol { text, location } => {
+            return Ok(Atom::new(match number(text, location)? {
+                Some(value) => AtomKind::Number(value),
+                None => AtomKind::Variable(name(text, location)?),
+            }))
+        }
+        Sexpr::List { items, location } => (items, location),
+    };
+    let (operator, count) = operator(items, location)?;
+    let operands = &items[1..];
+    let kind = match operator.as_str() {
+        "+" | "*" => AtomKind::Group(read_expression(sexpr)?),
+        "/" => {
+            check_operands(&operator, Arity::Exactly(2), count, location)?;
+            AtomKind::Fraction {
+                numerator: read_expression(&operands[0])?,
+                denominator: read_expression(&operands[1])?,
+            }
+        }
+        "^" => {
+            check_operands(&operator, Arity::Exactly(2), count, location)?;
+            AtomKind::Power {
+                base: Box::new(read_atom(&operands[0])?),
+                exponent: Box::new(read_atom(&operands[1])?),
+            }
+        }
+        _ => AtomKind::Call {
+            name: name(&operator, items[0].location())?,
+            args: operands
+                .iter()
+                .map(read_expression)
+                .collect::<Result<_, _>>()?,
+        },
+    };
+    Ok(Atom::new(kind))
+}
+
+fn is_list_of(sexpr: &Sexpr, head: &str) -> bool {
+    matches!(
+        sexpr,
+        Sexpr::List { items, .. }
+            if matches!(items.first(), Some(Sexpr::Symbol { text, .. }) if text == head)
+    )
+}
+
+fn read_term(sexpr: &Sexpr) -> Result<Term, SexprError> {
+    match sexpr {
+        Sexpr::List { items, .. } if is_list_of(sexpr, "*") => Ok(Term::new(
+            items[1..].iter().map(read_atom).collect::<Result<_, _>>()?,
+        )),
+        _ => Ok(Term::new(vec![read_atom(sexpr)?])),
+    }
+}
+
+fn read_expression(sexpr: &Sexpr) -> Result<Expression, SexprError> {
+    match sexpr {
+        Sexpr::List { items, .. } if is_list_of(sexpr, "+") => Ok(Expression::new(
+            items[1..].iter().map(read_term).collect::<Result<_, _>>()?,
+        )),
+        _ => Ok(Expression::new(vec![read_term(sexpr)?])),
+    }
+}
+
+/// Reads an expression printed by `Expression::to_sexpr`, the nodes have no spans
+pub fn parse_sexpr_expression(source: &str) -> Result<Expression, SexprError> {
+    read_expression(&Reader::read_all(source)?)
+}
+
+/// Reads an equation printed by `Equation::to_sexpr`, the nodes have no spans
+pub fn parse_sexpr_equation(source: &str) -> Result<Equation, SexprError> {
+    let sexpr = Reader::read_all(source)?;
+    match &sexpr {
+        Sexpr::List { items, location } if is_list_of(&sexpr, "=") => {
+            check_operands("=", Arity::Exactly(2), items.len() - 1, location)?;
+            Ok(Equation::Equality {
+                left: read_expression(&items[1])?,
+                right: read_expression(&items[2])?,
+                span: None,
+            })
+        }
+        _ => Err(SexprError::ExpectedEquation {
+            location: sexpr.location().clone(),
+        }),
+    }
+}
@@ -1,61 +1,126 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+use derive_more::{Display, IsVariant};
+use enum_as_inner::EnumAsInner;
 use num_rational::BigRational;
 
-use crate::{eval_atom, eval_expression, eval_term, Atom, Equation, Expression, Term};
-
-fn simplify_atom(atom: &Atom) -> Atom {
-    match atom {
-        Atom::Number(value) => Atom::Number(value.clone()),
-        Atom::Variable(name) => Atom::Variable(name.clone()),
-        Atom::Group(expression) => {
-            if !expression.contains_variable() {
-                Atom::Number(eval_expression(expression))
-            } else {
-                Atom::Group(simplify_expression(expression))
-            }
-        }
-        Atom::Fraction {
-            numerator,
-            denominator,
-        } => {
-            if !numerator.contains_variable() && !denominator.contains_variable() {
-                Atom::Number(eval_atom(atom))
-            } else {
-                Atom::Fraction {
-                    numerator: simplify_expression(numerator),
-                    denominator: simplify_expression(denominator),
-                }
-            }
-        }
+use crate::{
+    builtin_rule_set, coefficient, eval_atom, eval_expression, eval_term, merge_spans, Atom,
+    AtomKind, Builtin, DisplayOptions, DisplayWith, Equation, EvalError, Expression, NumberFormat,
+    Rule, RuleSet, SourceSpan, Term,
+};
+
+fn number(value: BigRational, span: Option<SourceSpan>) -> Atom {
+    Atom {
+        kind: AtomKind::Number(value),
+        span,
     }
 }
 
-fn simplify_term(term: &Term) -> Term {
-    Term {
-        atoms: if !term.contains_variable() {
-            vec![Atom::Number(eval_term(term))]
-        } else if term.atoms.iter().any(|atom| {
-            atom.as_number().map_or(false, |number| {
-                number == &BigRational::from_float(0.0).unwrap()
-            })
-        }) {
-            vec![Atom::Number(BigRational::from_float(0.0).unwrap())]
-        } else {
-            let amount: BigRational = term
+fn multiply_terms(terms: &[Term], atom: &Atom) -> Vec<Term> {
+    terms
+        .iter()
+        .map(|term| Term {
+            atoms: term
                 .atoms
                 .iter()
-                .filter_map(|atom| (!atom.contains_variable()).then(|| eval_atom(atom)))
-                .product();
-            let other_atoms = term.atoms.iter().filter(|atom| atom.contains_variable());
-            if amount == BigRational::from_float(1.0).unwrap() {
-                other_atoms.map(simplify_atom).collect()
-            } else {
-                std::iter::once(&Atom::Number(amount))
-                    .chain(other_atoms)
-                    .map(simplify_atom)
-                    .collect()
-            }
-        },
+                .cloned()
+                .chain(std::iter::once(atom.clone()))
+                .collect(),
+            span: term.span.clone(),
+        })
+        .collect()
+}
+
+fn evaluate_constants(atom: &Atom, _budget: &Budget) -> Result<Option<Atom>, RewriteError> {
+    if atom.kind.is_number() || !atom.is_exact() {
+        return Ok(None);
+    }
+    Ok(Some(number(eval_atom(atom)?, atom.span.clone())))
+}
+
+fn divide_by_constant(atom: &Atom, _budget: &Budget) -> Result<Option<Atom>, RewriteError> {
+    let Some((numerator, denominator)) = atom.kind.as_fraction() else {
+        return Ok(None);
+    };
+    if !denominator.is_exact() {
+        return Ok(None);
+    }
+    let value = eval_expression(denominator)?;
+    if value == BigRational::from_float(0.0).unwrap() {
+        return Err(EvalError::DivisionByZero {
+            atom: atom.clone(),
+            span: denominator.span.clone(),
+        }
+        .into());
+    }
+    let amount = number(value.recip(), denominator.span.clone());
+    Ok(Some(Atom {
+        kind: AtomKind::Group(Expression {
+            terms: numerator
+                .terms
+                .iter()
+                .map(|term| Term {
+                    atoms: std::iter::once(amount.clone())
+                        .chain(term.atoms.iter().cloned())
+                        .collect(),
+                    span: term.span.clone(),
+                })
+                .collect(),
+            span: atom.span.clone(),
+        }),
+        span: atom.span.clone(),
+    }))
+}
+
+fn zero(term: &Term) -> Option<&Atom> {
+    term.atoms.iter().find(|atom| {
+        atom.kind
+            .as_number()
+            .is_some_and(|number| number == &BigRational::from_float(0.0).unwrap())
+    })
+}
+
+fn multiply_by_zero(term: &Term, _budget: &Budget) -> Result<Option<Term>, RewriteError> {
+    Ok(zero(term)
+        .filter(|_| term.atoms.len() > 1)
+        .map(|zero| Term {
+            atoms: vec![zero.clone()],
+            span: term.span.clone(),
+        }))
+}
+
+fn coefficient_atoms(term: &Term) -> Result<Vec<Atom>, EvalError> {
+    if term.is_exact() {
+        return Ok(vec![number(eval_term(term)?, term.span.clone())]);
     }
+    let exact_atoms = term.atoms.iter().filter(|atom| atom.is_exact());
+    let amount: BigRational = exact_atoms
+        .clone()
+        .map(eval_atom)
+        .product::<Result<_, _>>()?;
+    let amount_span = merge_spans(exact_atoms.map(|atom| &atom.span));
+    let other_atoms = term.atoms.iter().filter(|atom| !atom.is_exact()).cloned();
+    Ok(if amount == BigRational::from_float(1.0).unwrap() {
+        other_atoms.collect()
+    } else {
+        std::iter::once(number(amount, amount_span))
+            .chain(other_atoms)
+            .collect()
+    })
+}
+
+fn collect_coefficients(term: &Term, _budget: &Budget) -> Result<Option<Term>, RewriteError> {
+    let atoms = coefficient_atoms(term)?;
+    Ok((atoms != term.atoms).then(|| Term {
+        atoms,
+        span: term.span.clone(),
+    }))
 }
 
 fn collect_like_terms(terms: &[Term]) -> Vec<Vec<&Term>> {
@@ -63,13 +128,13 @@ fn collect_like_terms(terms: &[Term]) -> Vec<Vec<&Term>> {
         let mut a_atoms = a
             .atoms
             .iter()
-            .filter(|atom| atom.contains_variable())
+            .filter(|atom| !atom.is_exact())
             .collect::<Vec<_>>();
         a_atoms.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
         let mut b_atoms = b
             .atoms
             .iter()
-            .filter(|atom| atom.contains_variable())
+            .filter(|atom| !atom.is_exact())
             .collect::<Vec<_>>();
         b_atoms.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
         a_atoms == b_atoms
@@ -83,218 +148,710 @@ fn collect_like_terms(terms: &[Term]) -> Vec<Vec<&Term>> {
     like_terms
 }
 
-fn simplify_expression(expression: &Expression) -> Expression {
-    Expression {
-        terms: if !expression.contains_variable() {
-            vec![Term {
-                atoms: vec![Atom::Number(eval_expression(expression))],
-            }]
-        } else {
-            let like_terms = collect_like_terms(&expression.terms);
-            let mut terms = like_terms
+fn combine_like_terms(
+    expression: &Expression,
+    _budget: &Budget,
+) -> Result<Option<Expression>, RewriteError> {
+    // this also turns a sum without terms into `0`
+    if expression.is_exact() {
+        let terms = vec![Term {
+            atoms: vec![number(
+                eval_expression(expression)?,
+                expression.span.clone(),
+            )],
+            span: expression.span.clone(),
+        }];
+        return Ok((terms != expression.terms).then(|| Expression {
+            terms,
+            span: expression.span.clone(),
+        }));
+    }
+    let terms = collect_like_terms(&expression.terms)
+        .iter()
+        .map(|terms| {
+            let amount: BigRational = terms
                 .iter()
-                .map(|terms| {
-                    let amount: BigRational = terms
+                .map(|term| {
+                    term.atoms
                         .iter()
-                        .map::<BigRational, _>(|term| {
-                            term.atoms
-                                .iter()
-                                .filter_map(|atom| {
-                                    (!atom.contains_variable()).then(|| eval_atom(atom))
-                                })
-                                .product()
-                        })
-                        .sum();
-                    Term {
-                        atoms: std::iter::once(&Atom::Number(amount))
-                            .chain(
-                                terms[0]
-                                    .atoms
-                                    .iter()
-                                    .filter(|atom| atom.contains_variable()),
-                            )
-                            .cloned()
-                            .collect(),
-                    }
+                        .filter(|atom| atom.is_exact())
+                        .map(eval_atom)
+                        .product::<Result<BigRational, _>>()
                 })
-                .map(|term| simplify_term(&term))
-                .collect::<Vec<_>>();
-            let mut i = 0;
-            while i < terms.len() {
-                let term = &mut terms[i];
-                if let Some(group) = term
-                    .atoms
+                .sum::<Result<_, _>>()?;
+            let amount_span = merge_spans(
+                terms
                     .iter()
-                    .enumerate()
-                    .find_map(|(i, atom)| atom.is_group().then_some(i))
-                {
-                    let mut atoms = term.atoms.clone();
-                    let group = atoms.remove(group).into_group().unwrap();
-                    terms.append(
-                        &mut group
-                            .terms
-                            .iter()
-                            .map(|term| Term {
-                                atoms: term.atoms.iter().chain(atoms.iter()).cloned().collect(),
-                            })
-                            .collect(),
-                    );
-                    terms.remove(i);
-                } else {
-                    i = i + 1;
-                }
-            }
-            terms
-        },
+                    .flat_map(|term| term.atoms.iter().filter(|atom| atom.is_exact()))
+                    .map(|atom| &atom.span),
+            );
+            let term = Term {
+                atoms: std::iter::once(&number(amount, amount_span))
+                    .chain(terms[0].atoms.iter().filter(|atom| !atom.is_exact()))
+                    .cloned()
+                    .collect(),
+                span: merge_spans(terms.iter().map(|term| &term.span)),
+            };
+            Ok(match zero(&term) {
+                Some(zero) => Term {
+                    atoms: vec![zero.clone()],
+                    span: term.span.clone(),
+                },
+                None => Term {
+                    atoms: coefficient_atoms(&term)?,
+                    span: term.span,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, EvalError>>()?;
+    Ok((terms != expression.terms).then(|| Expression {
+        terms,
+        span: expression.span.clone(),
+    }))
+}
+
+fn distribute(
+    expression: &Expression,
+    budget: &Budget,
+) -> Result<Option<Expression>, RewriteError> {
+    if !expression
+        .terms
+        .iter()
+        .any(|term| term.atoms.iter().any(|atom| atom.kind.is_group()))
+    {
+        return Ok(None);
     }
+    let mut terms = expression.terms.clone();
+    let mut nodes = expression_size(expression);
+    let mut i = 0;
+    while i < terms.len() {
+        let term = &mut terms[i];
+        if let Some(group) = term
+            .atoms
+            .iter()
+            .enumerate()
+            .find_map(|(i, atom)| atom.kind.is_group().then_some(i))
+        {
+            let mut atoms = term.atoms.clone();
+            let span = term.span.clone();
+            let group = atoms.remove(group).kind.into_group().unwrap();
+            let mut distributed = group
+                .terms
+                .iter()
+                .map(|term| Term {
+                    atoms: term.atoms.iter().chain(atoms.iter()).cloned().collect(),
+                    span: span.clone(),
+                })
+                .collect::<Vec<_>>();
+            nodes += distributed.iter().map(term_size).sum::<usize>();
+            nodes -= term_size(&terms[i]);
+            budget.check(nodes)?;
+            terms.append(&mut distributed);
+            terms.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    Ok(Some(Expression {
+        terms,
+        span: expression.span.clone(),
+    }))
 }
 
-fn simplify_equation(equation: &Equation) -> Equation {
-    match equation {
-        Equation::Equality { left, right } => {
-            let (left, right) = {
-                let left = simplify_expression(left);
-                let right = simplify_expression(right);
-                (
-                    Expression {
-                        terms: left
-                            .terms
-                            .iter()
-                            .filter(|term| term.contains_variable())
-                            .cloned()
-                            .chain(
-                                right
-                                    .terms
-                                    .iter()
-                                    .filter(|term| term.contains_variable())
-                                    .map(|term| Term {
-                                        atoms: term
-                                            .atoms
-                                            .iter()
-                                            .cloned()
-                                            .chain(std::iter::once(Atom::Number(
-                                                BigRational::from_float(-1.0).unwrap(),
-                                            )))
-                                            .collect(),
-                                    }),
-                            )
-                            .collect(),
-                    },
-                    Expression {
-                        terms: right
-                            .terms
-                            .iter()
-                            .filter(|term| !term.contains_variable())
-                            .cloned()
-                            .chain(
-                                left.terms
-                                    .iter()
-                                    .filter(|term| !term.contains_variable())
-                                    .map(|term| Term {
-                                        atoms: term
-                                            .atoms
-                                            .iter()
-                                            .cloned()
-                                            .chain(std::iter::once(Atom::Number(
-                                                BigRational::from_float(-1.0).unwrap(),
-                                            )))
-                                            .collect(),
-                                    }),
-                            )
-                            .collect(),
-                    },
+pub(crate) fn builtin_rules() -> Vec<Rule> {
+    vec![
+        Rule::builtin("evaluate_constants", Builtin::Atom(evaluate_constants)),
+        Rule::builtin("divide_by_constant", Builtin::Atom(divide_by_constant)),
+        Rule::builtin("multiply_by_zero", Builtin::Term(multiply_by_zero)),
+        Rule::builtin("collect_coefficients", Builtin::Term(collect_coefficients)),
+        Rule::builtin(
+            "combine_like_terms",
+            Builtin::Expression(combine_like_terms),
+        ),
+        Rule::builtin("distribute", Builtin::Expression(distribute)),
+        Rule::parse("power_of_zero", "?a^0 -> 1").unwrap(),
+        Rule::parse("power_of_one", "?a^1 -> ?a").unwrap(),
+    ]
+}
+
+/// One step of `simplify_with_trace`, like `subtract 3 from both sides`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Step {
+    /// The name of what was done, like `subtract_from_both_sides`
+    pub rule: String,
+    pub description: String,
+    pub before: Equation,
+    pub after: Equation,
+}
+
+struct Trace {
+    steps: Option<Vec<Step>>,
+}
+
+impl Trace {
+    fn snapshot(
+        &self,
+        left: &Expression,
+        right: &Expression,
+        span: &Option<SourceSpan>,
+    ) -> Option<Equation> {
+        self.steps.as_ref().map(|_| Equation::Equality {
+            left: left.clone(),
+            right: right.clone(),
+            span: span.clone(),
+        })
+    }
+
+    fn record(
+        &mut self,
+        rule: &str,
+        description: impl FnOnce() -> String,
+        before: Option<Equation>,
+        (left, right): (&Expression, &Expression),
+    ) {
+        let (Some(steps), Some(before)) = (&mut self.steps, before) else {
+            return;
+        };
+        let after = Equation::Equality {
+            left: left.clone(),
+            right: right.clone(),
+            span: before.span().cloned(),
+        };
+        if after != before {
+            steps.push(Step {
+                rule: rule.to_string(),
+                description: description(),
+                before,
+                after,
+            });
+        }
+    }
+}
+
+fn describe(value: &impl DisplayWith) -> String {
+    value
+        .with_options(DisplayOptions {
+            number_format: NumberFormat::Fraction,
+        })
+        .to_string()
+}
+
+fn normalized_term(term: &Term, negate: bool) -> Term {
+    let coefficient = coefficient(term);
+    let coefficient = if negate { -coefficient } else { coefficient };
+    let other_atoms = term.atoms.iter().filter(|atom| !atom.kind.is_number());
+    Term::new(if coefficient == BigRational::from_float(1.0).unwrap() {
+        other_atoms.cloned().collect()
+    } else {
+        std::iter::once(number(coefficient, None))
+            .chain(other_atoms.cloned())
+            .collect()
+    })
+}
+
+fn simplify_sides(
+    left: &Expression,
+    right: &Expression,
+    span: &Option<SourceSpan>,
+    rules: &RuleSet,
+    budget: &Budget,
+    trace: &mut Trace,
+) -> Result<(Expression, Expression), RewriteError> {
+    let before = trace.snapshot(left, right, span);
+    let simplified = (
+        rules.rewrite_expression(left, budget)?,
+        rules.rewrite_expression(right, budget)?,
+    );
+    let like_terms = [left, right]
+        .into_iter()
+        .flat_map(|side| collect_like_terms(&side.terms))
+        .filter(|terms| terms.len() > 1 && terms[0].contains_variable())
+        .map(|terms| describe(&Expression::new(terms.into_iter().cloned().collect())))
+        .collect::<Vec<_>>();
+    if like_terms.is_empty() {
+        trace.record(
+            "simplify",
+            || "simplify both sides".to_string(),
+            before,
+            (&simplified.0, &simplified.1),
+        );
+    } else {
+        trace.record(
+            "combine_like_terms",
+            || format!("combine like terms {}", like_terms.join(" and ")),
+            before,
+            (&simplified.0, &simplified.1),
+        );
+    }
+    Ok(simplified)
+}
+
+fn record_move(
+    trace: &mut Trace,
+    term: &Term,
+    before: Option<Equation>,
+    sides: (&Expression, &Expression),
+) {
+    if coefficient(term) < BigRational::from_float(0.0).unwrap() {
+        trace.record(
+            "add_to_both_sides",
+            || {
+                format!(
+                    "add {} to both sides",
+                    describe(&normalized_term(term, true))
+                )
+            },
+            before,
+            sides,
+        );
+    } else {
+        trace.record(
+            "subtract_from_both_sides",
+            || {
+                format!(
+                    "subtract {} from both sides",
+                    describe(&normalized_term(term, false))
                 )
+            },
+            before,
+            sides,
+        );
+    }
+}
+
+fn move_terms(
+    mut left: Expression,
+    mut right: Expression,
+    span: &Option<SourceSpan>,
+    trace: &mut Trace,
+) -> (Expression, Expression) {
+    let negative_one = number(BigRational::from_float(-1.0).unwrap(), None);
+    let mut i = 0;
+    while i < right.terms.len() {
+        if right.terms[i].contains_variable() {
+            let before = trace.snapshot(&left, &right, span);
+            let term = right.terms.remove(i);
+            left.terms.append(&mut multiply_terms(
+                std::slice::from_ref(&term),
+                &negative_one,
+            ));
+            record_move(trace, &term, before, (&left, &right));
+        } else {
+            i += 1;
+        }
+    }
+    let mut i = 0;
+    while i < left.terms.len() {
+        if !left.terms[i].contains_variable() {
+            let before = trace.snapshot(&left, &right, span);
+            let term = left.terms.remove(i);
+            right.terms.append(&mut multiply_terms(
+                std::slice::from_ref(&term),
+                &negative_one,
+            ));
+            record_move(trace, &term, before, (&left, &right));
+        } else {
+            i += 1;
+        }
+    }
+    left.span = merge_spans(left.terms.iter().map(|term| &term.span));
+    right.span = merge_spans(right.terms.iter().map(|term| &term.span));
+    (left, right)
+}
+
+fn isolate(
+    left: Expression,
+    right: Expression,
+    span: &Option<SourceSpan>,
+    trace: &mut Trace,
+) -> Result<(Expression, Expression), EvalError> {
+    let before = trace.snapshot(&left, &right, span);
+    let (left, right) = if left.terms.len() == 1 {
+        let term = &left.terms[0];
+        if let Some(fraction) = term
+            .atoms
+            .iter()
+            .enumerate()
+            .find_map(|(i, atom)| atom.kind.as_fraction().map(|_| i))
+        {
+            let mut other_atoms = term.atoms.clone();
+            let fraction = other_atoms.remove(fraction);
+            let (numerator, denominator) = fraction.kind.into_fraction().unwrap();
+            let multiplier = denominator.clone();
+            let denominator = Atom {
+                span: denominator.span.clone(),
+                kind: AtomKind::Group(denominator),
             };
-            let equality = if left.terms.len() == 1 {
-                let term = &left.terms[0];
-                if let Some(fraction) = term
-                    .atoms
-                    .iter()
-                    .enumerate()
-                    .find_map(|(i, atom)| atom.as_fraction().map(|_| i))
-                {
-                    let mut other_atoms = term.atoms.clone();
-                    other_atoms.remove(fraction);
-                    Equation::Equality {
-                        left: Expression {
-                            terms: vec![Term {
-                                atoms: other_atoms
-                                    .into_iter()
-                                    .chain(std::iter::once(Atom::Group(
-                                        term.atoms[fraction].as_fraction().unwrap().0.clone(),
-                                    )))
-                                    .collect(),
+            let sides = (
+                Expression {
+                    terms: vec![Term {
+                        atoms: other_atoms
+                            .into_iter()
+                            .chain(std::iter::once(Atom {
+                                span: numerator.span.clone(),
+                                kind: AtomKind::Group(numerator),
+                            }))
+                            .collect(),
+                        span: term.span.clone(),
+                    }],
+                    span: left.span.clone(),
+                },
+                Expression {
+                    terms: multiply_terms(&right.terms, &denominator),
+                    span: right.span,
+                },
+            );
+            trace.record(
+                "multiply_both_sides",
+                || format!("multiply both sides by {}", describe(&multiplier)),
+                before,
+                (&sides.0, &sides.1),
+            );
+            sides
+        } else {
+            let exact_atoms = term.atoms.iter().filter(|atom| atom.is_exact());
+            let amount: BigRational = exact_atoms
+                .clone()
+                .map(eval_atom)
+                .product::<Result<_, _>>()?;
+            let amount_span = merge_spans(exact_atoms.map(|atom| &atom.span));
+            let other_atoms = term
+                .atoms
+                .iter()
+                .filter(|atom| !atom.is_exact())
+                .cloned()
+                .collect::<Vec<_>>();
+            let divisor = (amount != BigRational::from_float(1.0).unwrap()).then(|| amount.clone());
+            let sides = (
+                Expression {
+                    terms: vec![Term {
+                        atoms: other_atoms,
+                        span: term.span.clone(),
+                    }],
+                    span: left.span.clone(),
+                },
+                if amount != BigRational::from_float(1.0).unwrap() {
+                    let span = right.span.clone();
+                    Expression {
+                        terms: vec![Term {
+                            atoms: vec![Atom {
+                                kind: AtomKind::Fraction {
+                                    numerator: right,
+                                    denominator: Expression {
+                                        terms: vec![Term {
+                                            atoms: vec![number(amount, amount_span.clone())],
+                                            span: amount_span.clone(),
+                                        }],
+                                        span: amount_span,
+                                    },
+                                },
+                                span: span.clone(),
                             }],
-                        },
-                        right: Expression {
-                            terms: right
-                                .terms
-                                .iter()
-                                .map(|t| Term {
-                                    atoms: t
-                                        .atoms
-                                        .iter()
-                                        .cloned()
-                                        .chain(std::iter::once(Atom::Group(
-                                            term.atoms[fraction].as_fraction().unwrap().1.clone(),
-                                        )))
-                                        .collect(),
-                                })
-                                .collect(),
-                        },
+                            span: span.clone(),
+                        }],
+                        span,
                     }
                 } else {
-                    let amount: BigRational = term
-                        .atoms
-                        .iter()
-                        .filter_map(|atom| (!atom.contains_variable()).then(|| eval_atom(atom)))
-                        .product();
-                    let other_atoms = term
-                        .atoms
-                        .iter()
-                        .filter(|atom| atom.contains_variable())
-                        .cloned()
-                        .collect::<Vec<_>>();
-                    Equation::Equality {
-                        left: Expression {
-                            terms: vec![Term { atoms: other_atoms }],
-                        },
-                        right: if amount != BigRational::from_float(1.0).unwrap() {
-                            Expression {
-                                terms: vec![Term {
-                                    atoms: vec![Atom::Fraction {
-                                        numerator: right,
-                                        denominator: Expression {
-                                            terms: vec![Term {
-                                                atoms: vec![Atom::Number(amount)],
-                                            }],
-                                        },
-                                    }],
-                                }],
-                            }
-                        } else {
-                            right
-                        },
-                    }
-                }
-            } else {
-                Equation::Equality { left, right }
-            };
-            Equation::Equality {
-                left: simplify_expression(equality.as_equality().unwrap().0),
-                right: simplify_expression(equality.as_equality().unwrap().1),
+                    right
+                },
+            );
+            match divisor {
+                Some(divisor) => trace.record(
+                    "divide_both_sides",
+                    || format!("divide both sides by {}", describe(&number(divisor, None))),
+                    before,
+                    (&sides.0, &sides.1),
+                ),
+                None => trace.record(
+                    "simplify",
+                    || "simplify both sides".to_string(),
+                    before,
+                    (&sides.0, &sides.1),
+                ),
             }
+            sides
+        }
+    } else {
+        (left, right)
+    };
+    Ok((left, right))
+}
+
+fn simplify_equation(
+    equation: &Equation,
+    rules: &RuleSet,
+    budget: &Budget,
+    trace: &mut Trace,
+) -> Result<Equation, RewriteError> {
+    match equation {
+        Equation::Equality { left, right, span } => {
+            let (left, right) = simplify_sides(left, right, span, rules, budget, trace)?;
+            let (left, right) = move_terms(left, right, span, trace);
+            let (left, right) = isolate(left, right, span, trace)?;
+            let (left, right) = simplify_sides(&left, &right, span, rules, budget, trace)?;
+            Ok(Equation::Equality {
+                left,
+                right,
+                span: span.clone(),
+            })
+        }
+    }
+}
+
+/// How long `simplify_with_options` keeps going before it gives up
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SimplifyOptions {
+    /// Passes over the whole equation
+    pub max_iterations: usize,
+    /// Numbers, variables and operations in the equation
+    pub max_nodes: usize,
+    pub timeout: Duration,
+}
+
+impl Default for SimplifyOptions {
+    fn default() -> SimplifyOptions {
+        SimplifyOptions {
+            max_iterations: 1_000,
+            max_nodes: 10_000,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, IsVariant)]
+pub enum SimplifyLimit {
+    #[display(fmt = "{_0} iterations")]
+    Iterations(usize),
+    #[display(fmt = "{_0} nodes")]
+    Nodes(usize),
+    #[display(fmt = "{}s", "_0.as_secs_f64()")]
+    Timeout(Duration),
+    /// The rules gave a result that was already seen
+    #[display(fmt = "going around in circles")]
+    Cycle,
+}
+
+/// `T` is what was being simplified, an `Equation` or an `Expression`
+#[derive(Clone, PartialEq, Debug, Display, IsVariant, EnumAsInner)]
+#[display(bound = "T: Display")]
+#[allow(clippy::large_enum_variant)]
+pub enum SimplifyError<T> {
+    EvalError(EvalError),
+    /// `best` is the smallest result before the limit was hit
+    #[display(fmt = "Gave up simplifying after {limit}, the simplest result so far is '{best}'")]
+    LimitExceeded {
+        limit: SimplifyLimit,
+        best: T,
+    },
+}
+
+impl<T> From<EvalError> for SimplifyError<T> {
+    fn from(error: EvalError) -> SimplifyError<T> {
+        SimplifyError::EvalError(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Budget {
+    start: Instant,
+    max_nodes: usize,
+    timeout: Duration,
+}
+
+impl Budget {
+    fn new(options: &SimplifyOptions) -> Budget {
+        Budget {
+            start: Instant::now(),
+            max_nodes: options.max_nodes,
+            timeout: options.timeout,
+        }
+    }
+
+    fn timed_out(&self) -> bool {
+        self.start.elapsed() >= self.timeout
+    }
+
+    pub(crate) fn check(&self, nodes: usize) -> Result<(), SimplifyLimit> {
+        if nodes > self.max_nodes {
+            Err(SimplifyLimit::Nodes(self.max_nodes))
+        } else if self.timed_out() {
+            Err(SimplifyLimit::Timeout(self.timeout))
+        } else {
+            Ok(())
         }
     }
 }
 
-pub fn simplify(equation: &Equation) -> Equation {
-    let mut result = equation.clone();
+#[derive(Clone, PartialEq, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum RewriteError {
+    EvalError(EvalError),
+    LimitExceeded(SimplifyLimit),
+}
+
+impl From<EvalError> for RewriteError {
+    fn from(error: EvalError) -> RewriteError {
+        RewriteError::EvalError(error)
+    }
+}
+
+impl From<SimplifyLimit> for RewriteError {
+    fn from(limit: SimplifyLimit) -> RewriteError {
+        RewriteError::LimitExceeded(limit)
+    }
+}
+
+fn atom_size(atom: &Atom) -> usize {
+    1 + match &atom.kind {
+        AtomKind::Number(_) | AtomKind::Variable(_) => 0,
+        AtomKind::Group(expression) => expression_size(expression),
+        AtomKind::Fraction {
+            numerator,
+            denominator,
+        } => expression_size(numerator) + expression_size(denominator),
+        AtomKind::Power { base, exponent } => atom_size(base) + atom_size(exponent),
+        AtomKind::Call { args, .. } => args.iter().map(expression_size).sum(),
+    }
+}
+
+fn term_size(term: &Term) -> usize {
+    term.atoms.iter().map(atom_size).sum()
+}
+
+fn expression_size(expression: &Expression) -> usize {
+    expression.terms.iter().map(term_size).sum()
+}
+
+fn equation_size(equation: &Equation) -> usize {
+    match equation {
+        Equation::Equality { left, right, .. } => expression_size(left) + expression_size(right),
+    }
+}
+
+fn fixpoint<T: Clone + Eq + Hash>(
+    value: &T,
+    options: &SimplifyOptions,
+    size: impl Fn(&T) -> usize,
+    mut step: impl FnMut(&T, &Budget) -> Result<T, RewriteError>,
+) -> Result<T, SimplifyError<T>> {
+    let budget = Budget::new(options);
+    // hashing ignores spans like equality does, so a state with other spans is still seen
+    let mut seen = HashSet::from([value.clone()]);
+    let mut result = value.clone();
+    let mut best = value.clone();
+    let mut iterations = 0;
     loop {
-        let next = simplify_equation(&result);
+        let limit = if iterations == options.max_iterations {
+            Some(SimplifyLimit::Iterations(options.max_iterations))
+        } else if budget.timed_out() {
+            Some(SimplifyLimit::Timeout(options.timeout))
+        } else {
+            None
+        };
+        if let Some(limit) = limit {
+            return Err(SimplifyError::LimitExceeded { limit, best });
+        }
+        iterations += 1;
+        let next = match step(&result, &budget) {
+            Ok(next) => next,
+            Err(RewriteError::EvalError(error)) => return Err(error.into()),
+            Err(RewriteError::LimitExceeded(limit)) => {
+                return Err(SimplifyError::LimitExceeded { limit, best })
+            }
+        };
         if next == result {
-            return result;
+            return Ok(result);
+        }
+        if size(&next) > options.max_nodes {
+            return Err(SimplifyError::LimitExceeded {
+                limit: SimplifyLimit::Nodes(options.max_nodes),
+                best,
+            });
+        }
+        if size(&next) <= size(&best) {
+            best = next.clone();
+        }
+        if !seen.insert(next.clone()) {
+            return Err(SimplifyError::LimitExceeded {
+                limit: SimplifyLimit::Cycle,
+                best,
+            });
         }
         result = next;
     }
 }
+
+fn simplify_traced(
+    equation: &Equation,
+    rules: &RuleSet,
+    options: &SimplifyOptions,
+    trace: &mut Trace,
+) -> Result<Equation, SimplifyError<Equation>> {
+    fixpoint(equation, options, equation_size, |equation, budget| {
+        simplify_equation(equation, rules, budget, trace)
+    })
+}
+
+/// Simplifies with the builtin rules and the default `SimplifyOptions`
+pub fn simplify(equation: &Equation) -> Result<Equation, SimplifyError<Equation>> {
+    simplify_with(equation, builtin_rule_set())
+}
+
+/// Simplifies with other rules than the builtin ones
+pub fn simplify_with(
+    equation: &Equation,
+    rules: &RuleSet,
+) -> Result<Equation, SimplifyError<Equation>> {
+    simplify_with_options(equation, rules, &SimplifyOptions::default())
+}
+
+pub fn simplify_with_options(
+    equation: &Equation,
+    rules: &RuleSet,
+    options: &SimplifyOptions,
+) -> Result<Equation, SimplifyError<Equation>> {
+    simplify_traced(equation, rules, options, &mut Trace { steps: None })
+}
+
+/// Simplifies like `simplify`, and also returns the steps that were taken
+pub fn simplify_with_trace(
+    equation: &Equation,
+) -> Result<(Equation, Vec<Step>), SimplifyError<Equation>> {
+    let mut trace = Trace {
+        steps: Some(vec![]),
+    };
+    let result = simplify_traced(
+        equation,
+        builtin_rule_set(),
+        &SimplifyOptions::default(),
+        &mut trace,
+    )?;
+    Ok((result, trace.steps.unwrap_or_default()))
+}
+
+/// Simplifies an expression like `simplify` does with both sides of an equation
+pub fn simplify_expression_fully(
+    expression: &Expression,
+) -> Result<Expression, SimplifyError<Expression>> {
+    simplify_expression_fully_with(expression, builtin_rule_set())
+}
+
+pub fn simplify_expression_fully_with(
+    expression: &Expression,
+    rules: &RuleSet,
+) -> Result<Expression, SimplifyError<Expression>> {
+    simplify_expression_fully_with_options(expression, rules, &SimplifyOptions::default())
+}
+
+pub fn simplify_expression_fully_with_options(
+    expression: &Expression,
+    rules: &RuleSet,
+    options: &SimplifyOptions,
+) -> Result<Expression, SimplifyError<Expression>> {
+    fixpoint(
+        expression,
+        options,
+        expression_size,
+        |expression, budget| rules.rewrite_expression(expression, budget),
+    )
+}
@@ -0,0 +1,331 @@
+use std::fmt::Display;
+
+use derive_more::{Display, IsVariant};
+use enum_as_inner::EnumAsInner;
+use num_rational::BigRational;
+use num_traits::Zero;
+
+use crate::{
+    eval_atom, eval_expression, eval_term, merge_spans, simplify_expression_fully, span_prefix,
+    Atom, AtomKind, DisplayOptions, DisplayWith, Equation, EvalError, Expression, SimplifyError,
+    SourceSpan, Term, MAX_DEGREE,
+};
+
+#[derive(Clone, PartialEq, Debug, EnumAsInner)]
+pub enum Solution {
+    Unique(Expression),
+    NoSolution,
+    AllValues,
+    /// The variable cancels out, so every value is a solution when the condition on the other variables holds and none is otherwise
+    Conditional(Equation),
+}
+
+impl DisplayWith for Solution {
+    fn fmt_with(&self, f: &mut std::fmt::Formatter, options: &DisplayOptions) -> std::fmt::Result {
+        match self {
+            Solution::Unique(value) => value.fmt_with(f, options),
+            Solution::NoSolution => write!(f, "no solution"),
+            Solution::AllValues => write!(f, "all values"),
+            Solution::Conditional(condition) => {
+                write!(f, "all values if ")?;
+                condition.fmt_with(f, options)?;
+                write!(f, ", otherwise no solution")
+            }
+        }
+    }
+}
+
+impl Display for Solution {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.fmt_with(f, &DisplayOptions::default())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Display, IsVariant, EnumAsInner)]
+pub enum SolveError {
+    EvalError(EvalError),
+    #[display(
+        fmt = "{}The equation is not linear in '{variable}'",
+        "span_prefix(span)"
+    )]
+    NonLinear {
+        variable: String,
+        span: Option<SourceSpan>,
+    },
+    #[display(
+        fmt = "{}The equation is not a polynomial with rational coefficients in '{variable}'",
+        "span_prefix(span)"
+    )]
+    NotPolynomial {
+        variable: String,
+        span: Option<SourceSpan>,
+    },
+    #[display(
+        fmt = "{}'{coefficient}' is not a rational coefficient",
+        "span_prefix(span)"
+    )]
+    IrrationalCoefficient {
+        coefficient: Atom,
+        span: Option<SourceSpan>,
+    },
+    #[display(
+        fmt = "{}'{variable}' does not appear in the equation",
+        "span_prefix(span)"
+    )]
+    VariableNotFound {
+        variable: String,
+        span: Option<SourceSpan>,
+    },
+    #[display(
+        fmt = "{}The degree of the polynomial in '{variable}' is higher than {}",
+        "span_prefix(span)",
+        "MAX_DEGREE"
+    )]
+    DegreeTooHigh {
+        variable: String,
+        span: Option<SourceSpan>,
+    },
+    #[display(
+        fmt = "{}The coefficients of the polynomial in '{variable}' are too large to find its roots",
+        "span_prefix(span)"
+    )]
+    CoefficientTooLarge {
+        variable: String,
+        span: Option<SourceSpan>,
+    },
+    SimplifyError(SimplifyError<Expression>),
+}
+
+impl From<EvalError> for SolveError {
+    fn from(error: EvalError) -> SolveError {
+        SolveError::EvalError(error)
+    }
+}
+
+impl From<SimplifyError<Expression>> for SolveError {
+    fn from(error: SimplifyError<Expression>) -> SolveError {
+        match error {
+            SimplifyError::EvalError(error) => SolveError::EvalError(error),
+            error => SolveError::SimplifyError(error),
+        }
+    }
+}
+
+/// `coefficient * variable + constant`, where neither part contains the variable
+struct Linear {
+    coefficient: Expression,
+    constant: Expression,
+}
+
+fn number(value: BigRational) -> Expression {
+    Expression::new(vec![Term::new(vec![Atom::new(AtomKind::Number(value))])])
+}
+
+fn multiply_expressions(a: &Expression, b: &Expression) -> Expression {
+    Expression {
+        terms: a
+            .terms
+            .iter()
+            .flat_map(|a| {
+                b.terms.iter().map(|b| Term {
+                    atoms: a.atoms.iter().chain(b.atoms.iter()).cloned().collect(),
+                    span: merge_spans([&a.span, &b.span]),
+                })
+            })
+            .collect(),
+        span: merge_spans([&a.span, &b.span]),
+    }
+}
+
+fn add_expressions(a: &Expression, b: &Expression) -> Expression {
+    Expression {
+        terms: a.terms.iter().chain(b.terms.iter()).cloned().collect(),
+        span: merge_spans([&a.span, &b.span]),
+    }
+}
+
+fn divide_expression(expression: &Expression, denominator: &Expression) -> Expression {
+    let span = merge_spans([&expression.span, &denominator.span]);
+    Expression {
+        terms: if expression.terms.is_empty() {
+            vec![]
+        } else {
+            vec![Term {
+                atoms: vec![Atom {
+                    kind: AtomKind::Fraction {
+                        numerator: expression.clone(),
+                        denominator: denominator.clone(),
+                    },
+                    span: span.clone(),
+                }],
+                span: span.clone(),
+            }]
+        },
+        span,
+    }
+}
+
+fn multiply_linear(
+    a: Linear,
+    b: Linear,
+    variable: &str,
+    span: &Option<SourceSpan>,
+) -> Result<Linear, SolveError> {
+    if !a.coefficient.terms.is_empty() && !b.coefficient.terms.is_empty() {
+        return Err(SolveError::NonLinear {
+            variable: variable.to_string(),
+            span: span.clone(),
+        });
+    }
+    Ok(Linear {
+        coefficient: add_expressions(
+            &multiply_expressions(&a.coefficient, &b.constant),
+            &multiply_expressions(&a.constant, &b.coefficient),
+        ),
+        constant: multiply_expressions(&a.constant, &b.constant),
+    })
+}
+
+fn linear_atom(atom: &Atom, variable: &str) -> Result<Linear, SolveError> {
+    if !atom.contains_variable_named(variable) {
+        return Ok(Linear {
+            coefficient: Expression::new(vec![]),
+            constant: Expression {
+                terms: vec![Term {
+                    atoms: vec![atom.clone()],
+                    span: atom.span.clone(),
+                }],
+                span: atom.span.clone(),
+            },
+        });
+    }
+    let non_linear = || SolveError::NonLinear {
+        variable: variable.to_string(),
+        span: atom.span.clone(),
+    };
+    match &atom.kind {
+        AtomKind::Number(_) => unreachable!(),
+        AtomKind::Variable(_) => Ok(Linear {
+            coefficient: number(BigRational::from_integer(1.into())),
+            constant: Expression::new(vec![]),
+        }),
+        AtomKind::Group(expression) => linear_expression(expression, variable),
+        AtomKind::Fraction {
+            numerator,
+            denominator,
+        } => {
+            if denominator.contains_variable_named(variable) {
+                return Err(non_linear());
+            }
+            let numerator = linear_expression(numerator, variable)?;
+            Ok(Linear {
+                coefficient: divide_expression(&numerator.coefficient, denominator),
+                constant: divide_expression(&numerator.constant, denominator),
+            })
+        }
+        AtomKind::Power { base, exponent } => {
+            if !exponent.is_exact() {
+                return Err(non_linear());
+            }
+            let exponent = eval_atom(exponent)?;
+            if exponent.is_zero() {
+                Ok(Linear {
+                    coefficient: Expression::new(vec![]),
+                    constant: number(BigRational::from_integer(1.into())),
+                })
+            } else if exponent == BigRational::from_integer(1.into()) {
+                linear_atom(base, variable)
+            } else {
+                Err(non_linear())
+            }
+        }
+        AtomKind::Call { .. } => Err(non_linear()),
+    }
+}
+
+fn linear_term(term: &Term, variable: &str) -> Result<Linear, SolveError> {
+    term.atoms.iter().try_fold(
+        Linear {
+            coefficient: Expression::new(vec![]),
+            constant: number(BigRational::from_integer(1.into())),
+        },
+        |linear, atom| multiply_linear(linear, linear_atom(atom, variable)?, variable, &term.span),
+    )
+}
+
+fn linear_expression(expression: &Expression, variable: &str) -> Result<Linear, SolveError> {
+    expression.terms.iter().try_fold(
+        Linear {
+            coefficient: Expression::new(vec![]),
+            constant: Expression::new(vec![]),
+        },
+        |linear, term| {
+            let term = linear_term(term, variable)?;
+            Ok(Linear {
+                coefficient: add_expressions(&linear.coefficient, &term.coefficient),
+                constant: add_expressions(&linear.constant, &term.constant),
+            })
+        },
+    )
+}
+
+fn is_zero(expression: &Expression) -> bool {
+    expression.is_exact() && eval_expression(expression).is_ok_and(|value| value.is_zero())
+}
+
+/// Isolates `variable` in a linear equation, any other variables are kept as symbolic parameters
+pub fn solve_for(equation: &Equation, variable: &str) -> Result<Solution, SolveError> {
+    match equation {
+        Equation::Equality { left, right, span } => {
+            // solve `left - right = 0`, which is `coefficient * variable + constant = 0`
+            let linear = linear_expression(
+                &add_expressions(
+                    left,
+                    &multiply_expressions(right, &number(BigRational::from_integer((-1).into()))),
+                ),
+                variable,
+            )?;
+            let coefficient = simplify_expression_fully(&linear.coefficient)?;
+            let constant = simplify_expression_fully(&linear.constant)?;
+            if is_zero(&coefficient) {
+                return if is_zero(&constant) {
+                    Ok(Solution::AllValues)
+                } else if constant.is_exact() {
+                    Ok(Solution::NoSolution)
+                } else if equation.contains_variable_named(variable) {
+                    // whether there is a solution depends on the other variables
+                    Ok(Solution::Conditional(Equation::Equality {
+                        // leave out the `0` terms combining like terms keeps, so the condition reads `y = 0`
+                        left: Expression {
+                            terms: constant
+                                .terms
+                                .into_iter()
+                                .filter(|term| {
+                                    !(term.is_exact()
+                                        && eval_term(term).is_ok_and(|value| value.is_zero()))
+                                })
+                                .collect(),
+                            span: constant.span,
+                        },
+                        right: number(BigRational::zero()),
+                        span: span.clone(),
+                    }))
+                } else {
+                    Err(SolveError::VariableNotFound {
+                        variable: variable.to_string(),
+                        span: span.clone(),
+                    })
+                };
+            }
+            let negated =
+                multiply_expressions(&constant, &number(BigRational::from_integer((-1).into())));
+            Ok(Solution::Unique(simplify_expression_fully(
+                &if coefficient.is_exact() {
+                    multiply_expressions(&negated, &number(eval_expression(&coefficient)?.recip()))
+                } else {
+                    divide_expression(&negated, &coefficient)
+                },
+            )?))
+        }
+    }
+}
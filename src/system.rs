@@ -0,0 +1,372 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use enum_as_inner::EnumAsInner;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Zero};
+
+use crate::{
+    eval_atom, format_number, Atom, AtomKind, DisplayOptions, DisplayWith, Equation, EvalError,
+    Expression, SolveError, SourceSpan, System, Term,
+};
+
+#[derive(Clone, PartialEq, Debug, EnumAsInner)]
+pub enum SystemSolution {
+    /// The value of every variable, ordered by name, empty for a true system without variables like `1 = 1`
+    Unique(Vec<(String, BigRational)>),
+    Inconsistent,
+    /// The variables that can take any value, and every other variable in terms of them
+    Parametric {
+        free: Vec<String>,
+        dependent: Vec<(String, Expression)>,
+    },
+}
+
+impl DisplayWith for SystemSolution {
+    fn fmt_with(&self, f: &mut std::fmt::Formatter, options: &DisplayOptions) -> std::fmt::Result {
+        match self {
+            SystemSolution::Unique(values) if values.is_empty() => write!(f, "always true"),
+            SystemSolution::Unique(values) => {
+                for (i, (name, value)) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(
+                        f,
+                        "{} = {}",
+                        name,
+                        format_number(value, options.number_format)
+                    )?;
+                }
+                Ok(())
+            }
+            SystemSolution::Inconsistent => write!(f, "no solution"),
+            SystemSolution::Parametric { free, dependent } => {
+                for (i, (name, value)) in dependent.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{} = ", name)?;
+                    value.fmt_with(f, options)?;
+                }
+                if !dependent.is_empty() {
+                    write!(f, " ")?;
+                }
+                write!(f, "for any {}", free.join(", "))
+            }
+        }
+    }
+}
+
+impl Display for SystemSolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.fmt_with(f, &DisplayOptions::default())
+    }
+}
+
+/// A linear combination of variables plus a constant
+#[derive(Clone, Default)]
+struct Linear {
+    coefficients: BTreeMap<String, BigRational>,
+    constant: BigRational,
+}
+
+impl Linear {
+    fn constant(value: BigRational) -> Linear {
+        Linear {
+            coefficients: BTreeMap::new(),
+            constant: value,
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        self.coefficients.values().all(Zero::is_zero)
+    }
+
+    fn add(mut self, other: Linear) -> Linear {
+        for (name, coefficient) in other.coefficients {
+            *self
+                .coefficients
+                .entry(name)
+                .or_insert_with(BigRational::zero) += coefficient;
+        }
+        self.constant += other.constant;
+        self
+    }
+
+    fn scale(mut self, factor: &BigRational) -> Linear {
+        for coefficient in self.coefficients.values_mut() {
+            *coefficient *= factor;
+        }
+        self.constant *= factor;
+        self
+    }
+
+    fn multiply(self, other: Linear, span: &Option<SourceSpan>) -> Result<Linear, SolveError> {
+        if self.is_constant() {
+            Ok(other.scale(&self.constant))
+        } else if other.is_constant() {
+            Ok(self.scale(&other.constant))
+        } else {
+            Err(SolveError::NonLinear {
+                variable: other.coefficients.into_keys().next().unwrap(),
+                span: span.clone(),
+            })
+        }
+    }
+}
+
+fn linear_atom(atom: &Atom) -> Result<Linear, SolveError> {
+    if atom.is_exact() {
+        return Ok(Linear::constant(eval_atom(atom)?));
+    }
+    match &atom.kind {
+        AtomKind::Number(_) => unreachable!(),
+        AtomKind::Variable(name) => Ok(Linear {
+            coefficients: BTreeMap::from([(name.clone(), BigRational::one())]),
+            constant: BigRational::zero(),
+        }),
+        AtomKind::Group(expression) => linear_expression(expression),
+        AtomKind::Fraction {
+            numerator,
+            denominator,
+        } => {
+            let denominator_span = &denominator.span;
+            let denominator = linear_expression(denominator)?;
+            if !denominator.is_constant() {
+                return Err(SolveError::NonLinear {
+                    variable: denominator.coefficients.into_keys().next().unwrap(),
+                    span: atom.span.clone(),
+                });
+            }
+            if denominator.constant.is_zero() {
+                return Err(EvalError::DivisionByZero {
+                    atom: atom.clone(),
+                    span: denominator_span.clone(),
+                }
+                .into());
+            }
+            Ok(linear_expression(numerator)?.scale(&denominator.constant.recip()))
+        }
+        AtomKind::Power { base, exponent } => {
+            let base = linear_atom(base)?;
+            if exponent.is_exact() && eval_atom(exponent)?.is_one() {
+                Ok(base)
+            } else if let Some(variable) = base.coefficients.into_keys().next() {
+                Err(SolveError::NonLinear {
+                    variable,
+                    span: atom.span.clone(),
+                })
+            } else {
+                Err(SolveError::IrrationalCoefficient {
+                    coefficient: atom.clone(),
+                    span: atom.span.clone(),
+                })
+            }
+        }
+        AtomKind::Call { args, .. } => {
+            let mut variables = BTreeSet::new();
+            for argument in args {
+                variables.extend(linear_expression(argument)?.coefficients.into_keys());
+            }
+            if let Some(variable) = variables.into_iter().next() {
+                Err(SolveError::NonLinear {
+                    variable,
+                    span: atom.span.clone(),
+                })
+            } else {
+                Err(SolveError::IrrationalCoefficient {
+                    coefficient: atom.clone(),
+                    span: atom.span.clone(),
+                })
+            }
+        }
+    }
+}
+
+fn linear_term(term: &Term) -> Result<Linear, SolveError> {
+    term.atoms
+        .iter()
+        .try_fold(Linear::constant(BigRational::one()), |linear, atom| {
+            linear.multiply(linear_atom(atom)?, &term.span)
+        })
+}
+
+fn linear_expression(expression: &Expression) -> Result<Linear, SolveError> {
+    expression
+        .terms
+        .iter()
+        .try_fold(Linear::default(), |linear, term| {
+            Ok(linear.add(linear_term(term)?))
+        })
+}
+
+/// Brings the integer matrix into row echelon form with Bareiss' fraction-free elimination,
+/// and returns the pivot column of every non zero row
+fn bareiss(matrix: &mut [Vec<BigInt>], columns: usize) -> Vec<usize> {
+    let mut pivots = vec![];
+    let mut previous = BigInt::one();
+    let mut row = 0;
+    for column in 0..columns {
+        let Some(pivot) = (row..matrix.len()).find(|&i| !matrix[i][column].is_zero()) else {
+            continue;
+        };
+        matrix.swap(row, pivot);
+        for i in row + 1..matrix.len() {
+            for j in column + 1..matrix[i].len() {
+                // the division is always exact, which keeps the entries small
+                matrix[i][j] = (&matrix[row][column] * &matrix[i][j]
+                    - &matrix[i][column] * &matrix[row][j])
+                    / &previous;
+            }
+            matrix[i][column] = BigInt::zero();
+        }
+        previous = matrix[row][column].clone();
+        pivots.push(column);
+        row += 1;
+    }
+    pivots
+}
+
+fn linear_to_expression(linear: &Linear) -> Expression {
+    let mut terms = linear
+        .coefficients
+        .iter()
+        .filter(|(_, coefficient)| !coefficient.is_zero())
+        .map(|(name, coefficient)| {
+            Term::new(if coefficient.is_one() {
+                vec![Atom::new(AtomKind::Variable(name.clone()))]
+            } else {
+                vec![
+                    Atom::new(AtomKind::Number(coefficient.clone())),
+                    Atom::new(AtomKind::Variable(name.clone())),
+                ]
+            })
+        })
+        .collect::<Vec<_>>();
+    if !linear.constant.is_zero() || terms.is_empty() {
+        terms.insert(
+            0,
+            Term::new(vec![Atom::new(AtomKind::Number(linear.constant.clone()))]),
+        );
+    }
+    Expression::new(terms)
+}
+
+/// Solves a system of linear equations with rational coefficients exactly
+pub fn solve_system(system: &System) -> Result<SystemSolution, SolveError> {
+    let rows =
+        system
+            .equations
+            .iter()
+            .map(|equation| match equation {
+                Equation::Equality { left, right, .. } => Ok(linear_expression(left)?
+                    .add(linear_expression(right)?.scale(&-BigRational::one()))),
+            })
+            .collect::<Result<Vec<_>, SolveError>>()?;
+    let variables = rows
+        .iter()
+        .flat_map(|row| row.coefficients.keys().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    // each row is `coefficients... | -constant`, scaled to integers
+    let mut matrix = rows
+        .iter()
+        .map(|row| {
+            let values = variables
+                .iter()
+                .map(|name| {
+                    row.coefficients
+                        .get(name)
+                        .cloned()
+                        .unwrap_or_else(BigRational::zero)
+                })
+                .chain(std::iter::once(-row.constant.clone()))
+                .collect::<Vec<_>>();
+            let scale = values
+                .iter()
+                .fold(BigInt::one(), |scale, value| scale.lcm(value.denom()));
+            values
+                .into_iter()
+                .map(|value| (value * &scale).to_integer())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let pivots = bareiss(&mut matrix, variables.len());
+
+    if matrix[pivots.len()..]
+        .iter()
+        .any(|row| !row[variables.len()].is_zero())
+    {
+        return Ok(SystemSolution::Inconsistent);
+    }
+
+    // back substitution, with the free variables standing for themselves
+    let mut values = variables
+        .iter()
+        .enumerate()
+        .filter(|(column, _)| !pivots.contains(column))
+        .map(|(column, name)| {
+            (
+                column,
+                Linear {
+                    coefficients: BTreeMap::from([(name.clone(), BigRational::one())]),
+                    constant: BigRational::zero(),
+                },
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+    for (row, &column) in pivots.iter().enumerate().rev() {
+        let value = (column + 1..variables.len())
+            .filter(|&j| !matrix[row][j].is_zero())
+            .fold(
+                Linear::constant(BigRational::from_integer(
+                    matrix[row][variables.len()].clone(),
+                )),
+                |value, j| {
+                    value.add(
+                        values[&j]
+                            .clone()
+                            .scale(&-BigRational::from_integer(matrix[row][j].clone())),
+                    )
+                },
+            )
+            .scale(&BigRational::from_integer(matrix[row][column].clone()).recip());
+        values.insert(column, value);
+    }
+
+    let free = variables
+        .iter()
+        .enumerate()
+        .filter(|(column, _)| !pivots.contains(column))
+        .map(|(_, name)| name.clone())
+        .collect::<Vec<_>>();
+    Ok(if free.is_empty() {
+        SystemSolution::Unique(
+            variables
+                .iter()
+                .enumerate()
+                .map(|(column, name)| (name.clone(), values[&column].constant.clone()))
+                .collect(),
+        )
+    } else {
+        SystemSolution::Parametric {
+            free,
+            dependent: pivots
+                .iter()
+                .map(|&column| {
+                    (
+                        variables[column].clone(),
+                        linear_to_expression(&values[&column]),
+                    )
+                })
+                .collect(),
+        }
+    })
+}
@@ -5,8 +5,14 @@ use num_rational::BigRational;
 use crate::SourceSpan;
 
 #[derive(Clone, PartialEq, Debug, Display, EnumAsInner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum TokenKind {
     #[display(fmt = "EOF")]
+    #[cfg_attr(feature = "serde", serde(rename = "eof"))]
     EOF,
     #[display(fmt = "name")]
     Name,
@@ -24,18 +30,31 @@ pub enum TokenKind {
     Multiply,
     #[display(fmt = "/")]
     Divide,
+    #[display(fmt = "^")]
+    Power,
     #[display(fmt = "=")]
     Equal,
+    #[display(fmt = ",")]
+    Comma,
+    #[display(fmt = ":=")]
+    Define,
 }
 
 #[derive(Clone, PartialEq, Debug, EnumAsInner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value", rename_all = "snake_case")
+)]
 pub enum TokenData {
     None,
     String(String),
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::rational"))]
     Number(BigRational),
 }
 
 #[derive(Clone, PartialEq, Debug, Display)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[display(fmt = "{kind}")]
 pub struct Token {
     pub kind: TokenKind,
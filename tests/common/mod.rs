@@ -0,0 +1,26 @@
+//! Helpers shared by the integration tests, each test uses only some of them
+#![allow(dead_code)]
+
+use math::*;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+pub fn expression(source: &str) -> Expression {
+    parse_expression(&mut Lexer::new("test", source)).unwrap()
+}
+
+pub fn equation(source: &str) -> Equation {
+    parse_equation(&mut Lexer::new("test", source)).unwrap()
+}
+
+pub fn system(source: &str) -> System {
+    parse_system(&mut Lexer::new("test", source)).unwrap()
+}
+
+pub fn rational(numerator: i64, denominator: i64) -> BigRational {
+    BigRational::new(BigInt::from(numerator), BigInt::from(denominator))
+}
+
+pub fn integer(value: i64) -> BigRational {
+    BigRational::from_integer(BigInt::from(value))
+}
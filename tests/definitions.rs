@@ -0,0 +1,72 @@
+#![allow(clippy::result_large_err)]
+
+mod common;
+
+use common::*;
+use math::*;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+fn define(definitions: &mut Definitions, source: &str) -> Result<(), DefinitionError> {
+    let mut lexer = Lexer::new("test", source);
+    definitions.define(parse_definition_with(&mut lexer, definitions).unwrap())
+}
+
+fn eval(definitions: &Definitions, source: &str) -> BigRational {
+    let mut lexer = Lexer::new("test", &format!("{source} = 0"));
+    let equation = parse_equation_with(&mut lexer, definitions).unwrap();
+    let equation = definitions.expand_equation(&equation).unwrap();
+    let mut environment = Environment::new();
+    environment.bind("y", BigRational::from_integer(BigInt::from(10)));
+    eval_expression_with(equation.as_equality().unwrap().0, &environment).unwrap()
+}
+
+#[test]
+fn expands_constants_and_functions() {
+    let mut definitions = Definitions::new();
+    define(&mut definitions, "f(x) := 3x^2 + 1").unwrap();
+    define(&mut definitions, "g := 2").unwrap();
+    define(&mut definitions, "h(x) := f(x) + g").unwrap();
+    assert_eq!(eval(&definitions, "f(2)"), integer(13));
+    assert_eq!(eval(&definitions, "h(1) + g"), integer(8));
+
+    // definitions are looked up when they are used
+    define(&mut definitions, "g := 5").unwrap();
+    assert_eq!(eval(&definitions, "h(1)"), integer(9));
+}
+
+#[test]
+fn substitution_avoids_capture() {
+    let mut definitions = Definitions::new();
+    define(&mut definitions, "f(x, y) := x - y").unwrap();
+    assert_eq!(eval(&definitions, "f(y, 1)"), integer(9));
+    assert_eq!(eval(&definitions, "f(1, y)"), integer(-9));
+
+    // parameters hide constants with the same name
+    define(&mut definitions, "x := 100").unwrap();
+    define(&mut definitions, "k(x) := x + 1").unwrap();
+    assert_eq!(eval(&definitions, "k(1)"), integer(2));
+    assert_eq!(eval(&definitions, "k(x)"), integer(101));
+}
+
+#[test]
+fn rejects_bad_definitions() {
+    let mut definitions = Definitions::new();
+    assert!(define(&mut definitions, "f(x) := f(x) + 1")
+        .unwrap_err()
+        .is_recursive_definition());
+    define(&mut definitions, "a := 1").unwrap();
+    define(&mut definitions, "b := a + 1").unwrap();
+    assert!(define(&mut definitions, "a := b")
+        .unwrap_err()
+        .is_recursive_definition());
+    assert_eq!(eval(&definitions, "b"), integer(2));
+    assert!(define(&mut definitions, "sqrt(x) := x")
+        .unwrap_err()
+        .is_builtin_redefinition());
+
+    let mut lexer = Lexer::new("test", "m(x, x) := x");
+    assert!(parse_definition(&mut lexer)
+        .unwrap_err()
+        .is_duplicate_parameter());
+}
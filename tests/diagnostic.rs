@@ -0,0 +1,233 @@
+#![allow(clippy::result_large_err)]
+
+use math::*;
+
+fn equation_error(source: &str) -> ParsingError {
+    parse_equation(&mut Lexer::new("test", source)).unwrap_err()
+}
+
+fn latex_error(source: &str) -> ParsingError {
+    parse_equation(&mut LatexLexer::new("test", source)).unwrap_err()
+}
+
+fn definition_error(source: &str) -> ParsingError {
+    parse_definition(&mut Lexer::new("test", source)).unwrap_err()
+}
+
+fn system_error(source: &str) -> ParsingError {
+    parse_system(&mut Lexer::new("test", source)).unwrap_err()
+}
+
+/// The human and the machine rendering, one after the other
+fn rendered(error: ParsingError, source: &str) -> String {
+    let diagnostic = Diagnostic::from(&error);
+    diagnostic.render(source, DiagnosticStyle::Human)
+        + &diagnostic.render(source, DiagnosticStyle::Machine)
+}
+
+#[test]
+fn lexer_errors() {
+    assert_eq!(
+        rendered(equation_error("2x $ 1 = 0"), "2x $ 1 = 0"),
+        "\
+error: Unexpected character '$'
+ --> test:1:4
+  |
+1 | 2x $ 1 = 0
+  |    ^
+test:1:4-1:5: error: Unexpected character '$'
+"
+    );
+    assert_eq!(
+        rendered(latex_error("\\foo x = 1"), "\\foo x = 1"),
+        "\
+error: Unknown LaTeX command '\\foo'
+ --> test:1:1
+  |
+1 | \\foo x = 1
+  | ^^^^
+  = note: supported are \\frac, \\sqrt, \\cdot, \\sin and such, and Greek letters
+test:1:1-1:5: error: Unknown LaTeX command '\\foo'
+test:1:1-1:5: note: supported are \\frac, \\sqrt, \\cdot, \\sin and such, and Greek letters
+"
+    );
+    assert_eq!(
+        rendered(latex_error("\\frac{1} = 2"), "\\frac{1} = 2"),
+        "\
+error: Expected an argument after '\\frac'
+ --> test:1:10
+  |
+1 | \\frac{1} = 2
+  |          ^
+  = note: an argument is a character, a command or a group like `{x + 1}`
+test:1:10-1:10: error: Expected an argument after '\\frac'
+test:1:10-1:10: note: an argument is a character, a command or a group like `{x + 1}`
+"
+    );
+    assert_eq!(
+        rendered(latex_error("\\frac{x = 1"), "\\frac{x = 1"),
+        "\
+error: Unclosed '{'
+ --> test:1:6
+  |
+1 | \\frac{x = 1
+  |      ^
+  = note: did you forget a closing '}'?
+test:1:6-1:7: error: Unclosed '{'
+test:1:6-1:7: note: did you forget a closing '}'?
+"
+    );
+}
+
+#[test]
+fn expected_tokens() {
+    assert_eq!(
+        rendered(equation_error("(x + 1 = 2"), "(x + 1 = 2"),
+        "\
+error: Expected ), but got =
+ --> test:1:8
+  |
+1 | (x + 1 = 2
+  |        ^
+  = note: did you forget a closing ')'?
+test:1:8-1:9: error: Expected ), but got =
+test:1:8-1:9: note: did you forget a closing ')'?
+"
+    );
+    assert_eq!(
+        rendered(equation_error("x + 1"), "x + 1"),
+        "\
+error: Expected =, but got EOF
+ --> test:1:6
+  |
+1 | x + 1
+  |      ^
+  = note: an equation needs an '=' between its two sides
+test:1:6-1:6: error: Expected =, but got EOF
+test:1:6-1:6: note: an equation needs an '=' between its two sides
+"
+    );
+    assert_eq!(
+        rendered(definition_error("f(x) = 1"), "f(x) = 1"),
+        "\
+error: Expected :=, but got =
+ --> test:1:6
+  |
+1 | f(x) = 1
+  |      ^
+  = note: a definition looks like `f(x) := 3x^2 + 1` or `g := 9.81`
+test:1:6-1:7: error: Expected :=, but got =
+test:1:6-1:7: note: a definition looks like `f(x) := 3x^2 + 1` or `g := 9.81`
+"
+    );
+    assert_eq!(
+        rendered(system_error("x = 1 y = 2"), "x = 1 y = 2"),
+        "\
+error: Expected ,, but got =
+ --> test:1:9
+  |
+1 | x = 1 y = 2
+  |         ^
+  = note: equations of a system are separated by ',' or new lines
+test:1:9-1:10: error: Expected ,, but got =
+test:1:9-1:10: note: equations of a system are separated by ',' or new lines
+"
+    );
+}
+
+#[test]
+fn expected_atoms() {
+    assert_eq!(
+        rendered(equation_error("x + "), "x + "),
+        "\
+error: Expected atom, but got EOF
+ --> test:1:5
+  |
+1 | x + 
+  |     ^
+  = note: the input ended in the middle of an expression
+test:1:5-1:5: error: Expected atom, but got EOF
+test:1:5-1:5: note: the input ended in the middle of an expression
+"
+    );
+    assert_eq!(
+        rendered(equation_error("x + * 2 = 1"), "x + * 2 = 1"),
+        "\
+error: Expected atom, but got *
+ --> test:1:5
+  |
+1 | x + * 2 = 1
+  |     ^
+  = note: an atom is a number, a name or a parenthesized expression
+test:1:5-1:6: error: Expected atom, but got *
+test:1:5-1:6: note: an atom is a number, a name or a parenthesized expression
+"
+    );
+}
+
+#[test]
+fn functions_and_parameters() {
+    assert_eq!(
+        rendered(equation_error("foo(x, y) = 1"), "foo(x, y) = 1"),
+        "\
+error: Unknown function 'foo'
+ --> test:1:1
+  |
+1 | foo(x, y) = 1
+  | ^^^^^^^^^
+  = note: the known functions are abs, acos, asin, atan, cbrt, ceil, cos, exp, floor, ln, max, min, round, sign, sin, sqrt, tan
+test:1:1-1:10: error: Unknown function 'foo'
+test:1:1-1:10: note: the known functions are abs, acos, asin, atan, cbrt, ceil, cos, exp, floor, ln, max, min, round, sign, sin, sqrt, tan
+"
+    );
+    assert_eq!(
+        rendered(equation_error("sqrt(1, 2) = 1"), "sqrt(1, 2) = 1"),
+        "\
+error: Function 'sqrt' takes 1 argument, but got 2
+ --> test:1:1
+  |
+1 | sqrt(1, 2) = 1
+  | ^^^^^^^^^^
+test:1:1-1:11: error: Function 'sqrt' takes 1 argument, but got 2
+"
+    );
+    assert_eq!(
+        rendered(definition_error("f(x, x) := x"), "f(x, x) := x"),
+        "\
+error: Parameter 'x' is declared more than once
+ --> test:1:6
+  |
+1 | f(x, x) := x
+  |      ^
+test:1:6-1:7: error: Parameter 'x' is declared more than once
+"
+    );
+}
+
+#[test]
+fn spans_across_lines() {
+    let source = "x = (1 +\n  2\n";
+    let span = SourceSpan {
+        filepath: "test".to_string(),
+        start: SourceLocation { line: 1, column: 5 },
+        end: SourceLocation { line: 2, column: 4 },
+    };
+    let diagnostic = Diagnostic::new("Something is wrong", span).with_note("twice");
+    assert_eq!(
+        diagnostic.render(source, DiagnosticStyle::Human),
+        "\
+error: Something is wrong
+ --> test:1:5
+  |
+1 | x = (1 +
+  |     ^^^^
+2 |   2
+  | ^^^
+  = note: twice
+"
+    );
+    assert_eq!(
+        diagnostic.render(source, DiagnosticStyle::Machine),
+        "test:1:5-2:4: error: Something is wrong\ntest:1:5-2:4: note: twice\n"
+    );
+}
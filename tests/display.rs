@@ -0,0 +1,48 @@
+mod common;
+
+use common::*;
+use math::*;
+
+#[test]
+fn number_formats() {
+    for (value, format, expected) in [
+        (rational(7, 3), NumberFormat::Fraction, "7/3"),
+        (rational(-4, 2), NumberFormat::Fraction, "-2"),
+        (rational(7, 3), NumberFormat::MixedNumber, "2 1/3"),
+        (rational(-5, 3), NumberFormat::MixedNumber, "-1 2/3"),
+        (rational(2, 3), NumberFormat::MixedNumber, "2/3"),
+        (rational(1, 3), NumberFormat::RepeatingDecimal, "0.(3)"),
+        (rational(-1, 6), NumberFormat::RepeatingDecimal, "-0.1(6)"),
+        (rational(1, 7), NumberFormat::RepeatingDecimal, "0.(142857)"),
+        (rational(5, 4), NumberFormat::RepeatingDecimal, "1.25"),
+        (rational(2, 3), NumberFormat::DecimalUpTo(3), "0.667"),
+        (rational(5, 2), NumberFormat::DecimalUpTo(3), "2.5"),
+        (rational(3, 1), NumberFormat::DecimalUpTo(3), "3"),
+        (rational(-1, 10000), NumberFormat::DecimalUpTo(3), "0"),
+    ] {
+        assert_eq!(format_number(&value, format), expected, "{value}");
+    }
+}
+
+#[test]
+fn negative_values_above_minus_one_keep_their_sign() {
+    assert_eq!(
+        format_number(&rational(-1, 3), NumberFormat::DecimalUpTo(10)),
+        "-0.3333333333"
+    );
+    let atom = Atom::new(AtomKind::Number(rational(-1, 2)));
+    assert_eq!(atom.to_string(), "-0.5");
+}
+
+#[test]
+fn options_apply_to_nested_nodes() {
+    let mut lexer = Lexer::new("test", "x^(1/2) = 1/3 + y");
+    let equation = simplify(&parse_equation(&mut lexer).unwrap()).unwrap();
+    let options = DisplayOptions {
+        number_format: NumberFormat::Fraction,
+    };
+    assert_eq!(
+        equation.with_options(options).to_string(),
+        "-y + x^(1/2) = 1/3"
+    );
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::*;
+use math::*;
+
+fn is_division_by_zero(error: &SolveError) -> bool {
+    error
+        .as_eval_error()
+        .is_some_and(EvalError::is_division_by_zero)
+}
+
+#[test]
+fn equations() {
+    let source = equation("1/0 = 1");
+    let error = simplify(&source).unwrap_err();
+    assert!(
+        error
+            .as_eval_error()
+            .is_some_and(EvalError::is_division_by_zero),
+        "{error}"
+    );
+    assert_eq!(error.to_string(), "test:1:3: Division by zero in '1/0'");
+    let error = solve_for(&source, "x").unwrap_err();
+    assert!(is_division_by_zero(&error), "{error}");
+    let error = solve_for(&equation("x/0 = 1"), "x").unwrap_err();
+    assert!(is_division_by_zero(&error), "{error}");
+    let error = solve_polynomial(&equation("x^2/(1 - 1) = 1"), "x").unwrap_err();
+    assert!(is_division_by_zero(&error), "{error}");
+}
+
+#[test]
+fn systems() {
+    for source in ["x/0 + y = 1, x = 2", "x + y/(2 - 2) = 1, x - y = 0"] {
+        let error = solve_system(&system(source)).unwrap_err();
+        assert!(is_division_by_zero(&error), "{source}: {error}");
+    }
+}
+
+#[test]
+fn powers_that_are_not_real() {
+    let (_, right, _) = equation("x = (-1)^(1/2)").into_equality().unwrap();
+    let error = eval_expression(&right).unwrap_err();
+    assert!(error.is_not_real(), "{error}");
+}
@@ -0,0 +1,62 @@
+#![allow(clippy::result_large_err)]
+
+mod common;
+
+use common::*;
+use math::*;
+use num_rational::BigRational;
+
+fn eval(source: &str, environment: &Environment) -> Result<BigRational, EvalError> {
+    eval_expression_with(&expression(source), environment)
+}
+
+#[test]
+fn later_bindings_shadow_earlier_ones() {
+    let mut environment = Environment::new();
+    environment.bind("x", integer(1));
+    environment.bind("x", integer(2));
+    assert_eq!(environment.get("x"), Some(&Binding::Value(integer(2))));
+    assert_eq!(environment.bindings().count(), 1);
+    assert_eq!(eval("x + 1", &environment).unwrap(), integer(3));
+
+    // expressions see the binding at the time they are used
+    environment.bind("y", expression("2x"));
+    assert_eq!(eval("y", &environment).unwrap(), integer(4));
+    environment.bind("x", expression("5"));
+    assert_eq!(eval("y", &environment).unwrap(), integer(10));
+
+    // unbinding removes the binding instead of uncovering the older one
+    assert_eq!(
+        environment.unbind("x"),
+        Some(Binding::Expression(expression("5")))
+    );
+    assert_eq!(environment.get("x"), None);
+    assert!(eval("x", &environment).unwrap_err().is_unbound_variable());
+}
+
+#[test]
+fn unbound_variables() {
+    let mut environment = Environment::new();
+    assert_eq!(environment.get("x"), None);
+    assert_eq!(environment.unbind("x"), None);
+    let error = eval("1 + 2x", &environment).unwrap_err();
+    assert_eq!(
+        error.as_unbound_variable().map(|(name, _)| name.as_str()),
+        Some("x")
+    );
+    assert_eq!(
+        error.to_string(),
+        "test:1:6: Variable 'x' is not bound to a value"
+    );
+
+    // variables that only appear inside bound expressions are reported too
+    environment.bind("y", expression("z + 1"));
+    let error = eval("y", &environment).unwrap_err();
+    assert_eq!(
+        error.as_unbound_variable().map(|(name, _)| name.as_str()),
+        Some("z")
+    );
+
+    environment.bind("z", expression("y"));
+    assert!(eval("y", &environment).unwrap_err().is_recursive_binding());
+}
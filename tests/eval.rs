@@ -0,0 +1,77 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::*;
+use math::*;
+
+#[test]
+fn exact_powers() {
+    for (base, exponent, expected) in [
+        (rational(2, 1), rational(10, 1), Some(rational(1024, 1))),
+        (rational(2, 3), rational(-2, 1), Some(rational(9, 4))),
+        (rational(4, 9), rational(1, 2), Some(rational(2, 3))),
+        (rational(-8, 1), rational(2, 3), Some(rational(4, 1))),
+        (rational(2, 1), rational(1, 2), None),
+        (rational(0, 1), rational(-1, 1), None),
+    ] {
+        assert_eq!(exact_power(&base, &exponent), expected, "{base}^{exponent}");
+    }
+}
+
+#[test]
+fn powers_are_simplified() {
+    for (source, expected) in [
+        ("x = 2^3*3", "x = 24"),
+        ("x = 2^3^2", "x = 512"),
+        ("x = 2^-1", "x = 0.5"),
+    ] {
+        assert_eq!(
+            simplify(&equation(source)).unwrap().to_string(),
+            expected,
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn huge_powers_are_not_computed() {
+    let start = Instant::now();
+    let huge = rational(2_000_000_000, 1);
+    assert_eq!(exact_power(&rational(2, 1), &huge), None);
+    assert_eq!(exact_power(&rational(1, 3), &huge), None);
+    assert_eq!(exact_power(&rational(1, 1), &huge), Some(rational(1, 1)));
+    assert_eq!(
+        exact_power(&rational(-1, 1), &rational(2_000_000_001, 1)),
+        Some(rational(-1, 1))
+    );
+    assert_eq!(exact_power(&rational(0, 1), &huge), Some(rational(0, 1)));
+    let smallest = rational(i32::MIN.into(), 1);
+    assert_eq!(
+        exact_power(&rational(1, 1), &smallest),
+        Some(rational(1, 1))
+    );
+    assert_eq!(
+        exact_power(&rational(-1, 1), &smallest),
+        Some(rational(1, 1))
+    );
+    assert_eq!(exact_power(&rational(0, 1), &smallest), None);
+    assert_eq!(
+        eval_expression(&expression("1^(-2147483648)")).unwrap(),
+        rational(1, 1)
+    );
+    assert_eq!(
+        simplify_expression_fully(&expression("x + 1^(-2147483648)")).unwrap(),
+        expression("1 + x")
+    );
+
+    let error = eval_expression(&expression("2^2000000000 + x")).unwrap_err();
+    assert!(error.is_too_large(), "{error}");
+    let error = eval_expression(&expression("2^2000000000")).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "test:1:1: '2^2000000000' is too large to compute"
+    );
+    assert!(!expression("2^2000000000").is_exact());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
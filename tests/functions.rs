@@ -0,0 +1,80 @@
+#![allow(clippy::result_large_err)]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::*;
+use math::*;
+use num_rational::BigRational;
+
+fn parse(source: &str) -> Result<Expression, ParsingError> {
+    let mut lexer = Lexer::new("test", source);
+    parse_expression(&mut lexer)
+}
+
+#[test]
+fn exact_results() {
+    for (source, expected) in [
+        ("sqrt(16)", integer(4)),
+        ("cbrt(-27)", integer(-3)),
+        ("abs(-3) + sign(-2)", integer(2)),
+        ("floor(7/2) + ceil(7/2)", integer(7)),
+        ("cos(0) + exp(0) + ln(1)", integer(2)),
+        ("max(1, 5, 3) - min(4, 2)", integer(3)),
+    ] {
+        let expression = parse(source).unwrap();
+        assert!(expression.is_exact(), "{source}");
+        assert_eq!(eval_expression(&expression).unwrap(), expected, "{source}");
+    }
+}
+
+#[test]
+fn inexact_results_stay_symbolic() {
+    let expression = parse("sqrt(2)").unwrap();
+    assert!(!expression.is_exact());
+    let value = eval_expression(&expression).unwrap();
+    assert!((value - BigRational::from_float(2f64.sqrt()).unwrap()) < integer(1) / integer(1000));
+}
+
+#[test]
+fn errors() {
+    assert!(parse("foo(1, 2)").unwrap_err().is_unknown_function());
+    assert!(parse("foo()").unwrap_err().is_unknown_function());
+    assert_eq!(
+        parse("foo((1, 2), 3)").unwrap_err().to_string(),
+        "test:1:1: Unknown function 'foo'"
+    );
+    assert!(parse("sqrt(1, 2)").unwrap_err().is_wrong_argument_count());
+    assert!(parse("max()").unwrap_err().is_wrong_argument_count());
+    assert!(eval_expression(&parse("sqrt(-1)").unwrap())
+        .unwrap_err()
+        .is_not_real());
+    assert!(eval_expression(&parse("ln(0)").unwrap())
+        .unwrap_err()
+        .is_not_real());
+
+    let call = Expression::new(vec![Term::new(vec![Atom::new(AtomKind::Call {
+        name: "foo".to_string(),
+        args: vec![],
+    })])]);
+    assert!(eval_expression(&call).unwrap_err().is_unknown_function());
+}
+
+#[test]
+fn unknown_names_before_a_group_are_multiplication() {
+    let expression = parse("x(2)").unwrap();
+    assert_eq!(expression.to_string(), "x*2");
+    assert_eq!(parse("x(y + 1)^2").unwrap().to_string(), "x*(y + 1)^2");
+    assert_eq!(parse("2^x(y + 1)").unwrap().to_string(), "2^x*(y + 1)");
+    assert!(parse("x(y + 1").is_err());
+
+    // each group is parsed once, so deep nesting does not take exponential time
+    let start = Instant::now();
+    let nested = format!("{}1{}", "a(".repeat(30), ")".repeat(30));
+    assert_eq!(
+        parse(&nested).unwrap(),
+        parse(&nested.replace('(', "*(")).unwrap()
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,55 @@
+use math::*;
+use num_rational::BigRational;
+
+fn latex(source: &str) -> String {
+    parse_equation(&mut Lexer::new("test", source))
+        .unwrap()
+        .to_latex()
+}
+
+#[test]
+fn expressions() {
+    for (source, expected) in [
+        ("x/2 = 1", "\\frac{x}{2} = 1"),
+        ("(x + 1)/(x - 1) = 0", "\\frac{x + 1}{x - 1} = 0"),
+        ("x^2 = 4", "x^{2} = 4"),
+        ("(x + 1)^(1 + y) = 0", "\\left(x + 1\\right)^{1 + y} = 0"),
+        ("x^(1/2) = sqrt(y)", "\\sqrt{x} = \\sqrt{y}"),
+        ("cbrt(x) = abs(y)", "\\sqrt[3]{x} = \\left|y\\right|"),
+        ("a - b - (c - d) = 0", "a - b - \\left(c - d\\right) = 0"),
+        ("2x*y = 2*3^x", "2xy = 2 \\cdot 3^{x}"),
+        ("x*(-y) = -x", "x\\left(-y\\right) = -x"),
+        (
+            "sin(x) = max(x, 1)",
+            "\\sin\\left(x\\right) = \\max\\left(x, 1\\right)",
+        ),
+        ("speed = x1", "\\mathit{speed} = x_{1}"),
+        ("(x/2)^2 = 1", "\\left(\\frac{x}{2}\\right)^{2} = 1"),
+        ("x/2*y = 1", "\\frac{x}{2}y = 1"),
+        ("alpha*x = beta1", "\\alpha x = \\beta_{1}"),
+        ("2pi*Omega = theta^2", "2\\pi\\Omega = \\theta^{2}"),
+    ] {
+        assert_eq!(latex(source), expected, "{source}");
+    }
+}
+
+#[test]
+fn numbers_are_exact_fractions() {
+    let mut lexer = Lexer::new("test", "x/2 - 0.25 = y");
+    let equation = simplify(&parse_equation(&mut lexer).unwrap()).unwrap();
+    assert_eq!(equation.to_latex(), "-y + \\frac{1}{2}x = \\frac{1}{4}");
+}
+
+#[test]
+fn fractions_are_parenthesized_as_bases() {
+    let half = Atom::new(AtomKind::Number(BigRational::new(1.into(), 2.into())));
+    let power = Atom::new(AtomKind::Power {
+        base: Box::new(half.clone()),
+        exponent: Box::new(Atom::new(AtomKind::Number(BigRational::from_integer(
+            2.into(),
+        )))),
+    });
+    assert_eq!(power.to_latex(), "\\left(\\frac{1}{2}\\right)^{2}");
+    let product = Term::new(vec![Atom::new(AtomKind::Variable("x".to_string())), half]);
+    assert_eq!(product.to_latex(), "\\frac{1}{2}x");
+}
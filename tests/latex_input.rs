@@ -0,0 +1,93 @@
+#![allow(clippy::result_large_err)]
+
+mod common;
+
+use common::*;
+use math::*;
+
+fn latex(source: &str) -> Result<Equation, ParsingError> {
+    parse_equation(&mut LatexLexer::new("test", source))
+}
+
+#[test]
+fn same_ast_as_plain_input() {
+    for (latex_source, plain_source) in [
+        ("\\frac{x+1}{2} = 3", "((x+1)/(2)) = 3"),
+        ("2\\cdot x = 4 \\times y", "2*x = 4*y"),
+        ("x^{2} = x^2", "x^(2) = x^2"),
+        ("x^23 = 1", "x^2*3 = 1"),
+        ("2xy = 1", "2x*y = 1"),
+        ("\\frac12 x = 1", "(1/2)*x = 1"),
+        ("\\left(a + b\\right)(a - b) = 0", "(a + b)(a - b) = 0"),
+        ("\\sqrt{x} = \\sqrt2", "sqrt(x) = sqrt(2)"),
+        ("\\sqrt[3]{x} = 2", "((x)^(1/(3))) = 2"),
+        (
+            "\\left|x\\right| = \\lfloor y \\rfloor",
+            "abs(x) = floor(y)",
+        ),
+        ("\\sin\\left(x\\right) = \\cos x", "sin(x) = cos(x)"),
+        ("\\arctan(x) = \\pi r^{2}", "atan(x) = pi*r^(2)"),
+        (
+            "\\alpha_1 = \\mathit{speed} + x_{12}",
+            "alpha1 = speed + x12",
+        ),
+        ("2\\,x = 1", "2x = 1"),
+    ] {
+        assert_eq!(
+            latex(latex_source).unwrap(),
+            equation(plain_source),
+            "{latex_source}"
+        );
+    }
+}
+
+#[test]
+fn printed_latex_reads_back() {
+    for source in [
+        "alpha*x = beta1*gamma",
+        "(x/2)^2 = 2pi*r",
+        "sqrt(a + b)^3 = -x/y",
+    ] {
+        // braces read back as groups, so the equations are compared as printed
+        let plain = equation(source);
+        assert_eq!(
+            latex(&plain.to_latex()).unwrap().to_string(),
+            plain.to_string(),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn spans_point_into_the_latex_source() {
+    let equation = latex("\\frac{x}{2} = \\alpha").unwrap();
+    let (left, right, span) = equation.as_equality().unwrap();
+    let span = span.as_ref().unwrap();
+    assert_eq!((span.start.column, span.end.column), (1, 21));
+    let left = left.span.as_ref().unwrap();
+    assert_eq!((left.start.column, left.end.column), (1, 12));
+    let right = right.span.as_ref().unwrap();
+    assert_eq!((right.start.column, right.end.column), (15, 21));
+}
+
+#[test]
+fn errors() {
+    let error = latex("\\foo{x} = 1").unwrap_err();
+    let span = error
+        .as_lexer_error()
+        .unwrap()
+        .as_unknown_command()
+        .unwrap()
+        .0;
+    assert_eq!((span.start.column, span.end.column), (1, 5));
+    assert!(latex("\\frac{x = 1")
+        .unwrap_err()
+        .as_lexer_error()
+        .unwrap()
+        .is_unclosed_brace());
+    assert!(latex("x^ = 1")
+        .unwrap_err()
+        .as_lexer_error()
+        .unwrap()
+        .is_missing_argument());
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::*;
+use math::*;
+
+#[test]
+fn rules_that_go_around_in_circles_stop() {
+    let mut rules = RuleSet::new();
+    rules.add(Rule::parse("commute", "?a + ?b -> ?b + ?a").unwrap());
+    let error = simplify_expression_fully_with(&expression("x + y"), &rules).unwrap_err();
+    let (limit, best) = error.as_limit_exceeded().unwrap();
+    assert_eq!(limit, &SimplifyLimit::Cycle);
+    assert_eq!(best, &expression("x + y"));
+
+    let mut rules = RuleSet::new();
+    // in the reverse order, so a single pass does not go all the way around
+    rules.add(Rule::parse("tan to sin", "tan(?x) -> sin(?x)").unwrap());
+    rules.add(Rule::parse("cos to tan", "cos(?x) -> tan(?x)").unwrap());
+    rules.add(Rule::parse("sin to cos", "sin(?x) -> cos(?x)").unwrap());
+    let error = simplify_expression_fully_with(&expression("sin(x)"), &rules).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Gave up simplifying after going around in circles, the simplest result so far is 'cos(x)'"
+    );
+
+    let mut rules = RuleSet::builtin();
+    rules.add(Rule::parse("commute", "?a + ?b -> ?b + ?a").unwrap());
+    assert!(simplify_with(&equation("x + y = 2"), &rules).is_ok());
+}
+
+#[test]
+fn growing_expressions_stop() {
+    let source = expression("(a + b)*(c + d)*(e + f)*(g + h)*(i + j)*(k + l)");
+    let options = SimplifyOptions {
+        max_nodes: 50,
+        ..SimplifyOptions::default()
+    };
+    let error =
+        simplify_expression_fully_with_options(&source, &RuleSet::builtin(), &options).unwrap_err();
+    let (limit, best) = error.as_limit_exceeded().unwrap();
+    assert_eq!(limit, &SimplifyLimit::Nodes(50));
+    assert_eq!(best, &source);
+}
+
+#[test]
+fn growing_expressions_stop_in_the_middle_of_a_pass() {
+    // distributing all of it at once would make 2^22 terms
+    let source = expression(
+        &(0..22)
+            .map(|i| format!("(a{i} + b{i})"))
+            .collect::<Vec<_>>()
+            .join("*"),
+    );
+    let options = SimplifyOptions {
+        max_nodes: 10_000,
+        timeout: Duration::from_secs(1),
+        ..SimplifyOptions::default()
+    };
+    let start = Instant::now();
+    let error =
+        simplify_expression_fully_with_options(&source, &RuleSet::builtin(), &options).unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    let (limit, best) = error.as_limit_exceeded().unwrap();
+    assert_eq!(limit, &SimplifyLimit::Nodes(10_000));
+    assert_eq!(best, &source);
+}
+
+#[test]
+fn the_best_result_is_kept() {
+    let source = equation("2x + 3x + 3 = 13");
+    let options = SimplifyOptions {
+        max_iterations: 1,
+        ..SimplifyOptions::default()
+    };
+    let error = simplify_with_options(&source, &RuleSet::builtin(), &options).unwrap_err();
+    let (limit, best) = error.as_limit_exceeded().unwrap();
+    assert_eq!(limit, &SimplifyLimit::Iterations(1));
+    assert_eq!(best, &equation("x = 2"));
+    assert_eq!(
+        error.to_string(),
+        "Gave up simplifying after 1 iterations, the simplest result so far is 'x = 2'"
+    );
+
+    let options = SimplifyOptions {
+        timeout: Duration::ZERO,
+        ..SimplifyOptions::default()
+    };
+    let error = simplify_with_options(&source, &RuleSet::builtin(), &options).unwrap_err();
+    assert_eq!(
+        error.as_limit_exceeded().unwrap(),
+        (&SimplifyLimit::Timeout(Duration::ZERO), &source)
+    );
+}
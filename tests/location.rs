@@ -0,0 +1,80 @@
+use math::*;
+
+fn span(start: (usize, usize), end: (usize, usize)) -> SourceSpan {
+    SourceSpan {
+        filepath: "test".to_string(),
+        start: SourceLocation {
+            line: start.0,
+            column: start.1,
+        },
+        end: SourceLocation {
+            line: end.0,
+            column: end.1,
+        },
+    }
+}
+
+#[test]
+fn merge() {
+    assert_eq!(
+        span((1, 1), (1, 4)).merge(&span((1, 3), (1, 8))),
+        span((1, 1), (1, 8))
+    );
+    assert_eq!(
+        span((1, 6), (1, 8)).merge(&span((1, 1), (1, 2))),
+        span((1, 1), (1, 8))
+    );
+    assert_eq!(
+        span((1, 2), (1, 9)).merge(&span((1, 4), (1, 5))),
+        span((1, 2), (1, 9))
+    );
+    // lines are compared before columns
+    assert_eq!(
+        span((1, 8), (1, 9)).merge(&span((2, 1), (2, 3))),
+        span((1, 8), (2, 3))
+    );
+    assert_eq!(
+        span((3, 1), (4, 2)).merge(&span((1, 5), (3, 9))),
+        span((1, 5), (4, 2))
+    );
+}
+
+#[test]
+fn merge_all() {
+    assert_eq!(SourceSpan::merge_all([]), None);
+    assert_eq!(
+        SourceSpan::merge_all([&span((1, 3), (1, 4))]),
+        Some(span((1, 3), (1, 4)))
+    );
+    assert_eq!(
+        SourceSpan::merge_all([&span((1, 5), (1, 6)), &span((1, 3), (1, 4))]),
+        Some(span((1, 3), (1, 6)))
+    );
+}
+
+#[test]
+fn merge_spans_skips_missing_spans() {
+    assert_eq!(merge_spans([]), None);
+    assert_eq!(merge_spans([&None, &None]), None);
+    assert_eq!(
+        merge_spans([&None, &Some(span((1, 3), (1, 4))), &None]),
+        Some(span((1, 3), (1, 4)))
+    );
+    assert_eq!(
+        merge_spans([
+            &Some(span((2, 1), (2, 7))),
+            &None,
+            &Some(span((1, 9), (1, 10))),
+        ]),
+        Some(span((1, 9), (2, 7)))
+    );
+}
+
+#[test]
+fn parsed_nodes_have_spans() {
+    let equation = parse_equation(&mut Lexer::new("test", "x + 12 = 3")).unwrap();
+    let (left, right, _) = equation.as_equality().unwrap();
+    assert_eq!(left.span, Some(span((1, 1), (1, 7))));
+    assert_eq!(left.terms[1].atoms[0].span, Some(span((1, 5), (1, 7))));
+    assert_eq!(right.span, Some(span((1, 10), (1, 11))));
+}
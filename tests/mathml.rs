@@ -0,0 +1,160 @@
+mod common;
+
+use common::*;
+use math::*;
+use num_rational::BigRational;
+
+fn math(markup: &str) -> String {
+    format!("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">{markup}</math>")
+}
+
+#[test]
+fn presentation_export() {
+    for (source, expected) in [
+        (
+            "x/2 = 3",
+            "<mrow><mfrac><mi>x</mi><mn>2</mn></mfrac><mo>=</mo><mn>3</mn></mrow>",
+        ),
+        (
+            "2x^2 - y = x1",
+            "<mrow><mrow><mrow><mn>2</mn><mo>&#x2062;</mo><msup><mi>x</mi><mn>2</mn></msup></mrow>\
+             <mo>&#x2212;</mo><mi>y</mi></mrow><mo>=</mo><msub><mi>x</mi><mn>1</mn></msub></mrow>",
+        ),
+        (
+            "2*3^x = sqrt(y + 1)",
+            "<mrow><mrow><mn>2</mn><mo>&#x22C5;</mo><msup><mn>3</mn><mi>x</mi></msup></mrow>\
+             <mo>=</mo><msqrt><mrow><mi>y</mi><mo>+</mo><mn>1</mn></mrow></msqrt></mrow>",
+        ),
+        (
+            "-(a + b) = sin(x)",
+            "<mrow><mrow><mo>&#x2212;</mo><mrow><mo>(</mo><mrow><mi>a</mi><mo>+</mo><mi>b</mi></mrow>\
+             <mo>)</mo></mrow></mrow><mo>=</mo><mrow><mi>sin</mi><mo>&#x2061;</mo>\
+             <mrow><mo>(</mo><mi>x</mi><mo>)</mo></mrow></mrow></mrow>",
+        ),
+        (
+            "(x/2)^2 = 1",
+            "<mrow><msup><mrow><mo>(</mo><mfrac><mi>x</mi><mn>2</mn></mfrac><mo>)</mo></mrow>\
+             <mn>2</mn></msup><mo>=</mo><mn>1</mn></mrow>",
+        ),
+    ] {
+        assert_eq!(equation(source).to_mathml(), math(expected), "{source}");
+    }
+    let half = Atom::new(AtomKind::Number(BigRational::new(1.into(), 2.into())));
+    let squared = Expression::new(vec![Term::new(vec![Atom::new(AtomKind::Power {
+        base: Box::new(half),
+        exponent: Box::new(Atom::new(AtomKind::Number(BigRational::from_integer(
+            2.into(),
+        )))),
+    })])]);
+    assert_eq!(
+        squared.to_mathml(),
+        math(
+            "<msup><mrow><mo>(</mo><mfrac><mn>1</mn><mn>2</mn></mfrac><mo>)</mo></mrow>\
+             <mn>2</mn></msup>"
+        )
+    );
+}
+
+#[test]
+fn exported_names_are_escaped() {
+    let name = |name: &str| {
+        Expression::new(vec![Term::new(vec![Atom::new(AtomKind::Variable(
+            name.to_string(),
+        ))])])
+    };
+    assert_eq!(name("a<b").to_mathml(), math("<mi>a&lt;b</mi>"));
+    assert_eq!(
+        name("a&b1").to_mathml(),
+        math("<msub><mi>a&amp;b</mi><mn>1</mn></msub>")
+    );
+    let call = Expression::new(vec![Term::new(vec![Atom::new(AtomKind::Call {
+        name: "f>".to_string(),
+        args: vec![name("x")],
+    })])]);
+    assert!(call.to_mathml().contains("<mi>f&gt;</mi>"));
+}
+
+#[test]
+fn content_import() {
+    for (markup, source) in [
+        (
+            "<apply><eq/><apply><divide/><apply><plus/><ci>x</ci><cn>1</cn></apply><cn>2</cn></apply>\
+             <cn>3</cn></apply>",
+            "(x + 1)/2 = 3",
+        ),
+        (
+            "<apply><eq/><apply><minus/><apply><times/><cn>2</cn><ci>x</ci></apply><ci>y</ci></apply>\
+             <apply><minus/><cn type=\"rational\">1<sep/>3</cn></apply></apply>",
+            "2x - y = -(1/3)",
+        ),
+        (
+            "<apply><eq/><apply><power/><ci>x</ci><cn>2</cn></apply>\
+             <apply><root/><degree><cn>3</cn></degree><ci>y</ci></apply></apply>",
+            "x^2 = y^(1/3)",
+        ),
+        (
+            "<apply><eq/><apply><abs/><ci>x</ci></apply><apply><max/><cn>2.5</cn><apply><root/><ci>y</ci></apply></apply></apply>",
+            "abs(x) = max(2.5, sqrt(y))",
+        ),
+    ] {
+        // the AST can differ in groups, like `(x + 1)` which is a group in plain input
+        let options = DisplayOptions {
+            number_format: NumberFormat::Fraction,
+        };
+        let imported = parse_mathml_equation("test", &math(markup)).unwrap();
+        assert_eq!(
+            imported.with_options(options).to_string(),
+            equation(source).with_options(options).to_string(),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn spans_point_into_the_mathml_source() {
+    let expression = parse_mathml_expression("test", "<apply><plus/>\n<ci>x</ci></apply>").unwrap();
+    let span = expression.terms[0].atoms[0].span.as_ref().unwrap();
+    assert_eq!((span.start.line, span.start.column), (2, 1));
+    assert_eq!((span.end.line, span.end.column), (2, 11));
+}
+
+#[test]
+fn errors_have_element_paths() {
+    let error = parse_mathml_equation(
+        "test",
+        &math("<apply><eq/><apply><plus/><ci>x</ci><foo/></apply><cn>1</cn></apply>"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "/math/apply/apply/foo: Unknown element <foo>"
+    );
+
+    let error = parse_mathml_expression(
+        "test",
+        "<apply><plus/><cn>1</cn><apply><divide/><cn>1</cn></apply><cn>x</cn></apply>",
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "/apply/apply: <divide/> takes 2 arguments, but got 1"
+    );
+
+    let error =
+        parse_mathml_expression("test", "<apply><plus/><cn>1</cn><cn>x</cn></apply>").unwrap_err();
+    assert!(error.is_invalid_number());
+    assert_eq!(error.to_string(), "/apply/cn[2]: Invalid number 'x'");
+
+    let error = parse_mathml_expression("test", "<ci>a&lt;b</ci>").unwrap_err();
+    assert!(error.is_invalid_name());
+    assert_eq!(error.to_string(), "/ci: Invalid name 'a<b'");
+    assert!(parse_mathml_expression("test", "<ci>1x</ci>")
+        .unwrap_err()
+        .is_invalid_name());
+
+    let error = parse_mathml_equation("test", &math("<ci>x</ci>")).unwrap_err();
+    assert!(error.is_expected_equation());
+    assert!(parse_mathml_expression("test", "<apply>")
+        .unwrap_err()
+        .is_invalid_xml());
+}
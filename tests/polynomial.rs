@@ -0,0 +1,134 @@
+mod common;
+
+use common::*;
+use math::*;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use std::time::{Duration, Instant};
+
+fn expanded(source: &str) -> String {
+    expression(source).expand().unwrap().to_string()
+}
+
+#[test]
+fn products_and_powers_are_multiplied_out() {
+    for (source, expected) in [
+        ("(x + 1)^2", "x^2 + 2x + 1"),
+        ("(x - y)(x + y)", "x^2 - y^2"),
+        ("x*x", "x^2"),
+        ("2(a + b)/4 - a", "-0.5a + 0.5b"),
+        ("(x + 1)^3 - x^3", "3x^2 + 3x + 1"),
+        ("x - x", "0"),
+        ("y*x^2*3 - 1", "3x^2*y - 1"),
+    ] {
+        assert_eq!(expanded(source), expected, "{source}");
+    }
+}
+
+#[test]
+fn equal_polynomials_print_the_same() {
+    for (a, b) in [
+        ("x*y + y*x", "2y*x"),
+        ("(a + b)^2", "b^2 + 2a*b + a^2"),
+        ("x*x*y", "y*x^2"),
+        ("(1 + z)(y + x)", "x*z + y + x + z*y"),
+    ] {
+        let a = Polynomial::from_expression(&expression(a)).unwrap();
+        let b = Polynomial::from_expression(&expression(b)).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.to_string(), b.to_string());
+    }
+}
+
+#[test]
+fn terms_are_in_canonical_order() {
+    let polynomial =
+        Polynomial::from_expression(&expression("1 + y^2 + x + x*y + x^2 + y")).unwrap();
+    let monomials = polynomial
+        .terms()
+        .map(|(monomial, _)| monomial.variables().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        monomials,
+        [
+            vec![("x", 2)],
+            vec![("x", 1), ("y", 1)],
+            vec![("y", 2)],
+            vec![("x", 1)],
+            vec![("y", 1)],
+            vec![],
+        ]
+    );
+    assert_eq!(polynomial.degree(), Some(2));
+    assert_eq!(
+        polynomial.coefficient(&Monomial::variable("x")),
+        BigRational::from_integer(BigInt::from(1))
+    );
+    assert_eq!(Polynomial::zero().degree(), None);
+}
+
+#[test]
+fn arithmetic() {
+    let x = Polynomial::variable("x");
+    let one = Polynomial::constant(BigRational::from_integer(BigInt::from(1)));
+    let square = (&x + &one).checked_mul(&(&x - &one)).unwrap();
+    assert_eq!(square.to_string(), "x^2 - 1");
+    assert_eq!(&square - &square, Polynomial::zero());
+    assert_eq!(
+        (&x + &one).checked_pow(2).unwrap(),
+        Polynomial::from_expression(&expression("x^2 + 2x + 1")).unwrap()
+    );
+    assert_eq!(x.checked_pow(u32::MAX).unwrap().degree(), Some(u32::MAX));
+    assert_eq!(x.checked_pow(u32::MAX).unwrap().checked_mul(&x), None);
+}
+
+#[test]
+fn large_exponents() {
+    assert_eq!(expanded("x^3000000000"), "x^3000000000");
+    assert_eq!(expanded("(2x)^3*x^3000000000"), "8x^3000000003");
+    for source in [
+        "x^3000000000*x^3000000000",
+        "(x^3000000000)^2",
+        "x^3000000000*y^3000000000",
+        "x^5000000000",
+    ] {
+        let error = expression(source).expand().unwrap_err();
+        assert!(error.is_too_large(), "{source}: {error}");
+    }
+}
+
+#[test]
+fn large_expansions_stop() {
+    let start = Instant::now();
+    for source in ["(2x)^3000000000", "(x + y + 1)^2000", "(x + 1)^100000"] {
+        let error = expression(source).expand().unwrap_err();
+        assert!(error.is_too_large(), "{source}: {error}");
+    }
+    assert_eq!(
+        expression("(x + y + 1)^2000")
+            .expand()
+            .unwrap_err()
+            .to_string(),
+        "test:1:1: '(x + y + 1)^2000' is too large to expand"
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(
+        Polynomial::from_expression(&expression("(x + 1)^100"))
+            .unwrap()
+            .terms()
+            .count(),
+        101
+    );
+}
+
+#[test]
+fn non_polynomials_are_rejected() {
+    for source in ["sqrt(x)", "1/x", "x^-1", "x^(1/2)", "2^x"] {
+        let error = expression(source).expand().unwrap_err();
+        assert!(error.is_not_polynomial(), "{source}: {error}");
+    }
+    assert_eq!(
+        expression("x/(y - y)").expand().unwrap_err().to_string(),
+        "test:1:1: Division by zero in 'x/(y - y)'"
+    );
+}
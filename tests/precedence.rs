@@ -0,0 +1,99 @@
+mod common;
+
+use common::*;
+use math::*;
+use num_rational::BigRational;
+
+fn eval(source: &str, environment: &Environment) -> BigRational {
+    let mut lexer = Lexer::new("test", source);
+    eval_expression_with(&parse_expression(&mut lexer).unwrap(), environment).unwrap()
+}
+
+fn check(cases: &[(&str, BigRational)]) {
+    let mut environment = Environment::new();
+    environment.bind("a", rational(10, 1));
+    environment.bind("b", rational(4, 1));
+    environment.bind("c", rational(3, 1));
+    for (source, expected) in cases {
+        assert_eq!(&eval(source, &environment), expected, "{source}");
+    }
+}
+
+#[test]
+fn subtraction_is_left_associative() {
+    check(&[
+        ("10 - 4 - 3", rational(3, 1)),
+        ("a - b - c", rational(3, 1)),
+        ("10 - 2 + 3", rational(11, 1)),
+        ("a + b - c - 1", rational(10, 1)),
+    ]);
+}
+
+#[test]
+fn division_is_left_associative() {
+    check(&[
+        ("24 / 4 / 2", rational(3, 1)),
+        ("a / b / c", rational(5, 6)),
+        ("24 / 4 * 2", rational(12, 1)),
+        ("2 * 3 / 4", rational(3, 2)),
+        ("1 + 2 / 3 / 4", rational(7, 6)),
+    ]);
+}
+
+#[test]
+fn power_is_right_associative() {
+    check(&[
+        ("2 ^ 3 ^ 2", rational(512, 1)),
+        ("2 ^ 3 * 2", rational(16, 1)),
+        ("8 / 2 ^ 2", rational(2, 1)),
+        ("(2 ^ 3) ^ 2", rational(64, 1)),
+    ]);
+}
+
+#[test]
+fn multiplication_binds_tighter_than_addition() {
+    check(&[
+        ("2 * 3 + 4", rational(10, 1)),
+        ("2 + 3 * 4", rational(14, 1)),
+        ("2 * (3 + 4)", rational(14, 1)),
+        ("a - b * c", rational(-2, 1)),
+    ]);
+}
+
+#[test]
+fn unary_operators() {
+    check(&[
+        ("-2 ^ 2", rational(-4, 1)),
+        ("(-2) ^ 2", rational(4, 1)),
+        ("2 ^ -1", rational(1, 2)),
+        ("2 ^ -2 ^ 2", rational(1, 16)),
+        ("2 ^ -1 * 3", rational(3, 2)),
+        ("- - 3", rational(3, 1)),
+        ("+3 - -1", rational(4, 1)),
+        ("2 * -3", rational(-6, 1)),
+        ("-a / b", rational(-5, 2)),
+    ]);
+}
+
+#[test]
+fn implicit_multiplication() {
+    check(&[
+        ("2a + 3", rational(23, 1)),
+        ("3(a + 1)", rational(33, 1)),
+        ("a(b)", rational(40, 1)),
+        ("(a)(b)", rational(40, 1)),
+        ("1/2a", rational(5, 1)),
+        ("6/2(1 + 2)", rational(9, 1)),
+        ("2^3a", rational(80, 1)),
+        ("-2a", rational(-20, 1)),
+        ("a - 2b", rational(2, 1)),
+    ]);
+}
+
+#[test]
+fn entry_points_check_for_eof() {
+    assert!(parse_expression(&mut Lexer::new("test", "1 + 2)")).is_err());
+    assert!(parse_expression(&mut Lexer::new("test", "1 + 2 = 3")).is_err());
+    assert!(parse_atom(&mut Lexer::new("test", "(1 + 2)")).is_ok());
+    assert!(parse_atom(&mut Lexer::new("test", "1 + 2")).is_err());
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::*;
+use math::*;
+use proptest::prelude::*;
+
+fn number() -> impl Strategy<Value = Atom> {
+    (-9i64..=9, 1i64..=4).prop_map(|(numerator, denominator)| {
+        Atom::new(AtomKind::Number(rational(numerator, denominator)))
+    })
+}
+
+fn leaf() -> impl Strategy<Value = Atom> {
+    prop_oneof![
+        number(),
+        prop::sample::select(vec!["a", "b", "c"])
+            .prop_map(|name| Atom::new(AtomKind::Variable(name.to_string()))),
+    ]
+}
+
+fn expression_from(atom: BoxedStrategy<Atom>) -> BoxedStrategy<Expression> {
+    prop::collection::vec(prop::collection::vec(atom, 0..3), 0..3)
+        .prop_map(|terms| Expression::new(terms.into_iter().map(Term::new).collect()))
+        .boxed()
+}
+
+fn atom() -> BoxedStrategy<Atom> {
+    leaf()
+        .prop_recursive(3, 24, 3, |atom| {
+            let expression = expression_from(atom.clone());
+            prop_oneof![
+                expression
+                    .clone()
+                    .prop_map(|expression| Atom::new(AtomKind::Group(expression))),
+                (expression.clone(), expression.clone()).prop_map(|(numerator, denominator)| {
+                    Atom::new(AtomKind::Fraction {
+                        numerator,
+                        denominator,
+                    })
+                }),
+                // integer exponents keep the values exact
+                (atom, -3i64..=3).prop_map(|(base, exponent)| {
+                    Atom::new(AtomKind::Power {
+                        base: Box::new(base),
+                        exponent: Box::new(Atom::new(AtomKind::Number(rational(exponent, 1)))),
+                    })
+                }),
+                (
+                    prop::sample::select(vec!["abs", "min", "max"]),
+                    prop::collection::vec(expression, 1..3)
+                )
+                    .prop_map(|(name, args)| {
+                        let args = if name == "abs" {
+                            args[..1].to_vec()
+                        } else {
+                            args
+                        };
+                        Atom::new(AtomKind::Call {
+                            name: name.to_string(),
+                            args,
+                        })
+                    }),
+            ]
+        })
+        .boxed()
+}
+
+fn environment() -> Environment {
+    let mut environment = Environment::new();
+    environment.bind("a", rational(2, 3));
+    environment.bind("b", rational(-5, 2));
+    environment.bind("c", rational(7, 1));
+    environment
+}
+
+proptest! {
+    #[test]
+    fn printed_expressions_parse_back_to_the_same_value(expression in expression_from(atom())) {
+        let environment = environment();
+        let value = eval_expression_with(&expression, &environment);
+        // expressions that divide by zero have no value to compare
+        prop_assume!(value.is_ok());
+
+        let options = DisplayOptions {
+            number_format: NumberFormat::Fraction,
+        };
+        let printed = expression.with_options(options).to_string();
+        let parsed = parse_expression(&mut Lexer::new("test", &printed))
+            .unwrap_or_else(|error| panic!("'{printed}' does not parse: {error}"));
+        prop_assert_eq!(
+            eval_expression_with(&parsed, &environment).ok(),
+            value.ok(),
+            "{}",
+            printed
+        );
+    }
+}
+
+#[test]
+fn minimal_parentheses() {
+    for (source, expected) in [
+        ("a - b - c", "a - b - c"),
+        ("a - (b - c)", "a - (b - c)"),
+        ("a - (b + c)", "a - (b + c)"),
+        ("a + (b + c)", "a + b + c"),
+        ("-x", "-x"),
+        ("a/b", "a/b"),
+        ("a/(b*c)", "a/(b*c)"),
+        ("(a + b)/c", "(a + b)/c"),
+        ("2*x", "2x"),
+        ("2*(x + 1)", "2(x + 1)"),
+        ("2*3^x", "2*3^x"),
+        ("(-2)^2", "(-2)^2"),
+        ("-2^2", "-2^2"),
+        ("(x^2)^3", "(x^2)^3"),
+        ("x^2^3", "x^2^3"),
+        ("x^(1 + y)", "x^(1 + y)"),
+        ("sqrt(x + 1)", "sqrt(x + 1)"),
+    ] {
+        let expression = parse_expression(&mut Lexer::new("test", source)).unwrap();
+        assert_eq!(expression.to_string(), expected, "{source}");
+    }
+}
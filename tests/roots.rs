@@ -0,0 +1,79 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::*;
+use math::*;
+
+fn roots(source: &str) -> String {
+    solve_polynomial(&equation(source), "x")
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn exact_roots() {
+    for (source, expected) in [
+        ("2x - 6 = 0", "{3}"),
+        ("x^2 = 4", "{-2, 2}"),
+        ("x^2 + 1 = 0", "{}"),
+        ("(x - 1)^2 = 0", "{1}"),
+        ("x^3 - 6x^2 + 11x - 6 = 0", "{1, 2, 3}"),
+        ("2x^3 = x^2", "{0, 0.5}"),
+        ("x^4 = 16", "{-2, 2}"),
+        ("x^5 = -32", "{-2}"),
+        ("x^4 = -16", "{}"),
+        ("x = x", "all values"),
+        (
+            "12x^3 - 25x^2 + 7x + 6 = 0",
+            "{(13 - 457^0.5)/24, 1, (13 + 457^0.5)/24}",
+        ),
+        ("6x^2 - 5x + 1 = 0", "{0.3333333333, 0.5}"),
+    ] {
+        assert_eq!(roots(source), expected, "{source}");
+    }
+}
+
+#[test]
+fn irrational_roots() {
+    assert_eq!(roots("x^2 = 8"), "{-2*2^0.5, 2*2^0.5}");
+    assert_eq!(roots("x^2 - 2x - 1 = 0"), "{1 - 2^0.5, 1 + 2^0.5}");
+    assert_eq!(roots("x^3 = 2"), "{} and the roots of x^3 - 2 = 0");
+    assert_eq!(
+        roots("x^3 + x + 1 = 0"),
+        "{} and the roots of x^3 + x + 1 = 0"
+    );
+}
+
+#[test]
+fn large_polynomials_are_rejected() {
+    let start = Instant::now();
+    let error = solve_polynomial(&equation("x^1000000000 = 1"), "x").unwrap_err();
+    assert!(error.is_degree_too_high(), "{error}");
+    let error = solve_polynomial(&equation("(x^2 + 1)^600 = 0"), "x").unwrap_err();
+    assert!(error.is_degree_too_high(), "{error}");
+    let error =
+        solve_polynomial(&equation("x^2 = 1000000000000000000000000000001"), "x").unwrap_err();
+    assert!(error.is_coefficient_too_large(), "{error}");
+    let error =
+        solve_polynomial(&equation("x^3 + x = 1000000000000000000000000000001"), "x").unwrap_err();
+    assert!(error.is_coefficient_too_large(), "{error}");
+    assert_eq!(
+        error.to_string(),
+        "test:1:1: The coefficients of the polynomial in 'x' are too large to find its roots"
+    );
+    // coefficients with too many divisors to try them all
+    let error =
+        solve_polynomial(&equation("200560490130x^4 + x + 200560490130 = 0"), "x").unwrap_err();
+    assert!(error.is_coefficient_too_large(), "{error}");
+    // exact roots of large numbers are still found
+    assert_eq!(
+        roots("x^2 = 1000000000000000000000000000000000000"),
+        "{-1000000000000000000, 1000000000000000000}"
+    );
+    assert_eq!(
+        roots("x^3 = 1000000000000000000000000000000"),
+        "{10000000000}"
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
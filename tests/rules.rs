@@ -0,0 +1,80 @@
+mod common;
+
+use common::*;
+use math::*;
+
+#[test]
+fn custom_rules_are_used() {
+    let mut rules = RuleSet::builtin();
+    rules.add(Rule::parse("pythagoras", "sin(?a)^2 + cos(?a)^2 -> 1").unwrap());
+    let source = expression("cos(x + 1)^2 + 3 + sin(x + 1)^2");
+    assert_eq!(
+        simplify_expression_fully_with(&source, &rules).unwrap(),
+        expression("4")
+    );
+    // the builtin rules do not know the identity
+    assert_ne!(simplify_expression_fully(&source).unwrap(), expression("4"));
+    assert_eq!(
+        simplify_with(&equation("sin(y)^2 + cos(y)^2 = x"), &rules).unwrap(),
+        equation("x = 1")
+    );
+}
+
+#[test]
+fn wildcards_match_in_any_order() {
+    let mut rules = RuleSet::new();
+    rules.add(Rule::parse("distribute", "?a*(?b + ?c) -> ?a*?b + ?a*?c").unwrap());
+    assert_eq!(
+        simplify_expression_fully_with(&expression("(x + 1)*y"), &rules).unwrap(),
+        expression("y*x + y*1")
+    );
+    // a wildcard used twice must match the same thing
+    rules.add(Rule::parse("double", "?a + ?a -> 2?a").unwrap());
+    assert_eq!(
+        simplify_expression_fully_with(&expression("x + y"), &rules).unwrap(),
+        expression("x + y")
+    );
+    assert_eq!(
+        simplify_expression_fully_with(&expression("y + x + y"), &rules).unwrap(),
+        expression("2y + x")
+    );
+}
+
+#[test]
+fn builtin_rules_can_be_removed() {
+    let mut rules = RuleSet::builtin();
+    assert!(rules.get("distribute").unwrap().is_builtin());
+    assert!(rules.remove("distribute").is_some());
+    assert!(rules.get("distribute").is_none());
+    assert_eq!(
+        simplify_expression_fully_with(&expression("2(x + 1)"), &rules)
+            .unwrap()
+            .to_sexpr(),
+        "(* 2 (+ 1 x))"
+    );
+    assert_eq!(
+        simplify_expression_fully(&expression("2(x + 1)"))
+            .unwrap()
+            .to_sexpr(),
+        "(+ 2 (* 2 x))"
+    );
+}
+
+#[test]
+fn invalid_rules_are_rejected() {
+    assert_eq!(
+        Rule::parse("arrow", "?a + 0").unwrap_err(),
+        RuleError::MissingArrow {
+            name: "arrow".to_string()
+        }
+    );
+    let error = Rule::parse("unbound", "?a*0 -> ?b").unwrap_err();
+    assert_eq!(error.as_unbound_wildcard().unwrap().0, "?b");
+    assert_eq!(
+        error.to_string(),
+        "unbound:1:9: Wildcard '?b' is not in the pattern"
+    );
+    assert!(Rule::parse("parse", "?a + -> 1")
+        .unwrap_err()
+        .is_parsing_error());
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::*;
+use math::*;
+
+fn saturated<C: CostFunction>(source: &str, cost: &C) -> String {
+    simplify_expression_saturated_with(
+        &expression(source),
+        &RuleSet::algebra(),
+        &SaturationLimits::default(),
+        cost,
+    )
+    .to_sexpr()
+}
+
+#[test]
+fn finds_forms_the_fast_path_misses() {
+    for (source, simplified) in [
+        ("x*y + x*z", "(* x (+ y z))"),
+        ("x*x*x", "(^ x 3)"),
+        ("x^2*x/x", "(^ x 2)"),
+        ("2(x + 1) - 2", "(* 2 x)"),
+    ] {
+        let source = expression(source);
+        assert_eq!(
+            simplify_expression_saturated(&source).to_sexpr(),
+            simplified,
+            "{source}"
+        );
+        assert_ne!(
+            simplify_expression_fully(&source).unwrap().to_sexpr(),
+            simplified,
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn the_cost_function_picks_the_result() {
+    assert_eq!(saturated("x/y + z/y", &NodeCount), "(/ (+ x z) y)");
+    assert_eq!(saturated("x/y + z/y", &Depth), "(/ (+ x z) y)");
+    assert_eq!(
+        saturated("x/y + z/y", &FewestFractions),
+        "(* (+ x z) (^ y -1))"
+    );
+
+    /// Powers cost as much as writing out the product
+    struct NoPowers;
+
+    impl CostFunction for NoPowers {
+        type Cost = usize;
+
+        fn cost(&self, node: &ENode, children: &[usize]) -> usize {
+            match node {
+                ENode::Power(_) => 100 + children.iter().sum::<usize>(),
+                _ => 1 + children.iter().sum::<usize>(),
+            }
+        }
+    }
+    assert_eq!(saturated("x*x*x", &NoPowers), "(* x x x)");
+}
+
+#[test]
+fn custom_rules_are_saturated() {
+    let mut rules = RuleSet::new();
+    rules.add(Rule::parse("pythagoras", "sin(?a)^2 + cos(?a)^2 -> 1").unwrap());
+    assert_eq!(
+        simplify_expression_saturated_with(
+            &expression("sin(x)^2 + cos(x)^2 + 1"),
+            &rules,
+            &SaturationLimits::default(),
+            &NodeCount,
+        ),
+        expression("2")
+    );
+}
+
+#[test]
+fn limits_stop_the_search() {
+    let source = expression("x*y + x*z");
+    let limits = SaturationLimits {
+        max_iterations: 0,
+        ..SaturationLimits::default()
+    };
+    assert_eq!(
+        simplify_expression_saturated_with(&source, &RuleSet::algebra(), &limits, &NodeCount),
+        source
+    );
+    // commutativity and associativity never run out of new sums, so only the limits end this
+    let source = expression("(a + b + c + d)*(e + f + g)");
+    let limits = SaturationLimits {
+        max_nodes: 500,
+        ..SaturationLimits::default()
+    };
+    let result =
+        simplify_expression_saturated_with(&source, &RuleSet::algebra(), &limits, &NodeCount);
+    assert_eq!(result, source);
+}
+
+#[test]
+fn large_powers_are_not_folded() {
+    let start = Instant::now();
+    assert_eq!(
+        simplify_expression_saturated(&expression("2^2000000000 + x")),
+        expression("2^2000000000 + x")
+    );
+    assert_eq!(
+        simplify_expression_saturated(&expression("2^10 + x")).to_sexpr(),
+        "(+ 1024 x)"
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn the_timeout_is_checked_while_searching() {
+    let source = expression("(a + b + c + d + e)*(f + g + h + i)*(j + k + l)");
+    let limits = SaturationLimits {
+        max_nodes: usize::MAX,
+        max_iterations: usize::MAX,
+        timeout: Duration::from_millis(100),
+    };
+    let start = Instant::now();
+    simplify_expression_saturated_with(&source, &RuleSet::algebra(), &limits, &NodeCount);
+    assert!(
+        start.elapsed() < Duration::from_secs(2),
+        "{:?}",
+        start.elapsed()
+    );
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use common::*;
+use math::*;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+#[test]
+fn equations_round_trip() {
+    for source in [
+        "x = 1",
+        "3x^2 - 2x + 1/3 = y",
+        "sqrt(x + 1) = max(a, b, 0.125)",
+        "(a + b)(a - b) = a^2 - b^2",
+    ] {
+        let equation = equation(source);
+        let json = to_json(&equation);
+        let read: Equation = from_json(&json).unwrap();
+        assert_eq!(read, equation, "{json}");
+        // equality ignores spans, so they are compared on their own
+        assert_eq!(read.span(), equation.span(), "{json}");
+    }
+}
+
+#[test]
+fn rationals_keep_their_precision() {
+    let value = BigRational::new(
+        "123456789012345678901234567891".parse::<BigInt>().unwrap(),
+        BigInt::from(7),
+    );
+    let atom = Atom::new(AtomKind::Number(value.clone()));
+    let json = to_json(&atom);
+    assert_eq!(
+        json,
+        r#"{"version":1,"value":{"kind":{"type":"number","value":{"num":"123456789012345678901234567891","den":"7"}}}}"#
+    );
+    let read: Atom = from_json(&json).unwrap();
+    assert_eq!(read.kind.as_number(), Some(&value));
+}
+
+#[test]
+fn tokens_round_trip() {
+    let mut lexer = Lexer::new("test", "f(x) := 2.5x");
+    let mut tokens = vec![];
+    loop {
+        let token = lexer.next_token().unwrap();
+        tokens.push(token.clone());
+        if token.kind == TokenKind::EOF {
+            break;
+        }
+    }
+    let json = to_json(&tokens);
+    assert!(json.contains(r#""kind":"open_parenthesis""#), "{json}");
+    assert!(json.contains(r#""kind":"eof""#), "{json}");
+    let read: Vec<Token> = from_json(&json).unwrap();
+    assert_eq!(read, tokens);
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let json = r#"{"version":2,"value":{"kind":{"type":"variable","value":"x"}}}"#;
+    assert_eq!(
+        from_json::<Atom>(json),
+        Err(JsonError::UnsupportedVersion { version: 2 })
+    );
+    let json = r#"{"version":1,"value":{"kind":{"type":"number","value":{"num":"1","den":"0"}}}}"#;
+    assert!(from_json::<Atom>(json).unwrap_err().is_invalid());
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use common::*;
+use math::*;
+
+#[test]
+fn printed_forms() {
+    for (source, expected) in [
+        ("2x + 3 = 7", "(= (+ (* 2 x) 3) 7)"),
+        ("x - 3 = 1/3", "(= (+ x (* 3 -1)) (/ 1 3))"),
+        ("2(x + 1) = (x)", "(= (* 2 (+ x 1)) (* (+ x)))"),
+        ("(2x)(3) = 2.5", "(= (* (* 2 x) (+ 3)) 5/2)"),
+        (
+            "sqrt(x + 1) = max(y^2, 0)",
+            "(= (sqrt (+ x 1)) (max (^ y 2) 0))",
+        ),
+    ] {
+        assert_eq!(equation(source).to_sexpr(), expected, "{source}");
+    }
+}
+
+#[test]
+fn equations_round_trip() {
+    for source in [
+        "2x + 3 = 7",
+        "(x) = ((x + 1))",
+        "(2x)(3) = (a)(b + c)",
+        "-(a + b)/(c - 2) = x^(1/2)",
+        "min(x, y) = max(1, abs(-x), 2.5)",
+    ] {
+        let equation = equation(source);
+        let sexpr = equation.to_sexpr();
+        assert_eq!(parse_sexpr_equation(&sexpr).unwrap(), equation, "{sexpr}");
+    }
+}
+
+#[test]
+fn simplifier_pairs() {
+    for (before, after) in [
+        ("(+ (* 2 x) (* 3 x))", "(* 5 x)"),
+        ("(* 1 y)", "y"),
+        ("(+ (* (+ x 1)) 2)", "(+ 3 x)"),
+        ("(^ x 1)", "x"),
+        ("(/ x 1)", "x"),
+        ("(+ (* x y) (* y x))", "(* 2 x y)"),
+        ("(* 2 (+ x 1))", "(+ 2 (* 2 x))"),
+        ("(sqrt 16)", "4"),
+    ] {
+        let expression = parse_sexpr_expression(before).unwrap();
+        let simplified = simplify_expression_fully(&expression).unwrap();
+        assert_eq!(
+            simplified,
+            parse_sexpr_expression(after).unwrap(),
+            "{before}"
+        );
+        assert_eq!(simplified.to_sexpr(), after, "{before}");
+    }
+}
+
+#[test]
+fn reader_errors() {
+    let error = parse_sexpr_expression("(+ x\n  (* 2 y)").unwrap_err();
+    assert!(error.is_unexpected_end());
+    assert_eq!(error.to_string(), "2:10: Unexpected end of input");
+
+    let error = parse_sexpr_expression("(+ x 1))").unwrap_err();
+    assert_eq!(error.to_string(), "1:8: Expected the end of input");
+
+    let error = parse_sexpr_expression("(^ x 1 2)").unwrap_err();
+    assert_eq!(error.to_string(), "1:1: '^' takes 2 arguments, but got 3");
+
+    assert!(parse_sexpr_expression("(+ x ((y)))")
+        .unwrap_err()
+        .is_expected_operator());
+    assert!(parse_sexpr_equation("(+ x 1)")
+        .unwrap_err()
+        .is_expected_equation());
+}
+
+#[test]
+fn invalid_symbols() {
+    let error = parse_sexpr_equation("(= 1/0 2x)").unwrap_err();
+    assert!(error.is_zero_denominator());
+    assert_eq!(
+        error.to_string(),
+        "1:4: The number '1/0' has a denominator of 0"
+    );
+
+    let error = parse_sexpr_equation("(= 1/2 2x)").unwrap_err();
+    assert!(error.is_invalid_symbol());
+    assert_eq!(error.to_string(), "1:8: '2x' is not a number or a name");
+
+    for source in ["x+1", "1/x", "(* 2 a.b)", "(2f x)", "(+ x _)", "1..2"] {
+        assert!(
+            parse_sexpr_expression(source)
+                .unwrap_err()
+                .is_invalid_symbol(),
+            "{source}"
+        );
+    }
+    assert_eq!(
+        parse_sexpr_expression("(* 2 x1 (f y))").unwrap().to_sexpr(),
+        "(* 2 x1 (f y))"
+    );
+}
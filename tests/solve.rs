@@ -0,0 +1,54 @@
+mod common;
+
+use common::*;
+use math::*;
+
+fn solved(source: &str, variable: &str) -> String {
+    solve_for(&equation(source), variable).unwrap().to_string()
+}
+
+#[test]
+fn unique_solutions() {
+    for (source, variable, expected) in [
+        ("2x + 3 = 7", "x", "2"),
+        ("x/4 = 1/2", "x", "2"),
+        ("3(x - 1) = x + 5", "x", "4"),
+        ("2x + y = 4", "x", "-0.5y + 2"),
+    ] {
+        assert_eq!(solved(source, variable), expected, "{source}");
+    }
+    assert_eq!(
+        solve_for(&equation("a*x = b"), "x").unwrap(),
+        Solution::Unique(expression("b/a"))
+    );
+}
+
+#[test]
+fn no_solution_and_identities() {
+    assert_eq!(
+        solve_for(&equation("x + 1 = x + 2"), "x").unwrap(),
+        Solution::NoSolution
+    );
+    assert_eq!(
+        solve_for(&equation("2(x + 1) = 2x + 2"), "x").unwrap(),
+        Solution::AllValues
+    );
+    assert_eq!(
+        solved("x - x + y = 0", "x"),
+        "all values if y = 0, otherwise no solution"
+    );
+}
+
+#[test]
+fn errors() {
+    let error = solve_for(&equation("x^2 = 4"), "x").unwrap_err();
+    assert!(error.is_non_linear(), "{error}");
+    let error = solve_for(&equation("x*x = 4"), "x").unwrap_err();
+    assert!(error.is_non_linear(), "{error}");
+    let error = solve_for(&equation("sqrt(x) = 2"), "x").unwrap_err();
+    assert!(error.is_non_linear(), "{error}");
+    assert_eq!(
+        solve_for(&equation("y = 1"), "x").unwrap_err().to_string(),
+        "test:1:1: 'x' does not appear in the equation"
+    );
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::*;
+use math::*;
+
+#[test]
+fn steps_explain_the_solution() {
+    let (result, steps) = simplify_with_trace(&equation("2x + 3x + 3 = 13")).unwrap();
+    assert_eq!(result, equation("x = 2"));
+    let steps = steps
+        .iter()
+        .map(|step| (step.rule.as_str(), step.description.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        steps,
+        [
+            ("combine_like_terms", "combine like terms 2x + 3x"),
+            ("subtract_from_both_sides", "subtract 3 from both sides"),
+            ("divide_both_sides", "divide both sides by 5"),
+            ("simplify", "simplify both sides"),
+        ]
+    );
+}
+
+#[test]
+fn negative_terms_are_added() {
+    let (_, steps) = simplify_with_trace(&equation("x - 3 = 2x")).unwrap();
+    let descriptions = steps
+        .iter()
+        .map(|step| step.description.as_str())
+        .collect::<Vec<_>>();
+    assert!(descriptions.contains(&"subtract 2x from both sides"));
+    assert!(descriptions.contains(&"add 3 to both sides"));
+}
+
+#[test]
+fn steps_are_a_chain() {
+    for source in ["x - 3 = 2x", "(x + 1)/3 = 2", "x/(y + 1) = 2", "x = 1"] {
+        let equation = equation(source);
+        let (result, steps) = simplify_with_trace(&equation).unwrap();
+        assert_eq!(result, simplify(&equation).unwrap(), "{source}");
+        let mut current = &equation;
+        for step in &steps {
+            assert_eq!(&step.before, current, "{source}");
+            assert_ne!(step.before, step.after, "{source}");
+            current = &step.after;
+        }
+        assert_eq!(current, &result, "{source}");
+    }
+}
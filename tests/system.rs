@@ -0,0 +1,40 @@
+mod common;
+
+use common::*;
+use math::*;
+
+fn solved(source: &str) -> String {
+    solve_system(&system(source)).unwrap().to_string()
+}
+
+#[test]
+fn unique_solutions() {
+    for (source, expected) in [
+        ("x + y = 3, x - y = 1", "x = 2, y = 1"),
+        ("2*x + 3*y = 1\n4*x - y = 9", "x = 2, y = -1"),
+        (
+            "x + y + z = 6, x - y = 0, z = 2*x",
+            "x = 1.5, y = 1.5, z = 3",
+        ),
+    ] {
+        assert_eq!(solved(source), expected, "{source}");
+    }
+}
+
+#[test]
+fn inconsistent_and_parametric_systems() {
+    assert_eq!(
+        solve_system(&system("x + y = 1, x + y = 2")).unwrap(),
+        SystemSolution::Inconsistent
+    );
+    assert_eq!(solved("x + y = 1, 2*x + 2*y = 2"), "x = 1 - y for any y");
+    assert_eq!(solved("1 = 1, 2 = 2"), "always true");
+    assert_eq!(solved("1 = 1, 2 = 3"), "no solution");
+}
+
+#[test]
+fn errors() {
+    let error = solve_system(&system("x*y = 1, x = 2")).unwrap_err();
+    assert!(error.is_non_linear(), "{error}");
+    assert!(parse_system(&mut Lexer::new("test", "x = 1 y = 2")).is_err());
+}